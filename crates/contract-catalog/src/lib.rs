@@ -1,15 +1,20 @@
 //! Search catalog contract for the Freenet search engine.
 //!
 //! Maintains a CRDT-based catalog of indexed web contracts with grow-only maps,
-//! max-wins scoring, attestation dedup by pubkey, and deterministic finalization.
-//! Uses bloom filters (k=7, SHA-256) for efficient state synchronization via
-//! the `summarize_state` / `get_state_delta` protocol.
+//! attestation dedup by pubkey, and deterministic finalization that derives
+//! contributor scores from the entries, so reputation can fall as well as rise.
+//! Uses versioned summaries (IBLTs by default, bloom filters on request)
+//! for state synchronization via the `summarize_state` / `get_state_delta` protocol,
+//! answering with whole entries that merge like full states. Signed key rotations
+//! form a grow-only set; finalization credits a rotated key's attestations to its
+//! successor. Each contributor key has a budget of attestations per time window.
 
 use freenet_stdlib::prelude::*;
-use search_common::bloom::BloomFilter;
-use search_common::hashing::metadata_hash;
+use search_common::antiflood;
+use search_common::hashing::{attestation_message, rotation_message, snippet_hash, variant_hash};
+use search_common::sync::{self, SummaryKind, SyncItem};
 use search_common::types::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub struct Contract;
 
@@ -19,27 +24,244 @@ fn cbor_serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
     buf
 }
 
-fn bloom_key(entry: &CatalogEntry) -> Vec<u8> {
-    let (best_hash, best_weight) = entry
-        .hash_variants
-        .iter()
-        .max_by_key(|(_, v)| v.total_weight)
-        .map(|(h, v)| (*h, v.total_weight))
-        .unwrap_or(([0u8; 32], 0));
-    let mut key = Vec::new();
-    key.extend_from_slice(entry.contract_key.as_bytes());
-    key.extend_from_slice(&best_hash);
-    key.extend_from_slice(&best_weight.to_be_bytes());
-    key
+/// An entry is keyed by contract key; its content is the whole entry, so any
+/// new variant or attestation makes it differ.
+fn sync_item(entry: &CatalogEntry) -> SyncItem {
+    SyncItem {
+        key: entry.contract_key.as_bytes().to_vec(),
+        content: cbor_serialize(entry),
+    }
+}
+
+/// A rotation is keyed by its whole encoding behind a 0xff byte, which no UTF-8
+/// contract key starts with.
+fn rotation_sync_item(rotation: &KeyRotation) -> SyncItem {
+    let mut key = vec![0xff];
+    key.extend_from_slice(&cbor_serialize(rotation));
+    SyncItem {
+        key,
+        content: Vec::new(),
+    }
+}
+
+/// Metadata size limits, in bytes.
+const MAX_TITLE_LEN: usize = 256;
+const MAX_DESCRIPTION_LEN: usize = 1024;
+/// Mini snippets are at most 300 chars.
+const MAX_MINI_SNIPPET_LEN: usize = 1200;
+
+/// Milliseconds per day; attestation timestamps are Unix milliseconds.
+const MS_PER_DAY: u64 = 86_400_000;
+const MS_PER_MINUTE: u64 = 60_000;
+
+/// An entry expires when its newest attestation is more than `ttl_days` older than the
+/// catalog's reference time. Contracts have no trusted clock, so time is measured
+/// relative to state contents, which keeps expiry deterministic across merges.
+/// A TTL of 0 disables expiry.
+fn is_expired(entry: &CatalogEntry, reference: u64, ttl_days: u16) -> bool {
+    ttl_days != 0 && reference.saturating_sub(entry.last_seen) > ttl_days as u64 * MS_PER_DAY
+}
+
+/// Every attestation across `entries`.
+fn attestations<'a>(
+    entries: impl IntoIterator<Item = &'a CatalogEntry>,
+) -> impl Iterator<Item = &'a Attestation> {
+    entries
+        .into_iter()
+        .flat_map(|entry| entry.hash_variants.values())
+        .flat_map(|variant| &variant.attestations)
+}
+
+/// The catalog's reference time: the newest time at least `threshold` contributor
+/// keys have attested at or after, taking each key's newest attestation, or the
+/// oldest of those when fewer keys have attested. Keys short of a quorum can't
+/// move it, so one future-dated attestation doesn't age the whole catalog.
+fn reference_time<'a>(
+    atts: impl IntoIterator<Item = &'a Attestation>,
+    threshold: u32,
+) -> Option<u64> {
+    let mut newest: BTreeMap<[u8; 32], u64> = BTreeMap::new();
+    for att in atts {
+        let t = newest.entry(att.contributor_pubkey).or_insert(0);
+        *t = (*t).max(att.token_created_at);
+    }
+    let mut times: Vec<u64> = newest.into_values().collect();
+    times.sort_unstable_by(|a, b| b.cmp(a));
+    let index = (threshold.max(1) as usize)
+        .min(times.len())
+        .checked_sub(1)?;
+    Some(times[index])
+}
+
+/// How far an attestation may be dated from the reference time, or `None`
+/// when the bound is disabled.
+fn clock_skew(params: &CatalogParameters) -> Option<u64> {
+    (params.max_clock_skew_mins != 0).then(|| params.max_clock_skew_mins as u64 * MS_PER_MINUTE)
+}
+
+/// Whether an attestation is dated no further past `reference` than the clock
+/// skew allows. With no reference yet, any date is accepted.
+fn not_ahead(att: &Attestation, reference: Option<u64>, params: &CatalogParameters) -> bool {
+    match (clock_skew(params), reference) {
+        (Some(skew), Some(r)) => att.token_created_at <= r.saturating_add(skew),
+        _ => true,
+    }
+}
+
+/// Whether an attestation is dated no further before `reference` than the clock
+/// skew allows, so a new key can't backdate its way past the minimum identity age
+/// nor a contributor into budget windows long gone.
+fn not_behind(att: &Attestation, reference: Option<u64>, params: &CatalogParameters) -> bool {
+    match (clock_skew(params), reference) {
+        (Some(skew), Some(r)) => att.token_created_at >= r.saturating_sub(skew),
+        _ => true,
+    }
+}
+
+/// Whether `local` already holds `att`, or an attestation superseding it, from
+/// the same contributor under the same entry and variant.
+fn holds(local: &CatalogState, key: &str, hash: &[u8; 32], att: &Attestation) -> bool {
+    local
+        .entries
+        .get(key)
+        .and_then(|entry| entry.hash_variants.get(hash))
+        .is_some_and(|variant| {
+            variant
+                .attestations
+                .iter()
+                .any(|a| a.contributor_pubkey == att.contributor_pubkey && !supersedes(att, a))
+        })
+}
+
+/// `incoming` without the attestations `local` doesn't hold yet that are dated
+/// further before its reference time than the clock skew allows, so neither a
+/// new key's age nor a contributor's budget windows reach back in time by way of
+/// a peer. The rest merges as usual, so a peer's state is never refused for them
+/// and a node that fell behind still catches up.
+fn without_backdated(
+    local: &CatalogState,
+    incoming: &BTreeMap<String, CatalogEntry>,
+    params: &CatalogParameters,
+) -> BTreeMap<String, CatalogEntry> {
+    let reference = reference_time(
+        attestations(local.entries.values()),
+        params.confirmation_weight_threshold,
+    );
+    let mut entries = incoming.clone();
+    for (key, entry) in entries.iter_mut() {
+        for (hash, variant) in entry.hash_variants.iter_mut() {
+            variant
+                .attestations
+                .retain(|a| holds(local, key, hash, a) || not_behind(a, reference, params));
+        }
+    }
+    entries
+}
+
+/// Whether `new` replaces `old` for the same contributor: the latest attestation wins,
+/// with ties broken by nonce and signature so merges pick the same one in any order.
+fn supersedes(new: &Attestation, old: &Attestation) -> bool {
+    (
+        new.token_created_at,
+        &new.antiflood_token.nonce,
+        &new.signature,
+    ) > (
+        old.token_created_at,
+        &old.antiflood_token.nonce,
+        &old.signature,
+    )
+}
+
+/// Who each key's attestations count for once rotations apply.
+struct Identities {
+    /// Old key -> (its only successor, earliest rotation time).
+    successors: BTreeMap<[u8; 32], ([u8; 32], u64)>,
+    /// Keys rotated to more than one successor.
+    revoked: BTreeSet<[u8; 32]>,
+    /// Identities first seen too recently to count toward quorum.
+    young: BTreeSet<[u8; 32]>,
+}
+
+impl Identities {
+    fn new(rotations: &[KeyRotation]) -> Self {
+        let mut targets: BTreeMap<[u8; 32], BTreeMap<[u8; 32], u64>> = BTreeMap::new();
+        for rotation in rotations {
+            let rotated_at = targets
+                .entry(rotation.old_pubkey)
+                .or_default()
+                .entry(rotation.new_pubkey)
+                .or_insert(u64::MAX);
+            *rotated_at = (*rotated_at).min(rotation.rotated_at);
+        }
+
+        let mut identities = Identities {
+            successors: BTreeMap::new(),
+            revoked: BTreeSet::new(),
+            young: BTreeSet::new(),
+        };
+        for (old, news) in targets {
+            // Two successors means the key is in two hands; trust neither
+            if news.len() == 1 {
+                identities
+                    .successors
+                    .extend(news.into_iter().map(|n| (old, n)));
+            } else {
+                identities.revoked.insert(old);
+            }
+        }
+        identities
+    }
+
+    /// The identity an attestation counts for: the end of its key's rotation
+    /// chain, or `None` if a key along the chain was revoked or the attestation
+    /// was made after its key rotated away.
+    fn resolve(&self, att: &Attestation) -> Option<[u8; 32]> {
+        let mut key = att.contributor_pubkey;
+        if let Some((_, rotated_at)) = self.successors.get(&key) {
+            if att.token_created_at > *rotated_at {
+                return None;
+            }
+        }
+        // A chain longer than the number of rotations is a cycle
+        for _ in 0..=self.successors.len() {
+            if self.revoked.contains(&key) {
+                return None;
+            }
+            match self.successors.get(&key) {
+                Some((next, _)) => key = *next,
+                None => return Some(key),
+            }
+        }
+        None
+    }
+
+    /// Distinct identities behind a variant's attestations.
+    fn of_variant(&self, variant: &HashVariant) -> BTreeSet<[u8; 32]> {
+        variant
+            .attestations
+            .iter()
+            .filter_map(|a| self.resolve(a))
+            .collect()
+    }
+
+    /// Distinct identities behind a variant that are old enough to count
+    /// toward quorum.
+    fn counted(&self, variant: &HashVariant) -> BTreeSet<[u8; 32]> {
+        let mut ids = self.of_variant(variant);
+        ids.retain(|pk| !self.young.contains(pk));
+        ids
+    }
 }
 
 /// Derive status using attestation COUNT (not total_weight) for CRDT commutativity.
-/// This makes status derivation independent of trust-weighted totals.
-fn derive_status(entry: &CatalogEntry, threshold: u32) -> Status {
+/// This makes status derivation independent of trust-weighted totals. A contributor
+/// attesting under both an old and a rotated key counts once, and identities younger
+/// than the minimum age not at all.
+fn derive_status(entry: &CatalogEntry, threshold: u32, identities: &Identities) -> Status {
     let mut counts: Vec<u32> = entry
         .hash_variants
         .values()
-        .map(|v| v.attestations.len() as u32)
+        .map(|v| identities.counted(v).len() as u32)
         .collect();
     counts.sort_unstable_by(|a, b| b.cmp(a));
 
@@ -57,8 +279,8 @@ fn derive_status(entry: &CatalogEntry, threshold: u32) -> Status {
     }
 }
 
-fn merge_catalog_states(a: &mut CatalogState, b: &CatalogState) {
-    for (key, b_entry) in &b.entries {
+fn merge_catalog_states(a: &mut CatalogState, b: &CatalogState, params: &CatalogParameters) {
+    for (key, b_entry) in &without_backdated(a, &b.entries, params) {
         let a_entry = a
             .entries
             .entry(key.clone())
@@ -80,22 +302,31 @@ fn merge_catalog_states(a: &mut CatalogState, b: &CatalogState) {
                     title: b_variant.title.clone(),
                     description: b_variant.description.clone(),
                     mini_snippet: b_variant.mini_snippet.clone(),
+                    snippet_hash: b_variant.snippet_hash,
+                    snippet: String::new(),
                     attestations: Vec::new(),
                     total_weight: 0,
                 });
+            // Both sides' snippets match the variant's snippet hash
+            if a_variant.snippet.is_empty() {
+                a_variant.snippet = b_variant.snippet.clone();
+            }
 
             for b_att in &b_variant.attestations {
-                if !a_variant
+                match a_variant
                     .attestations
-                    .iter()
-                    .any(|a| a.contributor_pubkey == b_att.contributor_pubkey)
+                    .iter_mut()
+                    .find(|a| a.contributor_pubkey == b_att.contributor_pubkey)
                 {
-                    a_variant.attestations.push(b_att.clone());
+                    Some(existing) => {
+                        if supersedes(b_att, existing) {
+                            *existing = b_att.clone();
+                        }
+                    }
+                    None => a_variant.attestations.push(b_att.clone()),
                 }
             }
-            a_variant
-                .attestations
-                .sort_by(|x, y| x.contributor_pubkey.cmp(&y.contributor_pubkey));
+            a_variant.attestations.sort_by_key(|x| x.contributor_pubkey);
             a_variant.total_weight = a_variant.attestations.iter().map(|a| a.weight).sum();
         }
 
@@ -109,33 +340,285 @@ fn merge_catalog_states(a: &mut CatalogState, b: &CatalogState) {
         a_entry.last_seen = a_entry.last_seen.max(b_entry.last_seen);
     }
 
-    for (pk, b_score) in &b.contributors {
-        let a_score = a
-            .contributors
+    // Scores are derived from the entries in finalization; only the keys merge
+    for pk in b.contributors.keys() {
+        a.contributors
             .entry(*pk)
             .or_insert_with(|| ContributorScore {
                 pubkey: *pk,
                 trust_score: 0,
                 total_contributions: 0,
+                first_seen: 0,
             });
-        a_score.trust_score = a_score.trust_score.max(b_score.trust_score);
-        a_score.total_contributions = a_score.total_contributions.max(b_score.total_contributions);
     }
+
+    merge_rotations(&mut a.rotations, &b.rotations);
 }
 
-fn validate_delta(delta: &CatalogDelta) -> Result<(), ContractError> {
-    if delta.contract_key.is_empty() {
+fn merge_rotations(a: &mut Vec<KeyRotation>, b: &[KeyRotation]) {
+    a.extend_from_slice(b);
+    a.sort();
+    a.dedup();
+}
+
+/// Verify that a rotation names a different, nonzero key and is signed by the old key.
+fn verify_rotation(rotation: &KeyRotation) -> bool {
+    let Ok(signature) = <[u8; 64]>::try_from(rotation.signature.as_slice()) else {
+        return false;
+    };
+    let message = rotation_message(
+        &rotation.old_pubkey,
+        &rotation.new_pubkey,
+        rotation.rotated_at,
+    );
+    rotation.old_pubkey != rotation.new_pubkey
+        && rotation.new_pubkey != [0u8; 32]
+        && rotation.rotated_at != 0
+        && delegate_identity::verify_signature(&rotation.old_pubkey, &message, &signature)
+}
+
+/// Recompute the attestation's proof-of-work over its bound payload
+/// (contributor pubkey, contract key, metadata hash, token_created_at).
+fn verify_attestation_pow(
+    contract_key: &str,
+    hash: &[u8; 32],
+    att: &Attestation,
+    min_difficulty: u8,
+) -> bool {
+    let challenge = antiflood::attestation_challenge(
+        &att.contributor_pubkey,
+        contract_key,
+        hash,
+        att.token_created_at,
+    );
+    antiflood::verify_token(&att.antiflood_token, &challenge, min_difficulty)
+}
+
+/// Verify the contributor's ed25519 signature over the attestation message
+/// (contract key, metadata hash, token_created_at, PoW nonce).
+fn verify_attestation_signature(contract_key: &str, hash: &[u8; 32], att: &Attestation) -> bool {
+    let Ok(signature) = <[u8; 64]>::try_from(att.signature.as_slice()) else {
+        return false;
+    };
+    let message = attestation_message(
+        contract_key,
+        hash,
+        att.token_created_at,
+        &att.antiflood_token.nonce,
+    );
+    delegate_identity::verify_signature(&att.contributor_pubkey, &message, &signature)
+}
+
+/// The attestation budget window a timestamp falls in. Fresh attestations are
+/// dated within the clock skew and merges drop backdated ones, so a contributor
+/// only reaches the windows around the catalog's reference time.
+fn budget_window(params: &CatalogParameters, token_created_at: u64) -> u64 {
+    token_created_at / (params.attestation_window_mins.max(1) as u64 * MS_PER_MINUTE)
+}
+
+/// Whether any contributor key has more attestations in one budget window
+/// across `entries` than the parameters allow.
+fn over_budget<'a>(
+    entries: impl IntoIterator<Item = &'a CatalogEntry>,
+    params: &CatalogParameters,
+) -> bool {
+    if params.max_attestations_per_window == 0 {
+        return false;
+    }
+    let mut used: BTreeMap<([u8; 32], u64), u32> = BTreeMap::new();
+    for entry in entries {
+        for att in entry.hash_variants.values().flat_map(|v| &v.attestations) {
+            let window = budget_window(params, att.token_created_at);
+            *used.entry((att.contributor_pubkey, window)).or_insert(0) += 1;
+        }
+    }
+    used.values()
+        .any(|n| *n > params.max_attestations_per_window)
+}
+
+/// Whether the delta's contributor has budget left in its window, not counting
+/// an attestation of theirs the delta would replace.
+fn within_budget(state: &CatalogState, delta: &CatalogDelta, params: &CatalogParameters) -> bool {
+    if params.max_attestations_per_window == 0 {
+        return true;
+    }
+    let pubkey = delta.attestation.contributor_pubkey;
+    let window = budget_window(params, delta.attestation.token_created_at);
+    let used = state
+        .entries
+        .values()
+        .flat_map(|entry| {
+            entry
+                .hash_variants
+                .iter()
+                .filter(move |(hash, _)| {
+                    entry.contract_key != delta.contract_key || **hash != delta.metadata_hash
+                })
+                .flat_map(|(_, v)| &v.attestations)
+        })
+        .filter(|a| {
+            a.contributor_pubkey == pubkey && budget_window(params, a.token_created_at) == window
+        })
+        .count();
+    (used as u64) < params.max_attestations_per_window as u64
+}
+
+/// Drop attestations beyond a contributor key's budget for a window. States merged
+/// from peers can each be within budget and exceed it together; keeping the
+/// earliest attestations (ties broken by contract key and variant) keeps the same
+/// ones in any merge order. Entries and variants keep their metadata.
+fn enforce_budget(state: &mut CatalogState, params: &CatalogParameters) {
+    if params.max_attestations_per_window == 0 {
+        return;
+    }
+    type Usage = Vec<(u64, String, [u8; 32])>;
+    let mut used: BTreeMap<([u8; 32], u64), Usage> = BTreeMap::new();
+    for (key, entry) in &state.entries {
+        for (hash, variant) in &entry.hash_variants {
+            for att in &variant.attestations {
+                let window = budget_window(params, att.token_created_at);
+                used.entry((att.contributor_pubkey, window))
+                    .or_default()
+                    .push((att.token_created_at, key.clone(), *hash));
+            }
+        }
+    }
+
+    let mut dropped: BTreeSet<(String, [u8; 32], [u8; 32])> = BTreeSet::new();
+    for ((pubkey, _), mut atts) in used {
+        atts.sort_unstable();
+        for (_, key, hash) in atts
+            .into_iter()
+            .skip(params.max_attestations_per_window as usize)
+        {
+            dropped.insert((key, hash, pubkey));
+        }
+    }
+    for (key, hash, pubkey) in dropped {
+        if let Some(variant) = state
+            .entries
+            .get_mut(&key)
+            .and_then(|e| e.hash_variants.get_mut(&hash))
+        {
+            variant
+                .attestations
+                .retain(|a| a.contributor_pubkey != pubkey);
+        }
+    }
+}
+
+/// Reject full-state merges carrying snippets that don't match their variant,
+/// attestations or rotations whose PoW or signature doesn't verify, or
+/// attestations beyond a contributor's budget.
+fn validate_incoming_state(
+    state: &CatalogState,
+    params: &CatalogParameters,
+) -> Result<(), ContractError> {
+    let min_difficulty = params.min_pow_difficulty;
+    if !state.rotations.iter().all(verify_rotation) || over_budget(state.entries.values(), params) {
         return Err(ContractError::InvalidUpdate);
     }
-    if delta.title.len() > 256 {
+    for entry in state.entries.values() {
+        for (hash, variant) in &entry.hash_variants {
+            if !variant_matches(hash, variant) {
+                return Err(ContractError::InvalidUpdate);
+            }
+            for att in &variant.attestations {
+                if !verify_attestation_pow(&entry.contract_key, hash, att, min_difficulty)
+                    || !verify_attestation_signature(&entry.contract_key, hash, att)
+                {
+                    return Err(ContractError::InvalidUpdate);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn within_limits(title: &str, description: &str, mini_snippet: &str, snippet: &str) -> bool {
+    title.len() <= MAX_TITLE_LEN
+        && description.len() <= MAX_DESCRIPTION_LEN
+        && mini_snippet.len() <= MAX_MINI_SNIPPET_LEN
+        && snippet.len() <= MAX_SNIPPET_LEN
+}
+
+/// Whether a variant's metadata hashes to its key and its snippet, if it has
+/// one yet, to its snippet hash.
+fn variant_matches(hash: &[u8; 32], variant: &HashVariant) -> bool {
+    let expected = variant_hash(
+        &variant.title,
+        &variant.description,
+        &variant.mini_snippet,
+        &variant.snippet_hash,
+    );
+    *hash == expected
+        && (variant.snippet.is_empty() || snippet_hash(&variant.snippet) == variant.snippet_hash)
+}
+
+/// Hold sync deltas to what `validate_delta` enforces for each variant: keys that
+/// match their entry, metadata within limits and under its hash, and attestations
+/// whose PoW and signature verify and fitting their contributors' budgets, plus
+/// rotations signed by their old key. Dates are bounded only for fresh
+/// submissions; merges drop backdated attestations instead.
+fn validate_sync_delta(
+    delta: &CatalogSyncDelta,
+    params: &CatalogParameters,
+) -> Result<(), ContractError> {
+    let min_difficulty = params.min_pow_difficulty;
+    if !delta.rotations.iter().all(verify_rotation) || over_budget(delta.entries.values(), params) {
         return Err(ContractError::InvalidUpdate);
     }
-    if delta.description.len() > 1024 {
+    for (key, entry) in &delta.entries {
+        if key.is_empty() || *key != entry.contract_key {
+            return Err(ContractError::InvalidUpdate);
+        }
+        for (hash, variant) in &entry.hash_variants {
+            if !within_limits(
+                &variant.title,
+                &variant.description,
+                &variant.mini_snippet,
+                &variant.snippet,
+            ) || !variant_matches(hash, variant)
+            {
+                return Err(ContractError::InvalidUpdate);
+            }
+            for att in &variant.attestations {
+                if !verify_attestation_pow(key, hash, att, min_difficulty)
+                    || !verify_attestation_signature(key, hash, att)
+                {
+                    return Err(ContractError::InvalidUpdate);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The full-snippet hash a delta attests to: computed from `snippet` when the
+/// contributor sent it (and then matching any `snippet_hash` alongside), else the
+/// `snippet_hash` sent in its place.
+fn delta_snippet_hash(delta: &CatalogDelta) -> Option<[u8; 32]> {
+    if delta.snippet.is_empty() {
+        return Some(delta.snippet_hash);
+    }
+    let hash = snippet_hash(&delta.snippet);
+    (delta.snippet_hash == [0u8; 32] || delta.snippet_hash == hash).then_some(hash)
+}
+
+fn validate_delta(
+    delta: &CatalogDelta,
+    params: &CatalogParameters,
+    state: &CatalogState,
+) -> Result<(), ContractError> {
+    if delta.contract_key.is_empty() {
         return Err(ContractError::InvalidUpdate);
     }
-    if delta.attestation.antiflood_token.nonce.is_empty()
-        || delta.attestation.antiflood_token.difficulty == 0
-    {
+    if !within_limits(
+        &delta.title,
+        &delta.description,
+        &delta.mini_snippet,
+        &delta.snippet,
+    ) {
         return Err(ContractError::InvalidUpdate);
     }
     if delta.attestation.contributor_pubkey == [0u8; 32] {
@@ -144,10 +627,43 @@ fn validate_delta(delta: &CatalogDelta) -> Result<(), ContractError> {
     if delta.attestation.token_created_at == 0 {
         return Err(ContractError::InvalidUpdate);
     }
-    let expected_hash = metadata_hash(&delta.title, &delta.description, &delta.snippet);
+    let reference = reference_time(
+        attestations(state.entries.values()),
+        params.confirmation_weight_threshold,
+    );
+    if !not_ahead(&delta.attestation, reference, params)
+        || !not_behind(&delta.attestation, reference, params)
+    {
+        return Err(ContractError::InvalidUpdate);
+    }
+    let snippet_hash = delta_snippet_hash(delta).ok_or(ContractError::InvalidUpdate)?;
+    let expected_hash = variant_hash(
+        &delta.title,
+        &delta.description,
+        &delta.mini_snippet,
+        &snippet_hash,
+    );
     if delta.metadata_hash != expected_hash {
         return Err(ContractError::InvalidUpdate);
     }
+    if !verify_attestation_pow(
+        &delta.contract_key,
+        &delta.metadata_hash,
+        &delta.attestation,
+        params.min_pow_difficulty,
+    ) {
+        return Err(ContractError::InvalidUpdate);
+    }
+    if !verify_attestation_signature(
+        &delta.contract_key,
+        &delta.metadata_hash,
+        &delta.attestation,
+    ) {
+        return Err(ContractError::InvalidUpdate);
+    }
+    if !within_budget(state, delta, params) {
+        return Err(ContractError::InvalidUpdate);
+    }
     Ok(())
 }
 
@@ -179,25 +695,38 @@ fn apply_delta_to_state(state: &mut CatalogState, delta: &CatalogDelta) {
             title: String::new(),
             description: String::new(),
             mini_snippet: String::new(),
+            snippet_hash: [0u8; 32],
+            snippet: String::new(),
             attestations: Vec::new(),
             total_weight: 0,
         });
 
     variant.title = delta.title.clone();
     variant.description = delta.description.clone();
-    variant.mini_snippet = delta.snippet.clone();
+    variant.mini_snippet = delta.mini_snippet.clone();
+    // Checked against the metadata hash by validate_delta
+    variant.snippet_hash = delta_snippet_hash(delta).unwrap_or(delta.snippet_hash);
+    if !delta.snippet.is_empty() {
+        variant.snippet = delta.snippet.clone();
+    }
 
-    if !variant
+    let mut attestation = delta.attestation.clone();
+    attestation.weight = weight;
+    // Re-attestation by the same contributor refreshes their attestation
+    match variant
         .attestations
-        .iter()
-        .any(|a| a.contributor_pubkey == delta.attestation.contributor_pubkey)
+        .iter_mut()
+        .find(|a| a.contributor_pubkey == attestation.contributor_pubkey)
     {
-        let mut attestation = delta.attestation.clone();
-        attestation.weight = weight;
-        variant.attestations.push(attestation);
-        variant
-            .attestations
-            .sort_by(|a, b| a.contributor_pubkey.cmp(&b.contributor_pubkey));
+        Some(existing) => {
+            if supersedes(&attestation, existing) {
+                *existing = attestation;
+            }
+        }
+        None => {
+            variant.attestations.push(attestation);
+            variant.attestations.sort_by_key(|a| a.contributor_pubkey);
+        }
     }
 
     variant.total_weight = variant.attestations.iter().map(|a| a.weight).sum();
@@ -212,24 +741,40 @@ fn apply_delta_to_state(state: &mut CatalogState, delta: &CatalogDelta) {
     entry.last_seen = entry.last_seen.max(delta.attestation.token_created_at);
 }
 
-/// Count how many entries each contributor has helped confirm.
-/// Returns a map: pubkey -> number of confirmed entries they attested to.
-fn compute_trust_from_entries(state: &CatalogState, threshold: u32) -> BTreeMap<[u8; 32], u32> {
-    let mut trust: BTreeMap<[u8; 32], u32> = BTreeMap::new();
+/// Net reputation of each contributor identity over the decided (confirmed or
+/// disputed) entries: +1 for each whose winning variant it attested, -1 for each
+/// losing variant it attested. Entries whose top variants tie have no winner and
+/// count for nobody, and identities too young for quorum neither gain nor lose.
+/// Derived from the entries alone, so it merges commutatively.
+fn compute_trust_from_entries(
+    state: &CatalogState,
+    threshold: u32,
+    identities: &Identities,
+) -> BTreeMap<[u8; 32], i64> {
+    let mut trust: BTreeMap<[u8; 32], i64> = BTreeMap::new();
 
     for entry in state.entries.values() {
-        let status = derive_status(entry, threshold);
-        if status == Status::Confirmed || status == Status::Disputed {
-            // Only count attestors of the winning (best count) variant
-            if let Some((_, best_variant)) = entry
-                .hash_variants
-                .iter()
-                .max_by_key(|(_, v)| v.attestations.len())
-            {
-                for att in &best_variant.attestations {
-                    *trust.entry(att.contributor_pubkey).or_insert(0) += 1;
-                }
-            }
+        let status = derive_status(entry, threshold, identities);
+        if status != Status::Confirmed && status != Status::Disputed {
+            continue;
+        }
+        let mut supporters: Vec<BTreeSet<[u8; 32]>> = entry
+            .hash_variants
+            .values()
+            .map(|v| identities.counted(v))
+            .collect();
+        supporters.sort_by_key(|ids| std::cmp::Reverse(ids.len()));
+        let Some((winner, losers)) = supporters.split_first() else {
+            continue;
+        };
+        if losers.first().is_some_and(|l| l.len() == winner.len()) {
+            continue;
+        }
+        for pk in winner {
+            *trust.entry(*pk).or_insert(0) += 1;
+        }
+        for pk in losers.iter().flatten() {
+            *trust.entry(*pk).or_insert(0) -= 1;
         }
     }
 
@@ -238,79 +783,122 @@ fn compute_trust_from_entries(state: &CatalogState, threshold: u32) -> BTreeMap<
 
 /// Recompute contributor scores, attestation weights, total_weights, and status
 /// deterministically from the current state. This is the CRDT finalization step.
-fn finalize_state(state: &mut CatalogState, threshold: u32) {
-    // Step 1: Ensure every attestor appears in the contributors table.
-    // Count total attestations per pubkey across all entries/variants.
+fn finalize_state(state: &mut CatalogState, params: &CatalogParameters) {
+    enforce_budget(state, params);
+    let threshold = params.confirmation_weight_threshold;
+    let mut identities = Identities::new(&state.rotations);
+    let reference = reference_time(attestations(state.entries.values()), threshold).unwrap_or(0);
+
+    // Step 1: Ensure every attestor and identity appears in the contributors table,
+    // then recount attested variants and first-seen times per identity from scratch,
+    // a rotated key's counting for its successor.
+    let mut attestors: BTreeSet<[u8; 32]> = BTreeSet::new();
     let mut all_contributions: BTreeMap<[u8; 32], u32> = BTreeMap::new();
+    let mut first_seen: BTreeMap<[u8; 32], u64> = BTreeMap::new();
     for entry in state.entries.values() {
         for variant in entry.hash_variants.values() {
+            attestors.extend(variant.attestations.iter().map(|a| a.contributor_pubkey));
+            for pk in identities.of_variant(variant) {
+                *all_contributions.entry(pk).or_insert(0) += 1;
+            }
+            // Attestations from new keys were dated within the clock skew of the
+            // reference when accepted, so backdating gains a key at most the skew
             for att in &variant.attestations {
-                *all_contributions
-                    .entry(att.contributor_pubkey)
-                    .or_insert(0) += 1;
+                if let Some(pk) = identities.resolve(att) {
+                    let seen = first_seen.entry(pk).or_insert(u64::MAX);
+                    *seen = (*seen).min(att.token_created_at);
+                }
             }
         }
     }
-    for (pk, count) in &all_contributions {
-        let score = state
+    // Identities are aged like entries expire: against the reference time
+    if params.min_identity_age_days > 0 {
+        let min_age = params.min_identity_age_days as u64 * MS_PER_DAY;
+        identities.young = first_seen
+            .iter()
+            .filter(|(_, seen)| reference.saturating_sub(**seen) < min_age)
+            .map(|(pk, _)| *pk)
+            .collect();
+    }
+    for pk in attestors.iter().chain(all_contributions.keys()) {
+        state
             .contributors
             .entry(*pk)
             .or_insert_with(|| ContributorScore {
                 pubkey: *pk,
                 trust_score: 0,
                 total_contributions: 0,
+                first_seen: 0,
             });
-        score.total_contributions = score.total_contributions.max(*count);
     }
-
-    // Step 2: Compute trust from confirmed entries (higher trust for reliable attestors)
-    let computed_trust = compute_trust_from_entries(state, threshold);
-    for (pk, trust) in &computed_trust {
-        if let Some(score) = state.contributors.get_mut(pk) {
-            score.trust_score = score.trust_score.max(*trust);
-        }
+    // Step 2: Derive trust from decided entries; winning attestations raise it,
+    // losing ones lower it, never below zero
+    let computed_trust = compute_trust_from_entries(state, threshold, &identities);
+    for (pk, score) in state.contributors.iter_mut() {
+        score.total_contributions = all_contributions.get(pk).copied().unwrap_or(0);
+        score.first_seen = first_seen.get(pk).copied().unwrap_or(0);
+        score.trust_score = computed_trust
+            .get(pk)
+            .map_or(0, |t| (*t).clamp(0, u32::MAX as i64) as u32);
     }
 
-    // Step 3: Recompute all attestation weights from final contributor table
+    // Step 3: Recompute all attestation weights from final contributor table;
+    // attestations that count for no identity weigh nothing
     for entry in state.entries.values_mut() {
         for variant in entry.hash_variants.values_mut() {
             for att in variant.attestations.iter_mut() {
-                let trust_score = state
-                    .contributors
-                    .get(&att.contributor_pubkey)
-                    .map(|c| c.trust_score)
-                    .unwrap_or(0);
-                att.weight = 1 + trust_score;
+                att.weight = match identities.resolve(att) {
+                    Some(pk) => {
+                        1 + state
+                            .contributors
+                            .get(&pk)
+                            .map(|c| c.trust_score)
+                            .unwrap_or(0)
+                    }
+                    None => 0,
+                };
             }
             variant.total_weight = variant.attestations.iter().map(|a| a.weight).sum();
         }
     }
 
-    // Step 4: Re-derive status for all entries (uses attestation count, not weight)
+    // Step 4: Re-derive status for all entries (uses attestation count, not weight),
+    // then expire entries not re-attested within the TTL
     for entry in state.entries.values_mut() {
-        entry.status = derive_status(entry, threshold);
+        entry.status = if is_expired(entry, reference, params.entry_ttl_days) {
+            Status::Expired
+        } else {
+            derive_status(entry, threshold, &identities)
+        };
     }
 }
 
 #[contract]
 impl ContractInterface for Contract {
     fn validate_state(
-        _parameters: Parameters<'static>,
+        parameters: Parameters<'static>,
         state: State<'static>,
         _related: RelatedContracts<'static>,
     ) -> Result<ValidateResult, ContractError> {
+        let params: CatalogParameters = ciborium::de::from_reader(parameters.as_ref())
+            .map_err(|_| ContractError::InvalidState)?;
         let catalog_state: CatalogState =
             ciborium::de::from_reader(state.as_ref()).map_err(|_| ContractError::InvalidState)?;
 
+        // Rotations are kept sorted without duplicates, and each must verify
+        if !catalog_state.rotations.windows(2).all(|w| w[0] < w[1])
+            || !catalog_state.rotations.iter().all(verify_rotation)
+        {
+            return Err(ContractError::InvalidState);
+        }
+
         for entry in catalog_state.entries.values() {
             if entry.contract_key.is_empty() {
                 return Err(ContractError::InvalidState);
             }
 
             for (hash, variant) in &entry.hash_variants {
-                let expected =
-                    metadata_hash(&variant.title, &variant.description, &variant.mini_snippet);
-                if *hash != expected {
+                if !variant_matches(hash, variant) || variant.snippet.len() > MAX_SNIPPET_LEN {
                     return Err(ContractError::InvalidState);
                 }
 
@@ -319,6 +907,9 @@ impl ContractInterface for Contract {
                     if !seen_pubkeys.insert(att.contributor_pubkey) {
                         return Err(ContractError::InvalidState);
                     }
+                    if !verify_attestation_signature(&entry.contract_key, hash, att) {
+                        return Err(ContractError::InvalidState);
+                    }
                 }
 
                 let sum: u32 = variant.attestations.iter().map(|a| a.weight).sum();
@@ -328,6 +919,10 @@ impl ContractInterface for Contract {
             }
         }
 
+        if over_budget(catalog_state.entries.values(), &params) {
+            return Err(ContractError::InvalidState);
+        }
+
         Ok(ValidateResult::Valid)
     }
 
@@ -347,15 +942,33 @@ impl ContractInterface for Contract {
                     if let Ok(delta) =
                         ciborium::de::from_reader::<CatalogDelta, _>(delta_bytes.as_ref())
                     {
-                        validate_delta(&delta)?;
+                        validate_delta(&delta, &params, &catalog_state)?;
                         apply_delta_to_state(&mut catalog_state, &delta);
                     } else if let Ok(deltas) =
                         ciborium::de::from_reader::<Vec<CatalogDelta>, _>(delta_bytes.as_ref())
                     {
                         for delta in &deltas {
-                            validate_delta(delta)?;
+                            validate_delta(delta, &params, &catalog_state)?;
                             apply_delta_to_state(&mut catalog_state, delta);
                         }
+                    } else if let Ok(rotation) =
+                        ciborium::de::from_reader::<KeyRotation, _>(delta_bytes.as_ref())
+                    {
+                        if !verify_rotation(&rotation) {
+                            return Err(ContractError::InvalidUpdate);
+                        }
+                        merge_rotations(&mut catalog_state.rotations, &[rotation]);
+                    } else if let Ok(delta) =
+                        ciborium::de::from_reader::<CatalogSyncDelta, _>(delta_bytes.as_ref())
+                    {
+                        validate_sync_delta(&delta, &params)?;
+                        // Scores are derived in finalization; any an older peer sends are ignored
+                        let other_state = CatalogState {
+                            entries: delta.entries,
+                            contributors: BTreeMap::new(),
+                            rotations: delta.rotations,
+                        };
+                        merge_catalog_states(&mut catalog_state, &other_state, &params);
                     } else {
                         return Err(ContractError::InvalidUpdate);
                     }
@@ -363,14 +976,15 @@ impl ContractInterface for Contract {
                 UpdateData::State(state_bytes) => {
                     let other_state: CatalogState = ciborium::de::from_reader(state_bytes.as_ref())
                         .map_err(|_| ContractError::InvalidUpdate)?;
-                    merge_catalog_states(&mut catalog_state, &other_state);
+                    validate_incoming_state(&other_state, &params)?;
+                    merge_catalog_states(&mut catalog_state, &other_state, &params);
                 }
                 _ => {}
             }
         }
 
         // Finalize: recompute trust, weights, and status deterministically
-        finalize_state(&mut catalog_state, params.confirmation_weight_threshold);
+        finalize_state(&mut catalog_state, &params);
 
         let new_state_bytes = cbor_serialize(&catalog_state);
         Ok(UpdateModification::valid(State::from(new_state_bytes)))
@@ -383,13 +997,17 @@ impl ContractInterface for Contract {
         let catalog_state: CatalogState =
             ciborium::de::from_reader(state.as_ref()).map_err(|_| ContractError::InvalidState)?;
 
-        let mut bloom = BloomFilter::new(8192);
-        for entry in catalog_state.entries.values() {
-            let key = bloom_key(entry);
-            bloom.insert(&key);
-        }
+        let items: Vec<SyncItem> = catalog_state
+            .entries
+            .values()
+            .map(sync_item)
+            .chain(catalog_state.rotations.iter().map(rotation_sync_item))
+            .collect();
 
-        Ok(StateSummary::from(bloom.to_bytes()))
+        Ok(StateSummary::from(sync::summarize(
+            SummaryKind::Iblt,
+            &items,
+        )))
     }
 
     fn get_state_delta(
@@ -400,40 +1018,33 @@ impl ContractInterface for Contract {
         let catalog_state: CatalogState =
             ciborium::de::from_reader(state.as_ref()).map_err(|_| ContractError::InvalidState)?;
 
-        let bloom = BloomFilter::from_bytes(summary.as_ref()).ok_or(ContractError::InvalidState)?;
-
-        let mut missing_deltas: Vec<CatalogDelta> = Vec::new();
+        // Entries first, then rotations, matching `summarize_state`
+        let entries: Vec<&CatalogEntry> = catalog_state.entries.values().collect();
+        let items: Vec<SyncItem> = entries
+            .iter()
+            .map(|e| sync_item(e))
+            .chain(catalog_state.rotations.iter().map(rotation_sync_item))
+            .collect();
+        let missing_items =
+            sync::missing_items(summary.as_ref(), &items).ok_or(ContractError::InvalidState)?;
 
-        for entry in catalog_state.entries.values() {
-            let key = bloom_key(entry);
-            if !bloom.contains(&key) {
-                for variant in entry.hash_variants.values() {
-                    for att in &variant.attestations {
-                        let hash = metadata_hash(
-                            &variant.title,
-                            &variant.description,
-                            &variant.mini_snippet,
-                        );
-                        missing_deltas.push(CatalogDelta {
-                            contract_key: entry.contract_key.clone(),
-                            title: variant.title.clone(),
-                            description: variant.description.clone(),
-                            mini_snippet: variant.mini_snippet.clone(),
-                            snippet: variant.mini_snippet.clone(),
-                            size_bytes: entry.size_bytes,
-                            version: entry.version,
-                            metadata_hash: hash,
-                            attestation: att.clone(),
-                        });
-                    }
+        let mut delta = CatalogSyncDelta::default();
+        for i in missing_items {
+            match entries.get(i) {
+                Some(entry) => {
+                    delta
+                        .entries
+                        .insert(entry.contract_key.clone(), (*entry).clone());
                 }
+                None => delta
+                    .rotations
+                    .push(catalog_state.rotations[i - entries.len()].clone()),
             }
         }
-
-        if missing_deltas.is_empty() {
+        if delta.entries.is_empty() && delta.rotations.is_empty() {
             Ok(StateDelta::from(vec![]))
         } else {
-            Ok(StateDelta::from(cbor_serialize(&missing_deltas)))
+            Ok(StateDelta::from(cbor_serialize(&delta)))
         }
     }
 }
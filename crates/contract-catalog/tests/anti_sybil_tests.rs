@@ -1,4 +1,5 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
 use search_common::types::*;
 
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
@@ -13,47 +14,90 @@ fn default_params() -> CatalogParameters {
         shard_count: 16,
         confirmation_weight_threshold: 3,
         entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 0,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
     }
 }
 
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
 fn make_delta_with_token(
     contract_key: &str,
-    pubkey: [u8; 32],
+    secret: [u8; 32],
     token: AntifloodToken,
     created_at: u64,
 ) -> CatalogDelta {
     let title = format!("Title for {}", contract_key);
     let description = format!("Description for {}", contract_key);
     let snippet = format!("Snippet for {}", contract_key);
-    let hash = search_common::hashing::metadata_hash(&title, &description, &snippet);
+    let hash = search_common::hashing::metadata_hash(&title, &description, "mini", &snippet);
+    let message =
+        search_common::hashing::attestation_message(contract_key, &hash, created_at, &token.nonce);
+    let signature = delegate_identity::sign_data(&secret, &message).to_vec();
 
     CatalogDelta {
         contract_key: contract_key.to_string(),
         title,
         description,
         mini_snippet: "mini".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash(&snippet),
         snippet,
         size_bytes: 1024,
         version: Some(1),
         metadata_hash: hash,
         attestation: Attestation {
-            contributor_pubkey: pubkey,
+            contributor_pubkey: public_key(&secret),
             antiflood_token: token,
             token_created_at: created_at,
             weight: 1,
+            signature,
         },
     }
 }
 
+/// Mine a token bound to the payload `make_delta_with_token` builds.
+fn mined_token(
+    contract_key: &str,
+    secret: [u8; 32],
+    created_at: u64,
+    difficulty: u8,
+) -> AntifloodToken {
+    let hash = search_common::hashing::metadata_hash(
+        &format!("Title for {}", contract_key),
+        &format!("Description for {}", contract_key),
+        "mini",
+        &format!("Snippet for {}", contract_key),
+    );
+    let challenge =
+        antiflood::attestation_challenge(&public_key(&secret), contract_key, &hash, created_at);
+    antiflood::mine_token(&challenge, difficulty)
+}
+
+fn apply(params: &CatalogParameters, delta: &CatalogDelta) -> bool {
+    contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(params)),
+        freenet_stdlib::prelude::State::from(serialize(&CatalogState::default())),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(serialize(delta)),
+        )],
+    )
+    .is_ok()
+}
+
 #[test]
 fn valid_token_accepted() {
     let state = CatalogState::default();
     let params = default_params();
 
-    let token = AntifloodToken {
-        nonce: vec![0u8; 8],
-        difficulty: 16,
-    };
+    let token = mined_token("contract-valid", [1u8; 32], 1000, 8);
     let delta = make_delta_with_token("contract-valid", [1u8; 32], token, 1000);
 
     let result = contract_catalog::Contract::update_state(
@@ -89,24 +133,67 @@ fn invalid_token_rejected() {
 
 #[test]
 fn missing_signature_rejected() {
-    let state = CatalogState::default();
-    let params = default_params();
+    let token = mined_token("contract-nosig", [1u8; 32], 1000, 8);
+    let mut delta = make_delta_with_token("contract-nosig", [1u8; 32], token, 1000);
+    delta.attestation.signature = vec![];
+    assert!(!apply(&default_params(), &delta));
+}
 
-    // Attestation with zeroed pubkey (no valid identity)
-    let token = AntifloodToken {
-        nonce: vec![0u8; 8],
-        difficulty: 16,
-    };
-    let delta = make_delta_with_token("contract-nosig", [0u8; 32], token, 1000);
+#[test]
+fn zero_pubkey_rejected() {
+    let token = mined_token("contract-zero", [1u8; 32], 1000, 8);
+    let mut delta = make_delta_with_token("contract-zero", [1u8; 32], token, 1000);
+    delta.attestation.contributor_pubkey = [0u8; 32];
+    assert!(!apply(&default_params(), &delta));
+}
 
-    let result = contract_catalog::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(serialize(&params)),
-        freenet_stdlib::prelude::State::from(serialize(&state)),
-        vec![freenet_stdlib::prelude::UpdateData::Delta(
-            freenet_stdlib::prelude::StateDelta::from(serialize(&delta)),
-        )],
+#[test]
+fn impersonation_rejected() {
+    // Attacker signs with their own key but claims the victim's pubkey,
+    // with a token mined for the victim's pubkey
+    let victim = public_key(&[1u8; 32]);
+    let hash = search_common::hashing::metadata_hash(
+        "Title for contract-imp",
+        "Description for contract-imp",
+        "mini",
+        "Snippet for contract-imp",
     );
-    assert!(result.is_err());
+    let token = antiflood::mine_token(
+        &antiflood::attestation_challenge(&victim, "contract-imp", &hash, 1000),
+        8,
+    );
+    let mut delta = make_delta_with_token("contract-imp", [2u8; 32], token, 1000);
+    delta.attestation.contributor_pubkey = victim;
+    assert!(!apply(&default_params(), &delta));
+}
+
+#[test]
+fn signature_bound_to_nonce() {
+    // A valid signature can't be moved onto a different (also valid) token
+    let token = mined_token("contract-nonce", [1u8; 32], 1000, 8);
+    let mut delta = make_delta_with_token("contract-nonce", [1u8; 32], token, 1000);
+    assert!(apply(&default_params(), &delta));
+
+    let challenge = antiflood::attestation_challenge(
+        &delta.attestation.contributor_pubkey,
+        &delta.contract_key,
+        &delta.metadata_hash,
+        1000,
+    );
+    let first = u64::from_le_bytes(
+        delta.attestation.antiflood_token.nonce[..]
+            .try_into()
+            .unwrap(),
+    );
+    let second = (first + 1..)
+        .map(|n| AntifloodToken {
+            nonce: n.to_le_bytes().to_vec(),
+            difficulty: 8,
+        })
+        .find(|t| antiflood::verify_token(t, &challenge, 8))
+        .unwrap();
+    delta.attestation.antiflood_token = second;
+    assert!(!apply(&default_params(), &delta));
 }
 
 #[test]
@@ -136,10 +223,7 @@ fn temporal_staking_valid() {
     let state = CatalogState::default();
     let params = default_params();
 
-    let token = AntifloodToken {
-        nonce: vec![0u8; 8],
-        difficulty: 16,
-    };
+    let token = mined_token("contract-old", [1u8; 32], 86400, 8);
     // Token created at reasonable past time
     let delta = make_delta_with_token("contract-old", [1u8; 32], token, 86400);
 
@@ -152,3 +236,297 @@ fn temporal_staking_valid() {
     );
     assert!(result.is_ok());
 }
+
+#[test]
+fn forged_token_rejected() {
+    // The cheap token from before PoW was verified contract-side
+    let cheap = AntifloodToken {
+        nonce: vec![1u8],
+        difficulty: 1,
+    };
+    let delta = make_delta_with_token("contract-forged", [1u8; 32], cheap, 1000);
+    assert!(!apply(&default_params(), &delta));
+
+    // Claiming the minimum difficulty without doing the work
+    let unmined = AntifloodToken {
+        nonce: vec![1u8],
+        difficulty: 8,
+    };
+    let delta = make_delta_with_token("contract-forged", [1u8; 32], unmined, 1000);
+    assert!(!apply(&default_params(), &delta));
+}
+
+#[test]
+fn token_below_min_difficulty_rejected() {
+    let token = mined_token("contract-weak", [1u8; 32], 1000, 4);
+    let weak = AntifloodToken {
+        nonce: token.nonce,
+        difficulty: 4,
+    };
+    let delta = make_delta_with_token("contract-weak", [1u8; 32], weak, 1000);
+    assert!(!apply(&default_params(), &delta));
+}
+
+#[test]
+fn token_bound_to_payload() {
+    // A token mined for one contract can't be replayed for another
+    let token = mined_token("contract-a", [1u8; 32], 1000, 8);
+    let delta = make_delta_with_token("contract-a", [1u8; 32], token.clone(), 1000);
+    assert!(apply(&default_params(), &delta));
+
+    let replayed = make_delta_with_token("contract-b", [1u8; 32], token.clone(), 1000);
+    assert!(!apply(&default_params(), &replayed));
+
+    // Nor by another contributor or with a different timestamp
+    let other_key = make_delta_with_token("contract-a", [2u8; 32], token.clone(), 1000);
+    assert!(!apply(&default_params(), &other_key));
+    let other_time = make_delta_with_token("contract-a", [1u8; 32], token, 2000);
+    assert!(!apply(&default_params(), &other_time));
+}
+
+/// A mined, signed delta attesting `title` for `contract_key`.
+fn attest(contract_key: &str, secret: [u8; 32], title: &str) -> CatalogDelta {
+    attest_at(contract_key, secret, title, 1000)
+}
+
+fn attest_at(contract_key: &str, secret: [u8; 32], title: &str, created_at: u64) -> CatalogDelta {
+    let mut delta = make_delta_with_token(
+        contract_key,
+        secret,
+        AntifloodToken {
+            nonce: vec![],
+            difficulty: 0,
+        },
+        created_at,
+    );
+    delta.title = title.to_string();
+    delta.metadata_hash = search_common::hashing::metadata_hash(
+        &delta.title,
+        &delta.description,
+        &delta.mini_snippet,
+        &delta.snippet,
+    );
+    let challenge = antiflood::attestation_challenge(
+        &delta.attestation.contributor_pubkey,
+        contract_key,
+        &delta.metadata_hash,
+        created_at,
+    );
+    delta.attestation.antiflood_token = antiflood::mine_token(&challenge, 8);
+    let message = search_common::hashing::attestation_message(
+        contract_key,
+        &delta.metadata_hash,
+        created_at,
+        &delta.attestation.antiflood_token.nonce,
+    );
+    delta.attestation.signature = delegate_identity::sign_data(&secret, &message).to_vec();
+    delta
+}
+
+fn apply_all(
+    params: &CatalogParameters,
+    state: &CatalogState,
+    deltas: &[CatalogDelta],
+) -> CatalogState {
+    let result = contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(params)),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        deltas
+            .iter()
+            .map(|d| {
+                freenet_stdlib::prelude::UpdateData::Delta(
+                    freenet_stdlib::prelude::StateDelta::from(serialize(d)),
+                )
+            })
+            .collect(),
+    )
+    .expect("update_state failed");
+    ciborium::de::from_reader(result.unwrap_valid().as_ref()).unwrap()
+}
+
+#[test]
+fn sybil_ring_loses_trust_backing_losing_variants() {
+    let honest: Vec<[u8; 32]> = (1..=4u8).map(|n| [n; 32]).collect();
+    let ring: Vec<[u8; 32]> = (10..=11u8).map(|n| [n; 32]).collect();
+
+    // The ring first earns trust alongside an honest contributor
+    let mut deltas = Vec::new();
+    for key in ["warmup-0", "warmup-1"] {
+        for secret in ring.iter().chain(&honest[..1]) {
+            deltas.push(attest(key, *secret, "Title"));
+        }
+    }
+    let warmed = apply_all(&default_params(), &CatalogState::default(), &deltas);
+    let ring_pubkey = public_key(&ring[0]);
+    assert_eq!(warmed.contributors[&ring_pubkey].trust_score, 2);
+
+    // Then spends it backing a spam title against the honest majority
+    let mut deltas = Vec::new();
+    for key in ["target-0", "target-1"] {
+        for secret in &honest {
+            deltas.push(attest(key, *secret, "Title"));
+        }
+        for secret in &ring {
+            deltas.push(attest(key, *secret, "Spam Title"));
+        }
+    }
+    let state = apply_all(&default_params(), &warmed, &deltas);
+
+    assert_eq!(state.entries["target-0"].status, Status::Disputed);
+    assert_eq!(state.contributors[&ring_pubkey].trust_score, 0);
+    assert_eq!(state.contributors[&public_key(&honest[0])].trust_score, 4);
+    // The ring's attestations are back to the weight of a fresh key
+    let spam = state.entries["target-1"]
+        .hash_variants
+        .values()
+        .find(|v| v.title == "Spam Title")
+        .unwrap();
+    assert!(spam.attestations.iter().all(|a| a.weight == 1));
+}
+
+const DAY_MS: u64 = 86_400_000;
+
+/// Contributors 1 and 2 attest on day 0; on day 30 they and newcomer 3
+/// attest "target".
+fn newcomer_deltas() -> Vec<CatalogDelta> {
+    let mut deltas = vec![
+        attest_at("history", [1u8; 32], "Title", 1000),
+        attest_at("history", [2u8; 32], "Title", 1000),
+    ];
+    for n in 1..=3u8 {
+        deltas.push(attest_at("target", [n; 32], "Title", 30 * DAY_MS));
+    }
+    deltas
+}
+
+#[test]
+fn young_identity_not_counted_toward_quorum() {
+    let params = CatalogParameters {
+        min_identity_age_days: 7,
+        ..default_params()
+    };
+    let state = apply_all(&params, &CatalogState::default(), &newcomer_deltas());
+
+    let newcomer = &state.contributors[&public_key(&[3u8; 32])];
+    assert_eq!(newcomer.first_seen, 30 * DAY_MS);
+    assert_eq!(state.contributors[&public_key(&[1u8; 32])].first_seen, 1000);
+    assert_eq!(state.entries["target"].status, Status::Pending);
+    assert_eq!(newcomer.trust_score, 0);
+
+    // A week later, by a quorum's clock, the newcomer is old enough to count
+    let later_deltas: Vec<CatalogDelta> = [1u8, 2, 4]
+        .into_iter()
+        .map(|n| attest_at("later", [n; 32], "Title", 37 * DAY_MS))
+        .collect();
+    let later = apply_all(&params, &state, &later_deltas);
+    assert_eq!(later.entries["target"].status, Status::Confirmed);
+    assert_eq!(later.contributors[&public_key(&[3u8; 32])].trust_score, 1);
+}
+
+#[test]
+fn zero_min_identity_age_counts_everyone() {
+    let state = apply_all(
+        &default_params(),
+        &CatalogState::default(),
+        &newcomer_deltas(),
+    );
+    assert_eq!(state.entries["target"].status, Status::Confirmed);
+}
+
+#[test]
+fn min_difficulty_defaults_when_absent() {
+    // Parameters serialized before min_pow_difficulty existed
+    #[derive(serde::Serialize)]
+    struct LegacyParams {
+        protocol_version: u16,
+        shard_count: u8,
+        confirmation_weight_threshold: u32,
+        entry_ttl_days: u16,
+    }
+    let legacy = serialize(&LegacyParams {
+        protocol_version: 1,
+        shard_count: 16,
+        confirmation_weight_threshold: 3,
+        entry_ttl_days: 90,
+    });
+    let params: CatalogParameters = ciborium::de::from_reader(legacy.as_slice()).unwrap();
+    assert_eq!(params.min_pow_difficulty, antiflood::DEFAULT_MIN_DIFFICULTY);
+    assert_eq!(params.min_identity_age_days, 0);
+}
+
+#[test]
+fn future_dated_attestation_does_not_age_everyone() {
+    let params = CatalogParameters {
+        min_identity_age_days: 7,
+        ..default_params()
+    };
+    let state = apply_all(&params, &CatalogState::default(), &newcomer_deltas());
+
+    // One key alone can't move the catalog's clock, however far ahead it dates
+    let far = apply_all(
+        &params,
+        &state,
+        &[attest_at("far", [1u8; 32], "Title", 1000 * DAY_MS)],
+    );
+    assert_eq!(far.entries["target"].status, Status::Pending);
+    assert_eq!(far.contributors[&public_key(&[3u8; 32])].trust_score, 0);
+}
+
+#[test]
+fn backdated_newcomer_dropped() {
+    // Three established keys put the catalog at day 30
+    let established: Vec<CatalogDelta> = [1u8, 2, 4]
+        .into_iter()
+        .map(|n| attest_at("history", [n; 32], "Title", 30 * DAY_MS))
+        .collect();
+    let local = apply_all(&default_params(), &CatalogState::default(), &established);
+    let params = CatalogParameters {
+        min_identity_age_days: 7,
+        max_clock_skew_mins: 60,
+        ..default_params()
+    };
+    let update = |state: &CatalogState, data| {
+        contract_catalog::Contract::update_state(
+            freenet_stdlib::prelude::Parameters::from(serialize(&params)),
+            freenet_stdlib::prelude::State::from(serialize(state)),
+            vec![data],
+        )
+    };
+    let as_delta = |delta: &CatalogDelta| {
+        freenet_stdlib::prelude::UpdateData::Delta(freenet_stdlib::prelude::StateDelta::from(
+            serialize(delta),
+        ))
+    };
+
+    // A new key dating its first attestation ten days back
+    let backdated = attest_at("target", [9u8; 32], "Title", 20 * DAY_MS);
+    assert!(update(&local, as_delta(&backdated)).is_err());
+
+    // Nor does it get in through a peer with no clock of its own, though the
+    // rest of the peer's state still merges
+    let peer_deltas = [
+        backdated,
+        attest_at("news", [1u8; 32], "Title", 30 * DAY_MS),
+    ];
+    let peer = apply_all(&default_params(), &CatalogState::default(), &peer_deltas);
+    let merge = freenet_stdlib::prelude::UpdateData::State(freenet_stdlib::prelude::State::from(
+        serialize(&peer),
+    ));
+    let merged: CatalogState =
+        ciborium::de::from_reader(update(&local, merge).unwrap().unwrap_valid().as_ref()).unwrap();
+    assert!(merged.entries.get("target").is_none_or(|entry| entry
+        .hash_variants
+        .values()
+        .all(|v| v.attestations.is_empty())));
+    assert_eq!(merged.contributors[&public_key(&[9u8; 32])].first_seen, 0);
+    assert_eq!(merged.entries["news"].hash_variants.len(), 1);
+
+    // Dated within the skew it is accepted, and starts out young
+    let fresh = attest_at("target", [9u8; 32], "Title", 30 * DAY_MS - 30 * 60_000);
+    assert!(update(&local, as_delta(&fresh)).is_ok());
+    let state = apply_all(&params, &local, &[fresh]);
+    assert_eq!(
+        state.contributors[&public_key(&[9u8; 32])].first_seen,
+        30 * DAY_MS - 30 * 60_000
+    );
+}
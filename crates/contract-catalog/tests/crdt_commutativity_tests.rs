@@ -1,4 +1,5 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
 use search_common::types::*;
 
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
@@ -17,34 +18,72 @@ fn default_params() -> CatalogParameters {
         shard_count: 16,
         confirmation_weight_threshold: 3,
         entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 0,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
     }
 }
 
-fn make_delta(contract_key: &str, pubkey: [u8; 32]) -> CatalogDelta {
+fn make_delta(contract_key: &str, secret: [u8; 32]) -> CatalogDelta {
+    make_delta_at(contract_key, secret, 1000)
+}
+
+fn make_delta_at(contract_key: &str, secret: [u8; 32], created_at: u64) -> CatalogDelta {
     let title = format!("Title for {}", contract_key);
     let description = format!("Description for {}", contract_key);
     let snippet = format!("Snippet for {}", contract_key);
-    let hash = search_common::hashing::metadata_hash(&title, &description, &snippet);
+    let hash = search_common::hashing::metadata_hash(&title, &description, "mini", &snippet);
 
-    CatalogDelta {
+    let mut delta = CatalogDelta {
         contract_key: contract_key.to_string(),
         title,
         description,
         mini_snippet: "mini".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash(&snippet),
         snippet,
         size_bytes: 1024,
         version: Some(1),
         metadata_hash: hash,
         attestation: Attestation {
-            contributor_pubkey: pubkey,
+            contributor_pubkey: public_key(&secret),
             antiflood_token: AntifloodToken {
-                nonce: vec![0u8; 8],
-                difficulty: 16,
+                nonce: vec![],
+                difficulty: 0,
             },
-            token_created_at: 1000,
+            token_created_at: created_at,
             weight: 1,
+            signature: vec![],
         },
-    }
+    };
+    seal(&mut delta, &secret);
+    delta
+}
+
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
+/// Mine the antiflood token and sign the attestation for the delta's current payload.
+fn seal(delta: &mut CatalogDelta, secret: &[u8; 32]) {
+    let challenge = antiflood::attestation_challenge(
+        &delta.attestation.contributor_pubkey,
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+    );
+    delta.attestation.antiflood_token = antiflood::mine_token(&challenge, 8);
+    let message = search_common::hashing::attestation_message(
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+        &delta.attestation.antiflood_token.nonce,
+    );
+    delta.attestation.signature = delegate_identity::sign_data(secret, &message).to_vec();
 }
 
 fn apply_delta(
@@ -127,7 +166,13 @@ fn conflicting_hashes_commute() {
     let a = make_delta("contract-x", [1u8; 32]);
     let mut b = make_delta("contract-x", [2u8; 32]);
     b.title = "Different Title".to_string();
-    b.metadata_hash = search_common::hashing::metadata_hash(&b.title, &b.description, &b.snippet);
+    b.metadata_hash = search_common::hashing::metadata_hash(
+        &b.title,
+        &b.description,
+        &b.mini_snippet,
+        &b.snippet,
+    );
+    seal(&mut b, &[2u8; 32]);
 
     let ab = apply_deltas_seq(&state, &params, &[a.clone(), b.clone()]);
     let ba = apply_deltas_seq(&state, &params, &[b, a]);
@@ -140,9 +185,9 @@ fn attestation_dedup_commutes() {
     let state = CatalogState::default();
     let params = default_params();
 
-    let pubkey = [1u8; 32];
-    let a = make_delta("contract-dup", pubkey);
-    let b = make_delta("contract-dup", pubkey); // same pubkey, same content
+    let secret = [1u8; 32];
+    let a = make_delta("contract-dup", secret);
+    let b = make_delta("contract-dup", secret); // same pubkey, same content
 
     let ab = apply_deltas_seq(&state, &params, &[a.clone(), b.clone()]);
     let ba = apply_deltas_seq(&state, &params, &[b, a]);
@@ -173,9 +218,9 @@ fn status_derivation_commutes() {
     // Enough attestations to trigger Confirmed
     let deltas: Vec<CatalogDelta> = (0..5u8)
         .map(|i| {
-            let mut pubkey = [0u8; 32];
-            pubkey[0] = i + 1;
-            make_delta("contract-status", pubkey)
+            let mut secret = [0u8; 32];
+            secret[0] = i + 1;
+            make_delta("contract-status", secret)
         })
         .collect();
 
@@ -195,9 +240,9 @@ fn contributor_score_commutes() {
     let mut deltas = Vec::new();
     for entry_idx in 0..3 {
         for contributor_idx in 0..3u8 {
-            let mut pubkey = [0u8; 32];
-            pubkey[0] = contributor_idx + 1;
-            deltas.push(make_delta(&format!("contract-{}", entry_idx), pubkey));
+            let mut secret = [0u8; 32];
+            secret[0] = contributor_idx + 1;
+            deltas.push(make_delta(&format!("contract-{}", entry_idx), secret));
         }
     }
 
@@ -233,9 +278,9 @@ fn stress_50_entries() {
 
     let deltas: Vec<CatalogDelta> = (0..50u8)
         .map(|i| {
-            let mut pubkey = [0u8; 32];
-            pubkey[0] = i + 1; // start at 1 to avoid zero pubkey
-            make_delta(&format!("contract-{}", i), pubkey)
+            let mut secret = [0u8; 32];
+            secret[0] = i + 1;
+            make_delta(&format!("contract-{}", i), secret)
         })
         .collect();
 
@@ -245,3 +290,191 @@ fn stress_50_entries() {
 
     assert_eq!(forward, backward);
 }
+
+const DAY_MS: u64 = 86_400_000;
+
+fn confirming_deltas(contract_key: &str, first_secret: u8, created_at: u64) -> Vec<CatalogDelta> {
+    (0..3)
+        .map(|i| {
+            let mut secret = [0u8; 32];
+            secret[0] = first_secret + i;
+            make_delta_at(contract_key, secret, created_at)
+        })
+        .collect()
+}
+
+#[test]
+fn ttl_expiry_commutes() {
+    let state = CatalogState::default();
+    let params = default_params(); // ttl = 90 days
+
+    // "old" confirmed at t=1000, "fresh" attested 91 days later
+    let mut deltas = confirming_deltas("contract-old", 1, 1000);
+    deltas.extend(confirming_deltas("contract-fresh", 10, 1000 + 91 * DAY_MS));
+
+    let forward = apply_deltas_seq(&state, &params, &deltas);
+    let reversed: Vec<CatalogDelta> = deltas.into_iter().rev().collect();
+    let backward = apply_deltas_seq(&state, &params, &reversed);
+
+    assert_eq!(forward, backward);
+    assert_eq!(forward.entries["contract-old"].status, Status::Expired);
+    assert_eq!(forward.entries["contract-fresh"].status, Status::Confirmed);
+}
+
+#[test]
+fn within_ttl_not_expired() {
+    let state = CatalogState::default();
+    let params = default_params();
+
+    let mut deltas = confirming_deltas("contract-old", 1, 1000);
+    deltas.extend(confirming_deltas("contract-fresh", 10, 1000 + 90 * DAY_MS));
+
+    let new_state = apply_deltas_seq(&state, &params, &deltas);
+    assert_eq!(new_state.entries["contract-old"].status, Status::Confirmed);
+}
+
+#[test]
+fn reattestation_revives_expired_entry() {
+    let state = CatalogState::default();
+    let params = default_params();
+    let later = 1000 + 91 * DAY_MS;
+
+    let mut deltas = confirming_deltas("contract-old", 1, 1000);
+    deltas.extend(confirming_deltas("contract-fresh", 10, later));
+    let expired = apply_deltas_seq(&state, &params, &deltas);
+    assert_eq!(expired.entries["contract-old"].status, Status::Expired);
+
+    // The original contributors re-attest with fresh tokens
+    let reattest = confirming_deltas("contract-old", 1, later);
+    let revived = apply_deltas_seq(&expired, &params, &reattest);
+    let entry = &revived.entries["contract-old"];
+    assert_eq!(entry.status, Status::Confirmed);
+    assert_eq!(entry.last_seen, later);
+    let variant = entry.hash_variants.values().next().unwrap();
+    assert_eq!(variant.attestations.len(), 3);
+    assert!(variant
+        .attestations
+        .iter()
+        .all(|a| a.token_created_at == later));
+}
+
+#[test]
+fn reattestation_merge_commutes() {
+    let state = CatalogState::default();
+    let params = default_params();
+
+    let old = make_delta_at("contract-re", [1u8; 32], 1000);
+    let new = make_delta_at("contract-re", [1u8; 32], 1000 + DAY_MS);
+
+    let a = apply_deltas_seq(&state, &params, std::slice::from_ref(&old));
+    let b = apply_deltas_seq(&state, &params, std::slice::from_ref(&new));
+
+    let merge = |x: &CatalogState, y: &CatalogState| -> CatalogState {
+        let result = contract_catalog::Contract::update_state(
+            freenet_stdlib::prelude::Parameters::from(serialize(&params)),
+            freenet_stdlib::prelude::State::from(serialize(x)),
+            vec![freenet_stdlib::prelude::UpdateData::State(
+                freenet_stdlib::prelude::State::from(serialize(y)),
+            )],
+        )
+        .expect("merge failed");
+        deserialize_state(result.unwrap_valid().as_ref())
+    };
+
+    let ab = merge(&a, &b);
+    assert_eq!(ab, merge(&b, &a));
+    assert_eq!(
+        ab,
+        apply_deltas_seq(&state, &params, &[new.clone(), old.clone()])
+    );
+    assert_eq!(ab, apply_deltas_seq(&state, &params, &[old, new]));
+}
+
+fn try_update(
+    state: &CatalogState,
+    params: &CatalogParameters,
+    update: freenet_stdlib::prelude::UpdateData<'static>,
+) -> Result<CatalogState, freenet_stdlib::prelude::ContractError> {
+    contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(params)),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        vec![update],
+    )
+    .map(|result| deserialize_state(result.unwrap_valid().as_ref()))
+}
+
+#[test]
+fn future_dated_attestation_does_not_expire_catalog() {
+    let state = CatalogState::default();
+    let params = default_params(); // no skew bound
+
+    let mut deltas = confirming_deltas("contract-old", 1, 1000);
+    deltas.push(make_delta_at("contract-far", [20u8; 32], u64::MAX / 2));
+    let forward = apply_deltas_seq(&state, &params, &deltas);
+    let reversed: Vec<CatalogDelta> = deltas.into_iter().rev().collect();
+
+    assert_eq!(forward, apply_deltas_seq(&state, &params, &reversed));
+    assert_eq!(forward.entries["contract-old"].status, Status::Confirmed);
+}
+
+#[test]
+fn future_dated_attestation_rejected() {
+    let params = CatalogParameters {
+        max_clock_skew_mins: 60,
+        ..default_params()
+    };
+    let local = apply_deltas_seq(
+        &CatalogState::default(),
+        &params,
+        &confirming_deltas("contract-old", 1, DAY_MS),
+    );
+
+    let far = make_delta_at("contract-far", [20u8; 32], 2 * DAY_MS);
+    let delta = freenet_stdlib::prelude::UpdateData::Delta(
+        freenet_stdlib::prelude::StateDelta::from(serialize(&far)),
+    );
+    assert!(try_update(&local, &params, delta).is_err());
+
+    // Within the skew is fine
+    let near = make_delta_at("contract-near", [20u8; 32], DAY_MS + 30 * 60_000);
+    let near_state = apply_delta(&local, &params, &near);
+    assert_eq!(near_state.entries["contract-old"].status, Status::Confirmed);
+
+    // A peer with no reference of its own took it; its state still merges, and
+    // one key can't age the catalog that way either
+    let peer = apply_delta(&CatalogState::default(), &params, &far);
+    let merged = try_update(
+        &local,
+        &params,
+        freenet_stdlib::prelude::UpdateData::State(freenet_stdlib::prelude::State::from(
+            serialize(&peer),
+        )),
+    )
+    .unwrap();
+    assert_eq!(merged.entries["contract-old"].status, Status::Confirmed);
+}
+
+#[test]
+fn lagging_node_catches_up_past_clock_skew() {
+    let params = CatalogParameters {
+        max_clock_skew_mins: 60,
+        ..default_params()
+    };
+    let history = confirming_deltas("contract-old", 1, DAY_MS);
+    let lagging = apply_deltas_seq(&CatalogState::default(), &params, &history);
+
+    // Meanwhile the network moved days on, with keys the lagging node never saw
+    let mut deltas = history;
+    deltas.extend(confirming_deltas("contract-new", 10, 5 * DAY_MS));
+    let current = apply_deltas_seq(&CatalogState::default(), &default_params(), &deltas);
+
+    let as_state = |state: &CatalogState| {
+        freenet_stdlib::prelude::UpdateData::State(freenet_stdlib::prelude::State::from(serialize(
+            state,
+        )))
+    };
+    let caught_up = try_update(&lagging, &params, as_state(&current)).unwrap();
+    let unchanged = try_update(&current, &params, as_state(&lagging)).unwrap();
+    assert_eq!(caught_up, unchanged);
+    assert_eq!(caught_up.entries["contract-new"].status, Status::Confirmed);
+}
@@ -0,0 +1,300 @@
+use freenet_stdlib::prelude::{ContractInterface, StateDelta, UpdateData};
+use search_common::antiflood;
+use search_common::types::*;
+
+const MINUTE_MS: u64 = 60_000;
+
+fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(val, &mut buf).unwrap();
+    buf
+}
+
+fn deserialize_state(bytes: &[u8]) -> CatalogState {
+    ciborium::de::from_reader(bytes).unwrap()
+}
+
+/// Three attestations per contributor per hour.
+fn budget_params() -> CatalogParameters {
+    CatalogParameters {
+        protocol_version: 1,
+        shard_count: 16,
+        confirmation_weight_threshold: 3,
+        entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 3,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
+    }
+}
+
+fn unlimited_params() -> CatalogParameters {
+    CatalogParameters {
+        max_attestations_per_window: 0,
+        ..budget_params()
+    }
+}
+
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
+fn make_delta(contract_key: &str, secret: [u8; 32], token_created_at: u64) -> CatalogDelta {
+    let title = format!("Title for {}", contract_key);
+    let description = format!("Description for {}", contract_key);
+    let snippet = format!("Snippet for {}", contract_key);
+    let hash = search_common::hashing::metadata_hash(&title, &description, "mini", &snippet);
+    let contributor_pubkey = public_key(&secret);
+
+    let challenge = antiflood::attestation_challenge(
+        &contributor_pubkey,
+        contract_key,
+        &hash,
+        token_created_at,
+    );
+    let antiflood_token = antiflood::mine_token(&challenge, 8);
+    let message = search_common::hashing::attestation_message(
+        contract_key,
+        &hash,
+        token_created_at,
+        &antiflood_token.nonce,
+    );
+    CatalogDelta {
+        contract_key: contract_key.to_string(),
+        title,
+        description,
+        mini_snippet: "mini".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash(&snippet),
+        snippet,
+        size_bytes: 1024,
+        version: Some(1),
+        metadata_hash: hash,
+        attestation: Attestation {
+            contributor_pubkey,
+            antiflood_token,
+            token_created_at,
+            weight: 1,
+            signature: delegate_identity::sign_data(&secret, &message).to_vec(),
+        },
+    }
+}
+
+/// Contributor 1 attesting `count` contracts a minute apart from `start`.
+fn burst(prefix: &str, start: u64, count: usize) -> Vec<CatalogDelta> {
+    (0..count)
+        .map(|i| {
+            make_delta(
+                &format!("{}-{}", prefix, i),
+                [1u8; 32],
+                start + i as u64 * MINUTE_MS,
+            )
+        })
+        .collect()
+}
+
+fn update(
+    params: &CatalogParameters,
+    state: &CatalogState,
+    updates: Vec<UpdateData<'static>>,
+) -> Result<CatalogState, freenet_stdlib::prelude::ContractError> {
+    contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(params)),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        updates,
+    )
+    .map(|result| deserialize_state(result.unwrap_valid().as_ref()))
+}
+
+fn delta_update<T: serde::Serialize>(delta: &T) -> UpdateData<'static> {
+    UpdateData::Delta(StateDelta::from(serialize(delta)))
+}
+
+fn apply(
+    params: &CatalogParameters,
+    state: &CatalogState,
+    deltas: &[CatalogDelta],
+) -> Result<CatalogState, freenet_stdlib::prelude::ContractError> {
+    update(params, state, deltas.iter().map(delta_update).collect())
+}
+
+fn validate(params: &CatalogParameters, state: &CatalogState) -> bool {
+    contract_catalog::Contract::validate_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(params)),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        freenet_stdlib::prelude::RelatedContracts::default(),
+    )
+    .is_ok()
+}
+
+fn attestation_count(state: &CatalogState) -> usize {
+    state
+        .entries
+        .values()
+        .flat_map(|e| e.hash_variants.values())
+        .map(|v| v.attestations.len())
+        .sum()
+}
+
+#[test]
+fn attestations_within_budget_accepted() {
+    let state = apply(
+        &budget_params(),
+        &CatalogState::default(),
+        &burst("c", 1000, 3),
+    )
+    .unwrap();
+    assert_eq!(attestation_count(&state), 3);
+    assert!(validate(&budget_params(), &state));
+}
+
+#[test]
+fn excess_attestation_rejected() {
+    let params = budget_params();
+    let state = apply(&params, &CatalogState::default(), &burst("c", 1000, 3)).unwrap();
+
+    let excess = make_delta("c-extra", [1u8; 32], 1000 + 10 * MINUTE_MS);
+    assert!(apply(&params, &state, &[excess]).is_err());
+
+    // Another contributor has a budget of their own
+    let other = make_delta("c-extra", [2u8; 32], 1000 + 10 * MINUTE_MS);
+    assert!(apply(&params, &state, &[other]).is_ok());
+}
+
+#[test]
+fn budget_resets_next_window() {
+    let params = budget_params();
+    let state = apply(&params, &CatalogState::default(), &burst("c", 1000, 3)).unwrap();
+
+    let next_hour = make_delta("c-next", [1u8; 32], 1000 + 60 * MINUTE_MS);
+    let state = apply(&params, &state, &[next_hour]).unwrap();
+    assert_eq!(attestation_count(&state), 4);
+    assert!(validate(&params, &state));
+}
+
+#[test]
+fn reattesting_same_variant_uses_no_budget() {
+    let params = budget_params();
+    let state = apply(&params, &CatalogState::default(), &burst("c", 1000, 3)).unwrap();
+
+    let again = make_delta("c-0", [1u8; 32], 1000 + 30 * MINUTE_MS);
+    let state = apply(&params, &state, &[again]).unwrap();
+    assert_eq!(attestation_count(&state), 3);
+}
+
+#[test]
+fn batch_over_budget_rejected() {
+    let deltas = burst("c", 1000, 4);
+    let batch = vec![UpdateData::Delta(StateDelta::from(serialize(&deltas)))];
+    assert!(update(&budget_params(), &CatalogState::default(), batch).is_err());
+}
+
+#[test]
+fn state_over_budget_invalid() {
+    let state = apply(
+        &unlimited_params(),
+        &CatalogState::default(),
+        &burst("c", 1000, 4),
+    )
+    .unwrap();
+    assert!(validate(&unlimited_params(), &state));
+    assert!(!validate(&budget_params(), &state));
+
+    // Nor is it accepted as a merge or sync delta
+    let merge = vec![UpdateData::State(serialize(&state).into())];
+    assert!(update(&budget_params(), &CatalogState::default(), merge).is_err());
+    let sync = CatalogSyncDelta {
+        entries: state.entries.clone(),
+        rotations: Vec::new(),
+    };
+    assert!(update(
+        &budget_params(),
+        &CatalogState::default(),
+        vec![delta_update(&sync)]
+    )
+    .is_err());
+}
+
+#[test]
+fn merged_states_trimmed_to_budget() {
+    let params = budget_params();
+    let a = apply(&params, &CatalogState::default(), &burst("a", 1000, 2)).unwrap();
+    let b = apply(
+        &params,
+        &CatalogState::default(),
+        &burst("b", 1000 + 30 * MINUTE_MS, 2),
+    )
+    .unwrap();
+
+    let ab = update(&params, &a, vec![UpdateData::State(serialize(&b).into())]).unwrap();
+    let ba = update(&params, &b, vec![UpdateData::State(serialize(&a).into())]).unwrap();
+    assert_eq!(ab, ba);
+    assert_eq!(attestation_count(&ab), 3);
+    assert!(validate(&params, &ab));
+
+    // The latest attestation is the one dropped
+    assert!(ab.entries["b-1"]
+        .hash_variants
+        .values()
+        .all(|v| v.attestations.is_empty()));
+}
+
+#[test]
+fn backdated_attestations_kept_out_of_budget_windows() {
+    let params = CatalogParameters {
+        max_clock_skew_mins: 60,
+        ..budget_params()
+    };
+    let now = 24 * 60 * MINUTE_MS;
+    let quorum: Vec<CatalogDelta> = (1..=3u8)
+        .map(|n| make_delta("c-now", [n; 32], now))
+        .collect();
+    let local = apply(&params, &CatalogState::default(), &quorum).unwrap();
+
+    // Windows hours back have budget to spare, but are out of reach
+    let backdated = burst("c-old", now - 10 * 60 * MINUTE_MS, 3);
+    assert!(apply(&params, &local, &backdated[..1]).is_err());
+
+    // A peer that took them passes them on with the rest of its state; the rest
+    // merges while the backdated ones stay out of the contributor's windows
+    let mut peer_deltas = backdated;
+    peer_deltas.push(make_delta("c-next", [2u8; 32], now));
+    let peer = apply(&budget_params(), &CatalogState::default(), &peer_deltas).unwrap();
+    let merge = vec![UpdateData::State(serialize(&peer).into())];
+    let merged = update(&params, &local, merge).unwrap();
+    assert_eq!(attestation_count(&merged), 4);
+    assert!(merged
+        .entries
+        .values()
+        .flat_map(|e| e.hash_variants.values())
+        .flat_map(|v| &v.attestations)
+        .all(|a| a.token_created_at == now));
+
+    // What the node already holds merges as before
+    let again = vec![UpdateData::State(serialize(&merged).into())];
+    assert_eq!(update(&params, &merged, again).unwrap(), merged);
+}
+
+#[test]
+fn budget_defaults_when_absent() {
+    // Parameters serialized before the attestation budget existed
+    #[derive(serde::Serialize)]
+    struct LegacyParams {
+        protocol_version: u16,
+        shard_count: u8,
+        confirmation_weight_threshold: u32,
+        entry_ttl_days: u16,
+    }
+    let legacy = serialize(&LegacyParams {
+        protocol_version: 1,
+        shard_count: 16,
+        confirmation_weight_threshold: 3,
+        entry_ttl_days: 90,
+    });
+    let params: CatalogParameters = ciborium::de::from_reader(legacy.as_slice()).unwrap();
+    assert_eq!(params.max_attestations_per_window, 0);
+    assert_eq!(params.attestation_window_mins, 60);
+}
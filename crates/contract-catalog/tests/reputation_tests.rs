@@ -1,4 +1,5 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
 use search_common::types::*;
 
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
@@ -17,34 +18,68 @@ fn default_params() -> CatalogParameters {
         shard_count: 16,
         confirmation_weight_threshold: 3,
         entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 0,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
     }
 }
 
-fn make_delta(contract_key: &str, pubkey: [u8; 32]) -> CatalogDelta {
+fn make_delta(contract_key: &str, secret: [u8; 32]) -> CatalogDelta {
     let title = format!("Title for {}", contract_key);
     let description = format!("Description for {}", contract_key);
     let snippet = format!("Snippet for {}", contract_key);
-    let hash = search_common::hashing::metadata_hash(&title, &description, &snippet);
+    let hash = search_common::hashing::metadata_hash(&title, &description, "mini", &snippet);
 
-    CatalogDelta {
+    let mut delta = CatalogDelta {
         contract_key: contract_key.to_string(),
         title,
         description,
         mini_snippet: "mini".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash(&snippet),
         snippet,
         size_bytes: 1024,
         version: Some(1),
         metadata_hash: hash,
         attestation: Attestation {
-            contributor_pubkey: pubkey,
+            contributor_pubkey: public_key(&secret),
             antiflood_token: AntifloodToken {
-                nonce: vec![0u8; 8],
-                difficulty: 16,
+                nonce: vec![],
+                difficulty: 0,
             },
             token_created_at: 1000,
             weight: 1,
+            signature: vec![],
         },
-    }
+    };
+    seal(&mut delta, &secret);
+    delta
+}
+
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
+/// Mine the antiflood token and sign the attestation for the delta's current payload.
+fn seal(delta: &mut CatalogDelta, secret: &[u8; 32]) {
+    let challenge = antiflood::attestation_challenge(
+        &delta.attestation.contributor_pubkey,
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+    );
+    delta.attestation.antiflood_token = antiflood::mine_token(&challenge, 8);
+    let message = search_common::hashing::attestation_message(
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+        &delta.attestation.antiflood_token.nonce,
+    );
+    delta.attestation.signature = delegate_identity::sign_data(secret, &message).to_vec();
 }
 
 fn apply_deltas(
@@ -80,8 +115,9 @@ fn new_contributor_zero_trust() {
     let state = CatalogState::default();
     let params = default_params();
 
-    let pubkey = [1u8; 32];
-    let delta = make_delta("contract-new", pubkey);
+    let secret = [1u8; 32];
+    let pubkey = public_key(&secret);
+    let delta = make_delta("contract-new", secret);
     let new_state = apply_deltas(&state, &params, &[delta]);
 
     // Contributor should exist with zero trust (entry not yet confirmed)
@@ -96,8 +132,7 @@ fn trust_incremented_on_confirmation() {
     let state = CatalogState::default();
     let params = default_params();
 
-    let pubkey = [1u8; 32];
-    let mut deltas = vec![make_delta("contract-trust", pubkey)];
+    let mut deltas = vec![make_delta("contract-trust", [1u8; 32])];
     // Add more contributors to cross confirmation threshold
     for i in 1..3 {
         let mut pk = [0u8; 32];
@@ -117,7 +152,7 @@ fn trust_incremented_on_confirmation() {
     for i in 0..3 {
         let mut pk = [0u8; 32];
         pk[0] = (i + 1) as u8;
-        if let Some(score) = new_state.contributors.get(&pk) {
+        if let Some(score) = new_state.contributors.get(&public_key(&pk)) {
             assert!(
                 score.trust_score > 0,
                 "contributor {} should have trust > 0",
@@ -127,77 +162,232 @@ fn trust_incremented_on_confirmation() {
     }
 }
 
-#[test]
-fn weight_reflects_trust_score() {
-    let mut state = CatalogState::default();
-    let params = default_params();
+/// A delta attesting `title` for `contract_key` instead of the default metadata.
+fn make_variant_delta(contract_key: &str, secret: [u8; 32], title: &str) -> CatalogDelta {
+    let mut delta = make_delta(contract_key, secret);
+    delta.title = title.to_string();
+    delta.metadata_hash = search_common::hashing::metadata_hash(
+        &delta.title,
+        &delta.description,
+        &delta.mini_snippet,
+        &delta.snippet,
+    );
+    seal(&mut delta, &secret);
+    delta
+}
 
-    let pubkey = [1u8; 32];
-    state.contributors.insert(
-        pubkey,
-        ContributorScore {
-            pubkey,
-            trust_score: 10,
-            total_contributions: 20,
-        },
+fn secret(n: u8) -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    secret[0] = n;
+    secret
+}
+
+fn trust_of(state: &CatalogState, n: u8) -> u32 {
+    state
+        .contributors
+        .get(&public_key(&secret(n)))
+        .map_or(0, |c| c.trust_score)
+}
+
+/// Contributors 1-3 confirm `contract_key`.
+fn confirm(contract_key: &str) -> Vec<CatalogDelta> {
+    (1..=3)
+        .map(|n| make_delta(contract_key, secret(n)))
+        .collect()
+}
+
+/// Contributors 1-4 attest the default metadata for `contract_key` and
+/// `losers` attest a competing title, leaving the entry disputed.
+fn dispute(contract_key: &str, losers: &[u8]) -> Vec<CatalogDelta> {
+    let mut deltas: Vec<CatalogDelta> = (1..=4)
+        .map(|n| make_delta(contract_key, secret(n)))
+        .collect();
+    deltas.extend(
+        losers
+            .iter()
+            .map(|n| make_variant_delta(contract_key, secret(*n), "Competing Title")),
     );
+    deltas
+}
 
-    let delta = make_delta("contract-wt", pubkey);
-    let new_state = apply_deltas(&state, &params, &[delta]);
+#[test]
+fn weight_reflects_trust_score() {
+    let params = default_params();
+    let mut deltas = confirm("contract-a");
+    deltas.extend(confirm("contract-b"));
+    deltas.push(make_delta("contract-wt", secret(1)));
+    let new_state = apply_deltas(&CatalogState::default(), &params, &deltas);
 
+    assert_eq!(trust_of(&new_state, 1), 2);
     let entry = &new_state.entries["contract-wt"];
     let variant = entry.hash_variants.values().next().unwrap();
-    // Weight = 1 + trust_score = 11
-    assert_eq!(variant.attestations[0].weight, 11);
+    // Weight = 1 + trust_score = 3
+    assert_eq!(variant.attestations[0].weight, 3);
 }
 
 #[test]
-fn trust_score_max_wins() {
+fn trust_score_not_max_merged() {
     let params = default_params();
+    let base = apply_deltas(&CatalogState::default(), &params, &confirm("contract-a"));
 
-    // Two nodes have different trust scores for the same contributor
-    let pubkey = [1u8; 32];
-
-    let mut state_low = CatalogState::default();
-    state_low.contributors.insert(
-        pubkey,
-        ContributorScore {
-            pubkey,
-            trust_score: 5,
-            total_contributions: 10,
-        },
-    );
-
-    let mut state_high = CatalogState::default();
-    state_high.contributors.insert(
+    // A peer claims a much higher score for contributor 1 than the entries support
+    let pubkey = public_key(&secret(1));
+    let mut inflated = base.clone();
+    inflated.contributors.insert(
         pubkey,
         ContributorScore {
             pubkey,
             trust_score: 8,
             total_contributions: 15,
+            first_seen: 1,
         },
     );
 
-    // CRDT merge: apply state_high as a full-state update to state_low
-    // max-wins should produce trust_score = max(5, 8) = 8
     let result = contract_catalog::Contract::update_state(
         freenet_stdlib::prelude::Parameters::from(serialize(&params)),
-        freenet_stdlib::prelude::State::from(serialize(&state_low)),
+        freenet_stdlib::prelude::State::from(serialize(&base)),
         vec![freenet_stdlib::prelude::UpdateData::State(
-            freenet_stdlib::prelude::State::from(serialize(&state_high)),
+            freenet_stdlib::prelude::State::from(serialize(&inflated)),
         )],
     )
     .expect("update_state with State merge failed");
 
     let merged: CatalogState = deserialize_state(result.unwrap_valid().as_ref());
+    assert_eq!(merged.contributors[&pubkey].trust_score, 1);
+    assert_eq!(merged.contributors[&pubkey].total_contributions, 1);
+}
 
-    if let Some(score) = merged.contributors.get(&pubkey) {
-        assert!(
-            score.trust_score >= 8,
-            "max-wins: trust_score should be >= 8, got {}",
-            score.trust_score
-        );
-    } else {
-        panic!("contributor should exist after merge");
-    }
+/// A sync delta as older peers sent it, with their contributor scores.
+#[derive(serde::Serialize)]
+struct LegacySyncDelta {
+    entries: std::collections::BTreeMap<String, CatalogEntry>,
+    contributors: std::collections::BTreeMap<[u8; 32], ContributorScore>,
+    rotations: Vec<KeyRotation>,
+}
+
+#[test]
+fn sync_delta_scores_ignored() {
+    let params = default_params();
+    let base = apply_deltas(&CatalogState::default(), &params, &confirm("contract-a"));
+
+    let pubkey = public_key(&secret(1));
+    let phantom = [42u8; 32];
+    let score = |pubkey| ContributorScore {
+        pubkey,
+        trust_score: 8,
+        total_contributions: 15,
+        first_seen: 1,
+    };
+    let legacy = LegacySyncDelta {
+        entries: base.entries.clone(),
+        contributors: [(pubkey, score(pubkey)), (phantom, score(phantom))].into(),
+        rotations: Vec::new(),
+    };
+
+    let result = contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(&params)),
+        freenet_stdlib::prelude::State::from(serialize(&base)),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(serialize(&legacy)),
+        )],
+    )
+    .expect("update_state with sync delta failed");
+
+    let merged: CatalogState = deserialize_state(result.unwrap_valid().as_ref());
+    assert_eq!(merged.contributors[&pubkey].trust_score, 1);
+    assert!(!merged.contributors.contains_key(&phantom));
+}
+
+#[test]
+fn losing_attestations_lower_trust() {
+    let params = default_params();
+    let mut deltas = confirm("contract-a");
+    deltas.extend(confirm("contract-b"));
+    let before = apply_deltas(&CatalogState::default(), &params, &deltas);
+    assert_eq!(trust_of(&before, 5), 0);
+    assert_eq!(trust_of(&before, 3), 2);
+
+    // Contributor 3 backs the losing title of a disputed entry
+    let mut disputed = dispute("contract-c", &[]);
+    disputed.retain(|d| d.attestation.contributor_pubkey != public_key(&secret(3)));
+    disputed.push(make_variant_delta(
+        "contract-c",
+        secret(3),
+        "Competing Title",
+    ));
+    disputed.push(make_variant_delta(
+        "contract-c",
+        secret(5),
+        "Competing Title",
+    ));
+    let after = apply_deltas(&before, &params, &disputed);
+
+    assert_eq!(after.entries["contract-c"].status, Status::Disputed);
+    assert_eq!(trust_of(&after, 3), 1);
+    assert_eq!(trust_of(&after, 1), 3);
+}
+
+#[test]
+fn trust_never_below_zero() {
+    let params = default_params();
+    let mut deltas = dispute("contract-a", &[6, 7]);
+    deltas.extend(dispute("contract-b", &[6, 7]));
+    let state = apply_deltas(&CatalogState::default(), &params, &deltas);
+
+    assert_eq!(state.entries["contract-a"].status, Status::Disputed);
+    assert_eq!(trust_of(&state, 6), 0);
+    let variant = state.entries["contract-a"]
+        .hash_variants
+        .values()
+        .find(|v| v.title == "Competing Title")
+        .unwrap();
+    assert!(variant.attestations.iter().all(|a| a.weight == 1));
+}
+
+#[test]
+fn tied_variants_count_for_nobody() {
+    let params = default_params();
+    let mut deltas: Vec<CatalogDelta> = (1..=3)
+        .map(|n| make_delta("contract-tie", secret(n)))
+        .collect();
+    deltas
+        .extend((4..=6).map(|n| make_variant_delta("contract-tie", secret(n), "Competing Title")));
+    let state = apply_deltas(&CatalogState::default(), &params, &deltas);
+
+    assert_eq!(state.entries["contract-tie"].status, Status::Disputed);
+    assert!((1..=6).all(|n| trust_of(&state, n) == 0));
+}
+
+#[test]
+fn falling_trust_commutes() {
+    let params = default_params();
+    let mut deltas = confirm("contract-a");
+    deltas.extend(confirm("contract-b"));
+    deltas.extend(dispute("contract-c", &[5, 6]));
+    deltas.extend(dispute("contract-d", &[3]));
+
+    let forward = apply_deltas(&CatalogState::default(), &params, &deltas);
+    let reversed: Vec<CatalogDelta> = deltas.iter().rev().cloned().collect();
+    let backward = apply_deltas(&CatalogState::default(), &params, &reversed);
+    assert_eq!(forward, backward);
+    // Contributor 3 won four entries and lost one
+    assert_eq!(trust_of(&forward, 3), 3);
+
+    // Merging a peer that has seen only the disputes lands in the same place
+    let (early, late) = deltas.split_at(6);
+    let a = apply_deltas(&CatalogState::default(), &params, early);
+    let b = apply_deltas(&CatalogState::default(), &params, late);
+    let merge = |x: &CatalogState, y: &CatalogState| -> CatalogState {
+        let result = contract_catalog::Contract::update_state(
+            freenet_stdlib::prelude::Parameters::from(serialize(&params)),
+            freenet_stdlib::prelude::State::from(serialize(x)),
+            vec![freenet_stdlib::prelude::UpdateData::State(
+                freenet_stdlib::prelude::State::from(serialize(y)),
+            )],
+        )
+        .expect("merge failed");
+        deserialize_state(result.unwrap_valid().as_ref())
+    };
+    assert_eq!(merge(&a, &b), forward);
+    assert_eq!(merge(&b, &a), forward);
 }
@@ -0,0 +1,292 @@
+use freenet_stdlib::prelude::{ContractInterface, StateDelta, UpdateData};
+use search_common::antiflood;
+use search_common::types::*;
+
+fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(val, &mut buf).unwrap();
+    buf
+}
+
+fn deserialize_state(bytes: &[u8]) -> CatalogState {
+    ciborium::de::from_reader(bytes).unwrap()
+}
+
+fn default_params() -> CatalogParameters {
+    CatalogParameters {
+        protocol_version: 1,
+        shard_count: 16,
+        confirmation_weight_threshold: 3,
+        entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 0,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
+    }
+}
+
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
+fn secret(n: u8) -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    secret[0] = n;
+    secret
+}
+
+fn make_delta(contract_key: &str, secret: [u8; 32], token_created_at: u64) -> CatalogDelta {
+    let title = format!("Title for {}", contract_key);
+    let description = format!("Description for {}", contract_key);
+    let snippet = format!("Snippet for {}", contract_key);
+    let hash = search_common::hashing::metadata_hash(&title, &description, "mini", &snippet);
+    let contributor_pubkey = public_key(&secret);
+
+    let challenge = antiflood::attestation_challenge(
+        &contributor_pubkey,
+        contract_key,
+        &hash,
+        token_created_at,
+    );
+    let antiflood_token = antiflood::mine_token(&challenge, 8);
+    let message = search_common::hashing::attestation_message(
+        contract_key,
+        &hash,
+        token_created_at,
+        &antiflood_token.nonce,
+    );
+    CatalogDelta {
+        contract_key: contract_key.to_string(),
+        title,
+        description,
+        mini_snippet: "mini".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash(&snippet),
+        snippet,
+        size_bytes: 1024,
+        version: Some(1),
+        metadata_hash: hash,
+        attestation: Attestation {
+            contributor_pubkey,
+            antiflood_token,
+            token_created_at,
+            weight: 1,
+            signature: delegate_identity::sign_data(&secret, &message).to_vec(),
+        },
+    }
+}
+
+fn rotation(old: [u8; 32], new: [u8; 32], rotated_at: u64) -> KeyRotation {
+    let old_pubkey = public_key(&old);
+    let new_pubkey = public_key(&new);
+    let message = search_common::hashing::rotation_message(&old_pubkey, &new_pubkey, rotated_at);
+    KeyRotation {
+        old_pubkey,
+        new_pubkey,
+        rotated_at,
+        signature: delegate_identity::sign_data(&old, &message).to_vec(),
+    }
+}
+
+fn update(
+    state: &CatalogState,
+    updates: Vec<UpdateData<'static>>,
+) -> Result<CatalogState, freenet_stdlib::prelude::ContractError> {
+    contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(&default_params())),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        updates,
+    )
+    .map(|result| deserialize_state(result.unwrap_valid().as_ref()))
+}
+
+fn delta_update<T: serde::Serialize>(delta: &T) -> UpdateData<'static> {
+    UpdateData::Delta(StateDelta::from(serialize(delta)))
+}
+
+fn apply(state: &CatalogState, deltas: &[CatalogDelta], rotations: &[KeyRotation]) -> CatalogState {
+    let updates = deltas
+        .iter()
+        .map(delta_update)
+        .chain(rotations.iter().map(delta_update))
+        .collect();
+    update(state, updates).expect("update_state failed")
+}
+
+fn validate(state: &CatalogState) -> bool {
+    contract_catalog::Contract::validate_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(&default_params())),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        freenet_stdlib::prelude::RelatedContracts::default(),
+    )
+    .is_ok()
+}
+
+fn weight_of(state: &CatalogState, contract_key: &str, secret: [u8; 32]) -> u32 {
+    let pubkey = public_key(&secret);
+    state.entries[contract_key]
+        .hash_variants
+        .values()
+        .flat_map(|v| &v.attestations)
+        .find(|a| a.contributor_pubkey == pubkey)
+        .unwrap()
+        .weight
+}
+
+fn trust_of(state: &CatalogState, secret: [u8; 32]) -> u32 {
+    state
+        .contributors
+        .get(&public_key(&secret))
+        .map_or(0, |c| c.trust_score)
+}
+
+/// "confirmed" attested by contributors 1-3 at time 1000.
+fn confirmed_state() -> CatalogState {
+    let deltas: Vec<CatalogDelta> = (1..=3)
+        .map(|n| make_delta("confirmed", secret(n), 1000))
+        .collect();
+    apply(&CatalogState::default(), &deltas, &[])
+}
+
+#[test]
+fn rotation_carries_history_to_successor() {
+    let state = apply(
+        &confirmed_state(),
+        &[],
+        &[rotation(secret(1), secret(9), 2000)],
+    );
+    assert!(validate(&state));
+
+    let successor = &state.contributors[&public_key(&secret(9))];
+    assert_eq!(successor.trust_score, 1);
+    assert_eq!(successor.total_contributions, 1);
+    // The old key's attestation now weighs with its successor's trust
+    assert_eq!(weight_of(&state, "confirmed", secret(1)), 2);
+    assert_eq!(state.entries["confirmed"].status, Status::Confirmed);
+
+    // The successor's own attestations carry the inherited trust
+    let state = apply(&state, &[make_delta("fresh", secret(9), 3000)], &[]);
+    assert_eq!(weight_of(&state, "fresh", secret(9)), 2);
+}
+
+#[test]
+fn rotation_chain_resolves_to_last_key() {
+    let state = apply(
+        &confirmed_state(),
+        &[],
+        &[
+            rotation(secret(1), secret(8), 2000),
+            rotation(secret(8), secret(9), 3000),
+        ],
+    );
+    assert_eq!(trust_of(&state, secret(9)), 1);
+    assert_eq!(trust_of(&state, secret(8)), 0);
+}
+
+#[test]
+fn conflicting_rotations_revoke_key() {
+    let state = apply(
+        &confirmed_state(),
+        &[],
+        &[
+            rotation(secret(1), secret(8), 2000),
+            rotation(secret(1), secret(9), 2000),
+        ],
+    );
+    assert!(validate(&state));
+
+    // Neither successor inherits, and the revoked key's attestation no longer counts
+    assert_eq!(trust_of(&state, secret(8)), 0);
+    assert_eq!(trust_of(&state, secret(9)), 0);
+    assert_eq!(weight_of(&state, "confirmed", secret(1)), 0);
+    assert_eq!(state.entries["confirmed"].status, Status::Pending);
+}
+
+#[test]
+fn old_and_new_key_count_once() {
+    let deltas = [
+        make_delta("shared", secret(1), 1000),
+        make_delta("shared", secret(9), 1000),
+        make_delta("shared", secret(2), 1000),
+    ];
+    let without_rotation = apply(&CatalogState::default(), &deltas, &[]);
+    assert_eq!(without_rotation.entries["shared"].status, Status::Confirmed);
+
+    let with_rotation = apply(
+        &CatalogState::default(),
+        &deltas,
+        &[rotation(secret(1), secret(9), 2000)],
+    );
+    assert_eq!(with_rotation.entries["shared"].status, Status::Pending);
+}
+
+#[test]
+fn attestation_after_rotation_ignored() {
+    let state = apply(
+        &CatalogState::default(),
+        &[make_delta("late", secret(1), 3000)],
+        &[rotation(secret(1), secret(9), 2000)],
+    );
+    assert_eq!(weight_of(&state, "late", secret(1)), 0);
+    assert!(!state.contributors.contains_key(&public_key(&secret(9))));
+}
+
+#[test]
+fn rotation_merge_commutes() {
+    let base = confirmed_state();
+    let a = apply(&base, &[], &[rotation(secret(1), secret(8), 2000)]);
+    let b = apply(&base, &[], &[rotation(secret(1), secret(9), 2500)]);
+
+    let ab = update(&a, vec![UpdateData::State(serialize(&b).into())]).unwrap();
+    let ba = update(&b, vec![UpdateData::State(serialize(&a).into())]).unwrap();
+    assert_eq!(ab, ba);
+    assert_eq!(ab.rotations.len(), 2);
+    assert_eq!(weight_of(&ab, "confirmed", secret(1)), 0);
+}
+
+#[test]
+fn forged_rotation_rejected() {
+    // Signed by the would-be successor instead of the old key
+    let mut forged = rotation(secret(9), secret(1), 2000);
+    forged.old_pubkey = public_key(&secret(1));
+    forged.new_pubkey = public_key(&secret(9));
+    assert!(update(&confirmed_state(), vec![delta_update(&forged)]).is_err());
+
+    let mut state = confirmed_state();
+    state.rotations.push(forged);
+    assert!(!validate(&state));
+
+    let to_self = rotation(secret(1), secret(1), 2000);
+    assert!(update(&confirmed_state(), vec![delta_update(&to_self)]).is_err());
+}
+
+#[test]
+fn rotation_syncs_to_peer() {
+    let base = confirmed_state();
+    let rotated = apply(&base, &[], &[rotation(secret(1), secret(9), 2000)]);
+    let params = serialize(&default_params());
+
+    let summary = contract_catalog::Contract::summarize_state(
+        freenet_stdlib::prelude::Parameters::from(params.clone()),
+        freenet_stdlib::prelude::State::from(serialize(&base)),
+    )
+    .unwrap();
+    let delta = contract_catalog::Contract::get_state_delta(
+        freenet_stdlib::prelude::Parameters::from(params),
+        freenet_stdlib::prelude::State::from(serialize(&rotated)),
+        summary,
+    )
+    .unwrap();
+    let sync_delta: CatalogSyncDelta = ciborium::de::from_reader(delta.as_ref()).unwrap();
+    assert_eq!(sync_delta.rotations, rotated.rotations);
+
+    let synced = update(
+        &base,
+        vec![UpdateData::Delta(StateDelta::from(delta.as_ref().to_vec()))],
+    )
+    .unwrap();
+    assert_eq!(synced, rotated);
+}
@@ -1,4 +1,5 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
 use search_common::types::*;
 
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
@@ -17,34 +18,68 @@ fn default_params() -> CatalogParameters {
         shard_count: 16,
         confirmation_weight_threshold: 3,
         entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 0,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
     }
 }
 
-fn make_delta(contract_key: &str, pubkey: [u8; 32]) -> CatalogDelta {
+fn make_delta(contract_key: &str, secret: [u8; 32]) -> CatalogDelta {
     let title = format!("Title for {}", contract_key);
     let description = format!("Description for {}", contract_key);
     let snippet = format!("Snippet for {}", contract_key);
-    let hash = search_common::hashing::metadata_hash(&title, &description, &snippet);
+    let hash = search_common::hashing::metadata_hash(&title, &description, "mini", &snippet);
 
-    CatalogDelta {
+    let mut delta = CatalogDelta {
         contract_key: contract_key.to_string(),
         title,
         description,
         mini_snippet: "mini".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash(&snippet),
         snippet,
         size_bytes: 1024,
         version: Some(1),
         metadata_hash: hash,
         attestation: Attestation {
-            contributor_pubkey: pubkey,
+            contributor_pubkey: public_key(&secret),
             antiflood_token: AntifloodToken {
-                nonce: vec![0u8; 8],
-                difficulty: 16,
+                nonce: vec![],
+                difficulty: 0,
             },
             token_created_at: 1000,
             weight: 1,
+            signature: vec![],
         },
-    }
+    };
+    seal(&mut delta, &secret);
+    delta
+}
+
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
+/// Mine the antiflood token and sign the attestation for the delta's current payload.
+fn seal(delta: &mut CatalogDelta, secret: &[u8; 32]) {
+    let challenge = antiflood::attestation_challenge(
+        &delta.attestation.contributor_pubkey,
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+    );
+    delta.attestation.antiflood_token = antiflood::mine_token(&challenge, 8);
+    let message = search_common::hashing::attestation_message(
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+        &delta.attestation.antiflood_token.nonce,
+    );
+    delta.attestation.signature = delegate_identity::sign_data(secret, &message).to_vec();
 }
 
 fn apply_deltas(
@@ -274,3 +309,264 @@ fn sync_convergence() {
     assert_eq!(state_a_synced.entries.len(), state_b_synced.entries.len());
     assert_eq!(state_a_synced, state_b_synced);
 }
+
+fn apply_sync_delta(
+    state: &CatalogState,
+    params: &CatalogParameters,
+    delta: Vec<u8>,
+) -> CatalogState {
+    let result = contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(params)),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(delta),
+        )],
+    )
+    .expect("sync delta rejected");
+    deserialize_state(result.unwrap_valid().as_ref())
+}
+
+#[test]
+fn synced_variant_keeps_originator_hash() {
+    let params = default_params();
+    let delta = make_delta("contract-0", [1u8; 32]);
+    let origin = apply_deltas(
+        &CatalogState::default(),
+        &params,
+        std::slice::from_ref(&delta),
+    );
+
+    // Sync deltas carry the mini snippet and only the full snippet's hash
+    let bytes = get_delta(
+        &origin,
+        &params,
+        &summarize(&CatalogState::default(), &params),
+    );
+    let synced: CatalogSyncDelta = ciborium::de::from_reader(bytes.as_slice()).unwrap();
+    let variant = &synced.entries["contract-0"].hash_variants[&delta.metadata_hash];
+    assert_eq!(variant.mini_snippet, delta.mini_snippet);
+    assert_eq!(
+        variant.snippet_hash,
+        search_common::hashing::snippet_hash(&delta.snippet)
+    );
+
+    let peer = apply_sync_delta(&CatalogState::default(), &params, bytes);
+    assert_eq!(peer, origin);
+    assert!(peer.entries["contract-0"]
+        .hash_variants
+        .contains_key(&delta.metadata_hash));
+}
+
+#[test]
+fn sync_delta_with_wrong_snippet_hash_rejected() {
+    let params = default_params();
+    let mut delta = make_delta("contract-0", [1u8; 32]);
+    delta.snippet = String::new();
+    delta.snippet_hash = search_common::hashing::snippet_hash("another snippet");
+    let result = contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(&params)),
+        freenet_stdlib::prelude::State::from(serialize(&CatalogState::default())),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(serialize(&delta)),
+        )],
+    );
+    assert!(result.is_err());
+}
+
+/// The per-attestation deltas sync sent before `CatalogSyncDelta`.
+fn legacy_sync_deltas(state: &CatalogState) -> Vec<CatalogDelta> {
+    let mut deltas = Vec::new();
+    for entry in state.entries.values() {
+        for (hash, variant) in &entry.hash_variants {
+            for att in &variant.attestations {
+                deltas.push(CatalogDelta {
+                    contract_key: entry.contract_key.clone(),
+                    title: variant.title.clone(),
+                    description: variant.description.clone(),
+                    mini_snippet: variant.mini_snippet.clone(),
+                    snippet: String::new(),
+                    snippet_hash: variant.snippet_hash,
+                    size_bytes: entry.size_bytes,
+                    version: entry.version,
+                    metadata_hash: *hash,
+                    attestation: att.clone(),
+                });
+            }
+        }
+    }
+    deltas
+}
+
+/// 20 entries with realistic metadata, each attested by `attestors` contributors.
+fn attested_state(attestors: u8) -> CatalogState {
+    let params = default_params();
+    let mut deltas = Vec::new();
+    for i in 0..20 {
+        for contributor in 1..=attestors {
+            let mut secret = [0u8; 32];
+            secret[0] = contributor;
+            let mut delta = make_delta(&format!("contract-{}", i), secret);
+            delta.title = format!("Title for contract-{} ", i).repeat(4);
+            delta.description = format!("Description for contract-{} ", i).repeat(10);
+            delta.mini_snippet = "m".repeat(300);
+            delta.metadata_hash = search_common::hashing::metadata_hash(
+                &delta.title,
+                &delta.description,
+                &delta.mini_snippet,
+                &delta.snippet,
+            );
+            seal(&mut delta, &secret);
+            deltas.push(delta);
+        }
+    }
+    apply_deltas(&CatalogState::default(), &params, &deltas)
+}
+
+#[test]
+fn sync_delta_size() {
+    let params = default_params();
+    let empty = summarize(&CatalogState::default(), &params);
+    // Largest sync delta allowed per attestor count, in tenths of the legacy size:
+    // metadata goes once per variant instead of once per attestation
+    for (attestors, max_tenths) in [(1u8, 11), (3, 6), (5, 5)] {
+        let state = attested_state(attestors);
+        let sync_bytes = get_delta(&state, &params, &empty).len();
+        let legacy_bytes = serialize(&legacy_sync_deltas(&state)).len();
+        assert!(
+            sync_bytes * 10 <= legacy_bytes * max_tenths,
+            "{} attestors: {} vs {} bytes",
+            attestors,
+            sync_bytes,
+            legacy_bytes
+        );
+    }
+}
+
+#[test]
+fn sync_delta_preserves_attestations() {
+    let params = default_params();
+    let origin = attested_state(3);
+    let delta = get_delta(
+        &origin,
+        &params,
+        &summarize(&CatalogState::default(), &params),
+    );
+    let synced: CatalogSyncDelta = ciborium::de::from_reader(delta.as_slice()).unwrap();
+    assert_eq!(synced.entries, origin.entries);
+
+    let peer = apply_sync_delta(&CatalogState::default(), &params, delta);
+    assert_eq!(peer, origin);
+}
+
+#[test]
+fn tampered_sync_delta_rejected() {
+    let params = default_params();
+    let origin = attested_state(1);
+    let delta = get_delta(
+        &origin,
+        &params,
+        &summarize(&CatalogState::default(), &params),
+    );
+    let synced: CatalogSyncDelta = ciborium::de::from_reader(delta.as_slice()).unwrap();
+    let rejected = |synced: &CatalogSyncDelta| {
+        contract_catalog::Contract::update_state(
+            freenet_stdlib::prelude::Parameters::from(serialize(&params)),
+            freenet_stdlib::prelude::State::from(serialize(&CatalogState::default())),
+            vec![freenet_stdlib::prelude::UpdateData::Delta(
+                freenet_stdlib::prelude::StateDelta::from(serialize(synced)),
+            )],
+        )
+        .is_err()
+    };
+    assert!(!rejected(&synced));
+
+    // Metadata no longer matching its variant hash
+    let mut tampered = synced.clone();
+    let entry = tampered.entries.get_mut("contract-0").unwrap();
+    entry.hash_variants.values_mut().next().unwrap().title = "Spam".to_string();
+    assert!(rejected(&tampered));
+
+    // A snippet that doesn't match the variant's snippet hash
+    let mut tampered = synced.clone();
+    let entry = tampered.entries.get_mut("contract-0").unwrap();
+    entry.hash_variants.values_mut().next().unwrap().snippet = "Spam".to_string();
+    assert!(rejected(&tampered));
+
+    // An attestation moved onto another contract
+    let mut tampered = synced.clone();
+    let mut entry = tampered.entries.remove("contract-0").unwrap();
+    entry.contract_key = "contract-elsewhere".to_string();
+    tampered
+        .entries
+        .insert("contract-elsewhere".to_string(), entry);
+    assert!(rejected(&tampered));
+
+    // Entry filed under a key other than its own
+    let mut tampered = synced.clone();
+    let entry = tampered.entries.remove("contract-0").unwrap();
+    tampered.entries.insert("contract-other".to_string(), entry);
+    assert!(rejected(&tampered));
+}
+
+proptest::proptest! {
+    #![proptest_config(proptest::prelude::ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn divergent_states_converge(
+        contributions in proptest::collection::vec(
+            (0u8..4, 1u8..6, 0u8..2, proptest::bool::ANY),
+            1..12,
+        )
+    ) {
+        let params = default_params();
+        let empty = CatalogState::default();
+
+        // Each contribution lands on A or B; some attest a competing title
+        let mut deltas_a = Vec::new();
+        let mut deltas_b = Vec::new();
+        let mut all = Vec::new();
+        for (contract, contributor, title, on_a) in contributions {
+            let mut secret = [0u8; 32];
+            secret[0] = contributor;
+            let mut delta = make_delta(&format!("contract-{}", contract), secret);
+            if title == 1 {
+                delta.title = "Alternate Title".to_string();
+                delta.metadata_hash = search_common::hashing::metadata_hash(
+                    &delta.title,
+                    &delta.description,
+                    &delta.mini_snippet,
+                    &delta.snippet,
+                );
+                seal(&mut delta, &secret);
+            }
+            all.push(delta.clone());
+            if on_a {
+                deltas_a.push(delta);
+            } else {
+                deltas_b.push(delta);
+            }
+        }
+        let mut state_a = apply_deltas(&empty, &params, &deltas_a);
+        let mut state_b = apply_deltas(&empty, &params, &deltas_b);
+
+        let mut rounds = 0;
+        loop {
+            let a_to_b = get_delta(&state_a, &params, &summarize(&state_b, &params));
+            let b_to_a = get_delta(&state_b, &params, &summarize(&state_a, &params));
+            if a_to_b.is_empty() && b_to_a.is_empty() {
+                break;
+            }
+            if !a_to_b.is_empty() {
+                state_b = apply_sync_delta(&state_b, &params, a_to_b);
+            }
+            if !b_to_a.is_empty() {
+                state_a = apply_sync_delta(&state_a, &params, b_to_a);
+            }
+            rounds += 1;
+            proptest::prop_assert!(rounds <= 3, "sync did not converge");
+        }
+
+        proptest::prop_assert_eq!(&state_a, &state_b);
+        proptest::prop_assert_eq!(&state_a, &apply_deltas(&empty, &params, &all));
+    }
+}
@@ -1,4 +1,5 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
 use search_common::types::*;
 
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
@@ -17,34 +18,68 @@ fn default_params() -> CatalogParameters {
         shard_count: 16,
         confirmation_weight_threshold: 3,
         entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 0,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
     }
 }
 
-fn make_delta(contract_key: &str, pubkey: [u8; 32]) -> CatalogDelta {
+fn make_delta(contract_key: &str, secret: [u8; 32]) -> CatalogDelta {
     let title = format!("Title for {}", contract_key);
     let description = format!("Description for {}", contract_key);
     let snippet = format!("Snippet for {}", contract_key);
-    let hash = search_common::hashing::metadata_hash(&title, &description, &snippet);
+    let hash = search_common::hashing::metadata_hash(&title, &description, "mini", &snippet);
 
-    CatalogDelta {
+    let mut delta = CatalogDelta {
         contract_key: contract_key.to_string(),
         title,
         description,
-        mini_snippet: "mini snippet".to_string(),
+        mini_snippet: "mini".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash(&snippet),
         snippet,
         size_bytes: 1024,
         version: Some(1),
         metadata_hash: hash,
         attestation: Attestation {
-            contributor_pubkey: pubkey,
+            contributor_pubkey: public_key(&secret),
             antiflood_token: AntifloodToken {
-                nonce: vec![0u8; 8],
-                difficulty: 16,
+                nonce: vec![],
+                difficulty: 0,
             },
             token_created_at: 1000,
             weight: 1,
+            signature: vec![],
         },
-    }
+    };
+    seal(&mut delta, &secret);
+    delta
+}
+
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
+/// Mine the antiflood token and sign the attestation for the delta's current payload.
+fn seal(delta: &mut CatalogDelta, secret: &[u8; 32]) {
+    let challenge = antiflood::attestation_challenge(
+        &delta.attestation.contributor_pubkey,
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+    );
+    delta.attestation.antiflood_token = antiflood::mine_token(&challenge, 8);
+    let message = search_common::hashing::attestation_message(
+        &delta.contract_key,
+        &delta.metadata_hash,
+        delta.attestation.token_created_at,
+        &delta.attestation.antiflood_token.nonce,
+    );
+    delta.attestation.signature = delegate_identity::sign_data(secret, &message).to_vec();
 }
 
 fn apply_deltas(
@@ -113,8 +148,13 @@ fn different_hash_creates_variant() {
     // Different content for same contract key
     let mut delta2 = make_delta("contract-b", [2u8; 32]);
     delta2.title = "Different Title".to_string();
-    delta2.metadata_hash =
-        search_common::hashing::metadata_hash(&delta2.title, &delta2.description, &delta2.snippet);
+    delta2.metadata_hash = search_common::hashing::metadata_hash(
+        &delta2.title,
+        &delta2.description,
+        &delta2.mini_snippet,
+        &delta2.snippet,
+    );
+    seal(&mut delta2, &[2u8; 32]);
 
     let new_state = apply_deltas(&state, &params, &[delta2]);
 
@@ -140,10 +180,10 @@ fn batch_multiple_deltas() {
 fn dedup_by_pubkey() {
     let state = CatalogState::default();
     let params = default_params();
-    let pubkey = [1u8; 32];
+    let secret = [1u8; 32];
 
-    let delta1 = make_delta("contract-dup", pubkey);
-    let delta2 = make_delta("contract-dup", pubkey); // same pubkey
+    let delta1 = make_delta("contract-dup", secret);
+    let delta2 = make_delta("contract-dup", secret); // same pubkey
 
     let new_state = apply_deltas(&state, &params, &[delta1, delta2]);
 
@@ -160,8 +200,12 @@ fn field_size_limits() {
     let mut delta = make_delta("contract-big", [1u8; 32]);
     delta.title = "X".repeat(300); // > 256 chars
                                    // Recompute hash with oversized title
-    delta.metadata_hash =
-        search_common::hashing::metadata_hash(&delta.title, &delta.description, &delta.snippet);
+    delta.metadata_hash = search_common::hashing::metadata_hash(
+        &delta.title,
+        &delta.description,
+        &delta.mini_snippet,
+        &delta.snippet,
+    );
 
     let state_bytes = serialize(&state);
     let params_bytes = serialize(&params);
@@ -227,9 +271,9 @@ fn status_transitions() {
     // Add 3 attestations from different contributors to cross threshold
     let deltas: Vec<CatalogDelta> = (0..3)
         .map(|i| {
-            let mut pubkey = [0u8; 32];
-            pubkey[0] = i + 1;
-            make_delta("contract-confirm", pubkey)
+            let mut secret = [0u8; 32];
+            secret[0] = i + 1;
+            make_delta("contract-confirm", secret)
         })
         .collect();
 
@@ -246,21 +290,26 @@ fn disputed_status() {
     // Create variant 1 with significant weight
     let mut deltas = Vec::new();
     for i in 0..3 {
-        let mut pubkey = [0u8; 32];
-        pubkey[0] = i + 1;
-        deltas.push(make_delta("contract-dispute", pubkey));
+        let mut secret = [0u8; 32];
+        secret[0] = i + 1;
+        deltas.push(make_delta("contract-dispute", secret));
     }
     let state = apply_deltas(&state, &params, &deltas);
 
     // Create variant 2 with different content, also significant weight
     let mut deltas2 = Vec::new();
     for i in 0..3 {
-        let mut pubkey = [0u8; 32];
-        pubkey[0] = i + 10;
-        let mut d = make_delta("contract-dispute", pubkey);
+        let mut secret = [0u8; 32];
+        secret[0] = i + 10;
+        let mut d = make_delta("contract-dispute", secret);
         d.title = "Alternate Title".to_string();
-        d.metadata_hash =
-            search_common::hashing::metadata_hash(&d.title, &d.description, &d.snippet);
+        d.metadata_hash = search_common::hashing::metadata_hash(
+            &d.title,
+            &d.description,
+            &d.mini_snippet,
+            &d.snippet,
+        );
+        seal(&mut d, &secret);
         deltas2.push(d);
     }
     let new_state = apply_deltas(&state, &params, &deltas2);
@@ -274,9 +323,10 @@ fn contributor_score_updated() {
     let state = CatalogState::default();
     let params = default_params();
 
-    let pubkey = [1u8; 32];
+    let secret = [1u8; 32];
+    let pubkey = public_key(&secret);
     // Add enough attestations to confirm
-    let mut deltas = vec![make_delta("contract-trust", pubkey)];
+    let mut deltas = vec![make_delta("contract-trust", secret)];
     for i in 1..3 {
         let mut pk = [0u8; 32];
         pk[0] = i + 1;
@@ -293,25 +343,67 @@ fn contributor_score_updated() {
 
 #[test]
 fn weight_reflects_trust() {
-    // A contributor with existing trust should have higher attestation weight
-    let mut state = CatalogState::default();
-    let pubkey = [1u8; 32];
-    state.contributors.insert(
-        pubkey,
-        ContributorScore {
-            pubkey,
-            trust_score: 5,
-            total_contributions: 10,
-        },
-    );
+    // A contributor who helped confirm an entry attests with higher weight
+    let secret = [1u8; 32];
+    let mut deltas = vec![make_delta("contract-trust", secret)];
+    for i in 1..3 {
+        let mut pk = [0u8; 32];
+        pk[0] = i + 1;
+        deltas.push(make_delta("contract-trust", pk));
+    }
+    deltas.push(make_delta("contract-weight", secret));
 
     let params = default_params();
-    let delta = make_delta("contract-weight", pubkey);
-    let new_state = apply_deltas(&state, &params, &[delta]);
+    let new_state = apply_deltas(&CatalogState::default(), &params, &deltas);
 
     let entry = &new_state.entries["contract-weight"];
     let variant = entry.hash_variants.values().next().unwrap();
     let att = &variant.attestations[0];
-    // Weight should be 1 + trust_score = 6
-    assert_eq!(att.weight, 6);
+    // Weight should be 1 + trust_score = 2
+    assert_eq!(att.weight, 2);
+}
+
+#[test]
+fn full_snippet_stored_on_variant() {
+    let state = CatalogState::default();
+    let params = default_params();
+    let delta = make_delta("contract-snip", [1u8; 32]);
+    let state = apply_deltas(&state, &params, std::slice::from_ref(&delta));
+
+    let variant = &state.entries["contract-snip"].hash_variants[&delta.metadata_hash];
+    assert_eq!(variant.snippet, delta.snippet);
+    assert_eq!(variant.mini_snippet, "mini");
+
+    // An attestation sent with only the snippet hash keeps the stored snippet
+    let mut hash_only = make_delta("contract-snip", [2u8; 32]);
+    hash_only.snippet = String::new();
+    seal(&mut hash_only, &[2u8; 32]);
+    let state = apply_deltas(&state, &params, &[hash_only]);
+    let variant = &state.entries["contract-snip"].hash_variants[&delta.metadata_hash];
+    assert_eq!(variant.attestations.len(), 2);
+    assert_eq!(variant.snippet, delta.snippet);
+}
+
+#[test]
+fn oversized_snippet_rejected() {
+    let params = default_params();
+    let mut delta = make_delta("contract-long", [1u8; 32]);
+    delta.snippet = "x".repeat(MAX_SNIPPET_LEN + 1);
+    delta.snippet_hash = search_common::hashing::snippet_hash(&delta.snippet);
+    delta.metadata_hash = search_common::hashing::metadata_hash(
+        &delta.title,
+        &delta.description,
+        &delta.mini_snippet,
+        &delta.snippet,
+    );
+    seal(&mut delta, &[1u8; 32]);
+
+    let result = contract_catalog::Contract::update_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(&params)),
+        freenet_stdlib::prelude::State::from(serialize(&CatalogState::default())),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(serialize(&delta)),
+        )],
+    );
+    assert!(result.is_err());
 }
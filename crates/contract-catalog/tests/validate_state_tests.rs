@@ -14,18 +14,39 @@ fn default_params() -> CatalogParameters {
         shard_count: 16,
         confirmation_weight_threshold: 3,
         entry_ttl_days: 90,
+        min_pow_difficulty: 8,
+        min_identity_age_days: 0,
+        max_attestations_per_window: 0,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 0,
     }
 }
 
-fn make_attestation(pubkey: [u8; 32], weight: u32) -> Attestation {
+/// Build an attestation signed by the ed25519 secret key `secret`.
+fn make_attestation(
+    secret: [u8; 32],
+    contract_key: &str,
+    hash: &[u8; 32],
+    weight: u32,
+) -> Attestation {
+    let antiflood_token = AntifloodToken {
+        nonce: vec![0u8; 8],
+        difficulty: 16,
+    };
+    let message = search_common::hashing::attestation_message(
+        contract_key,
+        hash,
+        1000,
+        &antiflood_token.nonce,
+    );
     Attestation {
-        contributor_pubkey: pubkey,
-        antiflood_token: AntifloodToken {
-            nonce: vec![0u8; 8],
-            difficulty: 16,
-        },
+        contributor_pubkey: ed25519_dalek::SigningKey::from_bytes(&secret)
+            .verifying_key()
+            .to_bytes(),
+        antiflood_token,
         token_created_at: 1000,
         weight,
+        signature: delegate_identity::sign_data(&secret, &message).to_vec(),
     }
 }
 
@@ -35,6 +56,8 @@ fn make_hash_variant(title: &str, attestations: Vec<Attestation>) -> HashVariant
         title: title.to_string(),
         description: "test description".to_string(),
         mini_snippet: "test snippet".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash("test snippet"),
+        snippet: "test snippet".to_string(),
         attestations,
         total_weight,
     }
@@ -58,10 +81,13 @@ fn valid_empty_state() {
 
 #[test]
 fn valid_state_with_entries() {
-    let pubkey = [1u8; 32];
-    let attestation = make_attestation(pubkey, 1);
-    let hash =
-        search_common::hashing::metadata_hash("Test Title", "test description", "test snippet");
+    let hash = search_common::hashing::metadata_hash(
+        "Test Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    let attestation = make_attestation([1u8; 32], "contract-abc", &hash, 1);
 
     let mut hash_variants = BTreeMap::new();
     hash_variants.insert(hash, make_hash_variant("Test Title", vec![attestation]));
@@ -82,6 +108,7 @@ fn valid_state_with_entries() {
     let state = CatalogState {
         entries,
         contributors: BTreeMap::new(),
+        rotations: Vec::new(),
     };
     let params = default_params();
 
@@ -108,10 +135,9 @@ fn invalid_cbor() {
 
 #[test]
 fn hash_mismatch() {
-    let pubkey = [1u8; 32];
-    let attestation = make_attestation(pubkey, 1);
     // Use a hash that doesn't match the title/description/snippet
     let wrong_hash = [99u8; 32];
+    let attestation = make_attestation([1u8; 32], "contract-xyz", &wrong_hash, 1);
 
     let mut hash_variants = BTreeMap::new();
     hash_variants.insert(wrong_hash, make_hash_variant("Title", vec![attestation]));
@@ -132,6 +158,7 @@ fn hash_mismatch() {
     let state = CatalogState {
         entries,
         contributors: BTreeMap::new(),
+        rotations: Vec::new(),
     };
     let params = default_params();
 
@@ -145,11 +172,14 @@ fn hash_mismatch() {
 
 #[test]
 fn duplicate_pubkeys() {
-    let pubkey = [1u8; 32];
-    let a1 = make_attestation(pubkey, 1);
-    let a2 = make_attestation(pubkey, 2); // same pubkey
-
-    let hash = search_common::hashing::metadata_hash("Title", "test description", "test snippet");
+    let hash = search_common::hashing::metadata_hash(
+        "Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    let a1 = make_attestation([1u8; 32], "dup-key", &hash, 1);
+    let a2 = make_attestation([1u8; 32], "dup-key", &hash, 2); // same pubkey
     let mut hash_variants = BTreeMap::new();
     hash_variants.insert(hash, make_hash_variant("Title", vec![a1, a2]));
 
@@ -169,6 +199,7 @@ fn duplicate_pubkeys() {
     let state = CatalogState {
         entries,
         contributors: BTreeMap::new(),
+        rotations: Vec::new(),
     };
     let params = default_params();
 
@@ -182,14 +213,20 @@ fn duplicate_pubkeys() {
 
 #[test]
 fn weight_inconsistency() {
-    let pubkey = [1u8; 32];
-    let attestation = make_attestation(pubkey, 5);
-    let hash = search_common::hashing::metadata_hash("Title", "test description", "test snippet");
+    let hash = search_common::hashing::metadata_hash(
+        "Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    let attestation = make_attestation([1u8; 32], "weight-key", &hash, 5);
 
     let variant = HashVariant {
         title: "Title".to_string(),
         description: "test description".to_string(),
         mini_snippet: "test snippet".to_string(),
+        snippet_hash: search_common::hashing::snippet_hash("test snippet"),
+        snippet: "test snippet".to_string(),
         attestations: vec![attestation],
         total_weight: 999, // Doesn't match sum of attestation weights (5)
     };
@@ -213,6 +250,7 @@ fn weight_inconsistency() {
     let state = CatalogState {
         entries,
         contributors: BTreeMap::new(),
+        rotations: Vec::new(),
     };
     let params = default_params();
 
@@ -226,9 +264,8 @@ fn weight_inconsistency() {
 
 #[test]
 fn empty_contract_key() {
-    let pubkey = [1u8; 32];
-    let attestation = make_attestation(pubkey, 1);
-    let hash = search_common::hashing::metadata_hash("Title", "desc", "snippet");
+    let hash = search_common::hashing::metadata_hash("Title", "desc", "snippet", "snippet");
+    let attestation = make_attestation([1u8; 32], "", &hash, 1);
 
     let mut hash_variants = BTreeMap::new();
     hash_variants.insert(hash, make_hash_variant("Title", vec![attestation]));
@@ -249,6 +286,7 @@ fn empty_contract_key() {
     let state = CatalogState {
         entries,
         contributors: BTreeMap::new(),
+        rotations: Vec::new(),
     };
     let params = default_params();
 
@@ -259,3 +297,95 @@ fn empty_contract_key() {
     );
     assert!(result.is_err());
 }
+
+fn single_entry_state(contract_key: &str, attestation: Attestation) -> CatalogState {
+    let hash = search_common::hashing::metadata_hash(
+        "Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    let mut hash_variants = BTreeMap::new();
+    hash_variants.insert(hash, make_hash_variant("Title", vec![attestation]));
+
+    let entry = CatalogEntry {
+        contract_key: contract_key.to_string(),
+        hash_variants,
+        size_bytes: 100,
+        version: None,
+        status: Status::Pending,
+        first_seen: 1000,
+        last_seen: 1000,
+    };
+
+    let mut entries = BTreeMap::new();
+    entries.insert(contract_key.to_string(), entry);
+    CatalogState {
+        entries,
+        contributors: BTreeMap::new(),
+        rotations: Vec::new(),
+    }
+}
+
+fn validate(state: &CatalogState) -> bool {
+    contract_catalog::Contract::validate_state(
+        freenet_stdlib::prelude::Parameters::from(serialize(&default_params())),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        freenet_stdlib::prelude::RelatedContracts::default(),
+    )
+    .is_ok()
+}
+
+#[test]
+fn signed_attestation_valid() {
+    let hash = search_common::hashing::metadata_hash(
+        "Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    let attestation = make_attestation([1u8; 32], "sig-key", &hash, 1);
+    assert!(validate(&single_entry_state("sig-key", attestation)));
+}
+
+#[test]
+fn unsigned_attestation_rejected() {
+    let hash = search_common::hashing::metadata_hash(
+        "Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    let mut attestation = make_attestation([1u8; 32], "sig-key", &hash, 1);
+    attestation.signature = vec![];
+    assert!(!validate(&single_entry_state("sig-key", attestation)));
+}
+
+#[test]
+fn attestation_signed_by_other_key_rejected() {
+    let hash = search_common::hashing::metadata_hash(
+        "Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    let mut attestation = make_attestation([1u8; 32], "sig-key", &hash, 1);
+    // Claim someone else's identity while keeping our signature
+    attestation.contributor_pubkey = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32])
+        .verifying_key()
+        .to_bytes();
+    assert!(!validate(&single_entry_state("sig-key", attestation)));
+}
+
+#[test]
+fn signature_bound_to_contract_key() {
+    let hash = search_common::hashing::metadata_hash(
+        "Title",
+        "test description",
+        "test snippet",
+        "test snippet",
+    );
+    // Signed for a different contract, moved under this one
+    let attestation = make_attestation([1u8; 32], "other-key", &hash, 1);
+    assert!(!validate(&single_entry_state("sig-key", attestation)));
+}
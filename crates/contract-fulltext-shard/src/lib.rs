@@ -2,41 +2,227 @@
 //!
 //! Maintains a sharded inverted index mapping terms to contract keys with
 //! TF-IDF scores. Words are routed to shards via SHA-256 hashing. Uses CRDT
-//! max-wins merging for scores and bloom filter sync for state propagation.
+//! max-wins merging for scores and IBLT sync for state propagation.
+//! Every posting carries its own proof-of-work and a score bounded by its term
+//! frequency, so states relayed by peers are checked like fresh deltas.
+//! Postings per word and total state size are capped by the shard parameters,
+//! with the worst-ranked postings evicted deterministically. Snippets live on
+//! catalog variants, not in postings.
 
 use freenet_stdlib::prelude::*;
-use search_common::bloom::BloomFilter;
+use search_common::antiflood;
 use search_common::hashing::shard_for_word;
+use search_common::scoring::max_tf_idf;
+use search_common::sync::{self, SummaryKind, SyncItem};
 use search_common::types::*;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 
 pub struct Contract;
 
-// NOTE: Design specifies shard_count from Parameters, but kept as const for now.
-const SHARD_COUNT: u8 = 16;
-
 fn cbor_serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
     let mut buf = Vec::new();
     ciborium::ser::into_writer(val, &mut buf).expect("CBOR serialization failed");
     buf
 }
 
-fn bloom_key(word: &str, contract_key: &str) -> Vec<u8> {
+/// Decode and sanity-check shard parameters.
+fn parse_params(parameters: &Parameters<'static>) -> Option<ShardParameters> {
+    let params: ShardParameters = ciborium::de::from_reader(parameters.as_ref()).ok()?;
+    if params.shard_count == 0 || params.shard_id >= params.shard_count {
+        return None;
+    }
+    Some(params)
+}
+
+/// Every posting in index order, with its word.
+fn postings(state: &ShardState) -> Vec<(&String, &TermEntry)> {
+    state
+        .index
+        .iter()
+        .flat_map(|(word, entries)| entries.iter().map(move |entry| (word, entry)))
+        .collect()
+}
+
+/// Summarize a shard's postings as `kind`. `summarize_state` uses IBLTs;
+/// `get_state_delta` answers every kind, as peers on older versions may send them.
+pub fn summarize_as(kind: SummaryKind, state: &ShardState) -> Vec<u8> {
+    let items: Vec<SyncItem> = postings(state)
+        .into_iter()
+        .map(|(word, entry)| sync_item(word, entry))
+        .collect();
+    sync::summarize(kind, &items)
+}
+
+/// A posting is keyed by (word, contract_key); its content covers every field
+/// a winning merge can replace.
+fn sync_item(word: &str, entry: &TermEntry) -> SyncItem {
     let mut key = Vec::new();
     key.extend_from_slice(word.as_bytes());
     key.push(0xFF);
-    key.extend_from_slice(contract_key.as_bytes());
-    key
+    key.extend_from_slice(entry.contract_key.as_bytes());
+
+    let mut content = Vec::new();
+    content.extend_from_slice(&entry.tf_idf_score.to_be_bytes());
+    content.extend_from_slice(&entry.term_frequency.to_be_bytes());
+    content.extend_from_slice(&(entry.positions.len() as u32).to_be_bytes());
+    for p in &entry.positions {
+        content.extend_from_slice(&p.to_be_bytes());
+    }
+    content.extend_from_slice(&(entry.antiflood_token.nonce.len() as u32).to_be_bytes());
+    content.extend_from_slice(&entry.antiflood_token.nonce);
+    content.push(entry.antiflood_token.difficulty);
+    SyncItem { key, content }
 }
 
-fn validate_shard_delta(state: &ShardState, delta: &ShardDelta) -> Result<(), ContractError> {
-    if delta.antiflood_token.nonce.is_empty() || delta.antiflood_token.difficulty == 0 {
-        return Err(ContractError::InvalidUpdate);
+/// Positions must be strictly ascending and within the per-posting cap.
+fn valid_positions(positions: &[u32]) -> bool {
+    positions.len() <= MAX_POSITIONS && positions.windows(2).all(|w| w[0] < w[1])
+}
+
+/// A posting filed under `word` is well-formed: term frequency and positions in
+/// range, a score no document with that term frequency could exceed, and a
+/// token meeting the shard's difficulty over the posting's payload.
+fn valid_posting(params: &ShardParameters, word: &str, entry: &TermEntry) -> bool {
+    let challenge = antiflood::posting_challenge(
+        word,
+        &entry.contract_key,
+        entry.tf_idf_score,
+        entry.term_frequency,
+        &entry.positions,
+    );
+    entry.term_frequency <= 10000
+        && entry.tf_idf_score <= max_tf_idf(entry.term_frequency)
+        && valid_positions(&entry.positions)
+        && antiflood::verify_token(
+            &entry.antiflood_token,
+            &challenge,
+            params.min_pow_difficulty,
+        )
+}
+
+/// Max-wins order for conflicting postings of the same word and contract.
+fn posting_rank(entry: &TermEntry) -> (u32, u32, &[u32]) {
+    (entry.tf_idf_score, entry.term_frequency, &entry.positions)
+}
+
+/// Max-wins order for conflicting copies of one posting: the posting rank, with
+/// the token breaking exact ties so peers holding different tokens converge.
+fn merge_rank(entry: &TermEntry) -> ((u32, u32, &[u32]), &[u8], u8) {
+    (
+        posting_rank(entry),
+        &entry.antiflood_token.nonce,
+        entry.antiflood_token.difficulty,
+    )
+}
+
+/// Merge one posting into a word's postings.
+fn merge_posting(entries: &mut Vec<TermEntry>, incoming: &TermEntry) {
+    match entries
+        .iter_mut()
+        .find(|e| e.contract_key == incoming.contract_key)
+    {
+        Some(existing) => {
+            if merge_rank(incoming) > merge_rank(existing) {
+                *existing = incoming.clone();
+            }
+        }
+        None => entries.push(incoming.clone()),
     }
+}
+
+fn term_entry(delta_entry: &ShardDeltaEntry) -> TermEntry {
+    TermEntry {
+        contract_key: delta_entry.contract_key.clone(),
+        tf_idf_score: delta_entry.tf_idf_score,
+        term_frequency: delta_entry.term_frequency,
+        positions: delta_entry.positions.clone(),
+        antiflood_token: delta_entry.antiflood_token.clone(),
+    }
+}
+
+/// Total order deciding which postings caps keep, best last: posting rank, then
+/// earlier contract keys and words win ties.
+fn retention_order(a: (&str, &TermEntry), b: (&str, &TermEntry)) -> Ordering {
+    posting_rank(a.1)
+        .cmp(&posting_rank(b.1))
+        .then_with(|| b.1.contract_key.cmp(&a.1.contract_key))
+        .then_with(|| b.0.cmp(a.0))
+}
+
+/// Enforce the shard's caps: keep each word's `max_postings_per_term` best
+/// postings, then evict the worst-ranked postings shard-wide until the state
+/// fits in `max_state_bytes`. Words left without postings go too. The result
+/// depends only on the state, so every peer prunes alike.
+fn prune(state: &mut ShardState, params: &ShardParameters) {
+    let max_postings = params.max_postings_per_term as usize;
+    if max_postings > 0 {
+        for (word, entries) in state.index.iter_mut() {
+            if entries.len() > max_postings {
+                entries.sort_by(|a, b| retention_order((word, b), (word, a)));
+                entries.truncate(max_postings);
+                entries.sort_by(|a, b| a.contract_key.cmp(&b.contract_key));
+            }
+        }
+    }
+
+    if params.max_state_bytes > 0 {
+        let size = cbor_serialize(state).len() as u64;
+        if size > params.max_state_bytes {
+            // Dropping a posting shrinks the state by at least its own encoding
+            let mut excess = size - params.max_state_bytes;
+            let mut ranked = postings(state);
+            ranked.sort_by(|a, b| retention_order((a.0, a.1), (b.0, b.1)));
+            let mut evicted: Vec<(String, String)> = Vec::new();
+            for (word, entry) in ranked {
+                if excess == 0 {
+                    break;
+                }
+                excess = excess.saturating_sub(cbor_serialize(entry).len() as u64);
+                evicted.push((word.clone(), entry.contract_key.clone()));
+            }
+            for (word, contract_key) in evicted {
+                if let Some(entries) = state.index.get_mut(&word) {
+                    entries.retain(|e| e.contract_key != contract_key);
+                }
+            }
+        }
+    }
+
+    state.index.retain(|_, entries| !entries.is_empty());
+}
+
+fn validate_shard_delta(
+    params: &ShardParameters,
+    state: &ShardState,
+    delta: &ShardDelta,
+) -> Result<(), ContractError> {
     for entry in &delta.entries {
-        if entry.word.is_empty() {
+        if entry.word.is_empty()
+            || shard_for_word(&entry.word, params.shard_count) != state.shard_id
+            || !valid_posting(params, &entry.word, &term_entry(entry))
+        {
             return Err(ContractError::InvalidUpdate);
         }
-        if shard_for_word(&entry.word, SHARD_COUNT) != state.shard_id {
+    }
+    Ok(())
+}
+
+/// Check that a state received for merging belongs to this shard, only holds
+/// words routed here, and that every posting would pass as a fresh delta.
+fn validate_incoming_state(
+    params: &ShardParameters,
+    state: &ShardState,
+    other: &ShardState,
+) -> Result<(), ContractError> {
+    if other.shard_id != state.shard_id {
+        return Err(ContractError::InvalidUpdate);
+    }
+    for (word, entries) in &other.index {
+        if word.is_empty() || shard_for_word(word, params.shard_count) != state.shard_id {
+            return Err(ContractError::InvalidUpdate);
+        }
+        if !entries.iter().all(|e| valid_posting(params, word, e)) {
             return Err(ContractError::InvalidUpdate);
         }
     }
@@ -46,21 +232,7 @@ fn validate_shard_delta(state: &ShardState, delta: &ShardDelta) -> Result<(), Co
 fn apply_shard_delta(state: &mut ShardState, delta: &ShardDelta) {
     for delta_entry in &delta.entries {
         let entries = state.index.entry(delta_entry.word.clone()).or_default();
-        if let Some(existing) = entries
-            .iter_mut()
-            .find(|e| e.contract_key == delta_entry.contract_key)
-        {
-            if delta_entry.tf_idf_score > existing.tf_idf_score {
-                existing.tf_idf_score = delta_entry.tf_idf_score;
-                existing.snippet = delta_entry.snippet.clone();
-            }
-        } else {
-            entries.push(TermEntry {
-                contract_key: delta_entry.contract_key.clone(),
-                snippet: delta_entry.snippet.clone(),
-                tf_idf_score: delta_entry.tf_idf_score,
-            });
-        }
+        merge_posting(entries, &term_entry(delta_entry));
         entries.sort_by(|a, b| a.contract_key.cmp(&b.contract_key));
     }
 }
@@ -69,17 +241,7 @@ fn merge_shard_states(a: &mut ShardState, b: &ShardState) {
     for (word, b_entries) in &b.index {
         let a_entries = a.index.entry(word.clone()).or_default();
         for b_entry in b_entries {
-            if let Some(existing) = a_entries
-                .iter_mut()
-                .find(|e| e.contract_key == b_entry.contract_key)
-            {
-                if b_entry.tf_idf_score > existing.tf_idf_score {
-                    existing.tf_idf_score = b_entry.tf_idf_score;
-                    existing.snippet = b_entry.snippet.clone();
-                }
-            } else {
-                a_entries.push(b_entry.clone());
-            }
+            merge_posting(a_entries, b_entry);
         }
         a_entries.sort_by(|a, b| a.contract_key.cmp(&b.contract_key));
     }
@@ -88,20 +250,32 @@ fn merge_shard_states(a: &mut ShardState, b: &ShardState) {
 #[contract]
 impl ContractInterface for Contract {
     fn validate_state(
-        _parameters: Parameters<'static>,
+        parameters: Parameters<'static>,
         state: State<'static>,
         _related: RelatedContracts<'static>,
     ) -> Result<ValidateResult, ContractError> {
+        let params = parse_params(&parameters).ok_or(ContractError::InvalidState)?;
         let shard_state: ShardState =
             ciborium::de::from_reader(state.as_ref()).map_err(|_| ContractError::InvalidState)?;
 
+        if shard_state.shard_id != params.shard_id {
+            return Err(ContractError::InvalidState);
+        }
+        if params.max_state_bytes > 0 && state.as_ref().len() as u64 > params.max_state_bytes {
+            return Err(ContractError::InvalidState);
+        }
         for (word, entries) in &shard_state.index {
-            if shard_for_word(word, SHARD_COUNT) != shard_state.shard_id {
+            if shard_for_word(word, params.shard_count) != shard_state.shard_id {
+                return Err(ContractError::InvalidState);
+            }
+            if params.max_postings_per_term > 0
+                && entries.len() > params.max_postings_per_term as usize
+            {
                 return Err(ContractError::InvalidState);
             }
             let mut seen_keys = std::collections::HashSet::new();
             for entry in entries {
-                if !seen_keys.insert(&entry.contract_key) {
+                if !seen_keys.insert(&entry.contract_key) || !valid_posting(&params, word, entry) {
                     return Err(ContractError::InvalidState);
                 }
             }
@@ -111,12 +285,16 @@ impl ContractInterface for Contract {
     }
 
     fn update_state(
-        _parameters: Parameters<'static>,
+        parameters: Parameters<'static>,
         state: State<'static>,
         data: Vec<UpdateData<'static>>,
     ) -> Result<UpdateModification<'static>, ContractError> {
+        let params = parse_params(&parameters).ok_or(ContractError::InvalidUpdate)?;
         let mut shard_state: ShardState =
             ciborium::de::from_reader(state.as_ref()).map_err(|_| ContractError::InvalidUpdate)?;
+        if shard_state.shard_id != params.shard_id {
+            return Err(ContractError::InvalidUpdate);
+        }
 
         for update in &data {
             match update {
@@ -124,15 +302,21 @@ impl ContractInterface for Contract {
                     if let Ok(delta) =
                         ciborium::de::from_reader::<ShardDelta, _>(delta_bytes.as_ref())
                     {
-                        validate_shard_delta(&shard_state, &delta)?;
+                        validate_shard_delta(&params, &shard_state, &delta)?;
                         apply_shard_delta(&mut shard_state, &delta);
                     } else if let Ok(deltas) =
                         ciborium::de::from_reader::<Vec<ShardDelta>, _>(delta_bytes.as_ref())
                     {
                         for delta in &deltas {
-                            validate_shard_delta(&shard_state, delta)?;
+                            validate_shard_delta(&params, &shard_state, delta)?;
                             apply_shard_delta(&mut shard_state, delta);
                         }
+                    } else if let Ok(partial) =
+                        ciborium::de::from_reader::<ShardState, _>(delta_bytes.as_ref())
+                    {
+                        // Sync delta from get_state_delta: a partial state merged like a full one.
+                        validate_incoming_state(&params, &shard_state, &partial)?;
+                        merge_shard_states(&mut shard_state, &partial);
                     } else {
                         return Err(ContractError::InvalidUpdate);
                     }
@@ -140,12 +324,14 @@ impl ContractInterface for Contract {
                 UpdateData::State(state_bytes) => {
                     let other_state: ShardState = ciborium::de::from_reader(state_bytes.as_ref())
                         .map_err(|_| ContractError::InvalidUpdate)?;
+                    validate_incoming_state(&params, &shard_state, &other_state)?;
                     merge_shard_states(&mut shard_state, &other_state);
                 }
                 _ => {}
             }
         }
 
+        prune(&mut shard_state, &params);
         let new_state_bytes = cbor_serialize(&shard_state);
         Ok(UpdateModification::valid(State::from(new_state_bytes)))
     }
@@ -157,15 +343,10 @@ impl ContractInterface for Contract {
         let shard_state: ShardState =
             ciborium::de::from_reader(state.as_ref()).map_err(|_| ContractError::InvalidState)?;
 
-        let mut bloom = BloomFilter::new(8192);
-        for (word, entries) in &shard_state.index {
-            for entry in entries {
-                let key = bloom_key(word, &entry.contract_key);
-                bloom.insert(&key);
-            }
-        }
-
-        Ok(StateSummary::from(bloom.to_bytes()))
+        Ok(StateSummary::from(summarize_as(
+            SummaryKind::Iblt,
+            &shard_state,
+        )))
     }
 
     fn get_state_delta(
@@ -176,35 +357,33 @@ impl ContractInterface for Contract {
         let shard_state: ShardState =
             ciborium::de::from_reader(state.as_ref()).map_err(|_| ContractError::InvalidState)?;
 
-        let bloom = BloomFilter::from_bytes(summary.as_ref()).ok_or(ContractError::InvalidState)?;
-
-        let mut missing_entries: Vec<ShardDeltaEntry> = Vec::new();
+        let postings = postings(&shard_state);
+        let items: Vec<SyncItem> = postings
+            .iter()
+            .map(|(word, entry)| sync_item(word, entry))
+            .collect();
+        let missing_items =
+            sync::missing_items(summary.as_ref(), &items).ok_or(ContractError::InvalidState)?;
 
-        for (word, entries) in &shard_state.index {
-            for entry in entries {
-                let key = bloom_key(word, &entry.contract_key);
-                if !bloom.contains(&key) {
-                    missing_entries.push(ShardDeltaEntry {
-                        word: word.clone(),
-                        contract_key: entry.contract_key.clone(),
-                        snippet: entry.snippet.clone(),
-                        tf_idf_score: entry.tf_idf_score,
-                    });
-                }
-            }
+        // Postings carry their own proof-of-work, so sync deltas are partial
+        // states holding only the postings missing from the summary.
+        let mut missing = ShardState {
+            shard_id: shard_state.shard_id,
+            index: BTreeMap::new(),
+        };
+        for i in missing_items {
+            let (word, entry) = postings[i];
+            missing
+                .index
+                .entry(word.clone())
+                .or_default()
+                .push(entry.clone());
         }
 
-        if missing_entries.is_empty() {
+        if missing.index.is_empty() {
             Ok(StateDelta::from(vec![]))
         } else {
-            let delta = ShardDelta {
-                entries: missing_entries,
-                antiflood_token: AntifloodToken {
-                    nonce: vec![0u8; 8],
-                    difficulty: 1,
-                },
-            };
-            Ok(StateDelta::from(cbor_serialize(&delta)))
+            Ok(StateDelta::from(cbor_serialize(&missing)))
         }
     }
 }
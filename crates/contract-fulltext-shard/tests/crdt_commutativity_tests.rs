@@ -1,24 +1,51 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
+use search_common::tokenization::Analyzer;
 use search_common::types::*;
 use std::collections::BTreeMap;
 
+const POW_DIFFICULTY: u8 = 8;
+
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
     let mut buf = Vec::new();
     ciborium::ser::into_writer(val, &mut buf).unwrap();
     buf
 }
 
+fn shard_params(shard_id: u8) -> freenet_stdlib::prelude::Parameters<'static> {
+    let params = ShardParameters {
+        shard_id,
+        shard_count: 16,
+        protocol_version: 1,
+        analyzer: Analyzer::default(),
+        max_postings_per_term: DEFAULT_MAX_POSTINGS_PER_TERM,
+        max_state_bytes: DEFAULT_MAX_SHARD_BYTES,
+        min_pow_difficulty: POW_DIFFICULTY,
+    };
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&params, &mut buf).unwrap();
+    freenet_stdlib::prelude::Parameters::from(buf)
+}
+
 fn deserialize_state(bytes: &[u8]) -> ShardState {
     ciborium::de::from_reader(bytes).unwrap()
 }
 
+fn mine(mut entry: ShardDeltaEntry) -> ShardDeltaEntry {
+    let challenge = antiflood::posting_challenge(
+        &entry.word,
+        &entry.contract_key,
+        entry.tf_idf_score,
+        entry.term_frequency,
+        &entry.positions,
+    );
+    entry.antiflood_token = antiflood::mine_token(&challenge, POW_DIFFICULTY);
+    entry
+}
+
 fn make_shard_delta(entries: Vec<ShardDeltaEntry>) -> ShardDelta {
     ShardDelta {
-        entries,
-        antiflood_token: AntifloodToken {
-            nonce: vec![0u8; 8],
-            difficulty: 16,
-        },
+        entries: entries.into_iter().map(mine).collect(),
     }
 }
 
@@ -27,7 +54,7 @@ fn apply_delta(state: &ShardState, delta: &ShardDelta) -> ShardState {
     let delta_bytes = serialize(delta);
 
     let result = contract_fulltext_shard::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         vec![freenet_stdlib::prelude::UpdateData::Delta(
             freenet_stdlib::prelude::StateDelta::from(delta_bytes),
@@ -85,14 +112,18 @@ fn word_ordering_commutes() {
     let d1 = make_shard_delta(vec![ShardDeltaEntry {
         word: words[0].clone(),
         contract_key: "c1".to_string(),
-        snippet: "s1".to_string(),
         tf_idf_score: 1000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
     let d2 = make_shard_delta(vec![ShardDeltaEntry {
         word: words[1].clone(),
         contract_key: "c2".to_string(),
-        snippet: "s2".to_string(),
         tf_idf_score: 2000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let ab = apply_deltas_seq(&state, &[d1.clone(), d2.clone()]);
@@ -115,14 +146,18 @@ fn score_conflicts_commute() {
     let d1 = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "contract-a".to_string(),
-        snippet: "snippet1".to_string(),
         tf_idf_score: 3000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
     let d2 = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "contract-a".to_string(),
-        snippet: "snippet2".to_string(),
         tf_idf_score: 5000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let ab = apply_deltas_seq(&state, &[d1.clone(), d2.clone()]);
@@ -133,6 +168,36 @@ fn score_conflicts_commute() {
     assert_eq!(ab.index[&word][0].tf_idf_score, 5000);
 }
 
+#[test]
+fn term_frequency_follows_winning_score() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+
+    let entry = |score: u32, tf: u32| ShardDeltaEntry {
+        word: word.clone(),
+        contract_key: "contract-a".to_string(),
+        tf_idf_score: score,
+        term_frequency: tf,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
+    };
+    let low = make_shard_delta(vec![entry(3000, 900)]);
+    let high = make_shard_delta(vec![entry(5000, 200)]);
+    let tied = make_shard_delta(vec![entry(5000, 400)]);
+
+    let abc = apply_deltas_seq(&state, &[low.clone(), high.clone(), tied.clone()]);
+    let cba = apply_deltas_seq(&state, &[tied, high, low]);
+
+    assert_eq!(abc, cba);
+    // Equal scores fall back to the higher term frequency
+    assert_eq!(abc.index[&word][0].tf_idf_score, 5000);
+    assert_eq!(abc.index[&word][0].term_frequency, 400);
+}
+
 #[test]
 fn mixed_words_commute() {
     let shard_id = 0u8;
@@ -147,21 +212,27 @@ fn mixed_words_commute() {
         ShardDeltaEntry {
             word: words[0].clone(),
             contract_key: "c1".to_string(),
-            snippet: "s1".to_string(),
             tf_idf_score: 1000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         },
         ShardDeltaEntry {
             word: words[1].clone(),
             contract_key: "c2".to_string(),
-            snippet: "s2".to_string(),
             tf_idf_score: 2000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         },
     ]);
     let d2 = make_shard_delta(vec![ShardDeltaEntry {
         word: words[2].clone(),
         contract_key: "c3".to_string(),
-        snippet: "s3".to_string(),
         tf_idf_score: 3000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let ab = apply_deltas_seq(&state, &[d1.clone(), d2.clone()]);
@@ -183,8 +254,10 @@ fn dedup_commutes() {
     let entry = ShardDeltaEntry {
         word: word.clone(),
         contract_key: "contract-dup".to_string(),
-        snippet: "same".to_string(),
         tf_idf_score: 1000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     };
 
     let d1 = make_shard_delta(vec![entry.clone()]);
@@ -211,20 +284,26 @@ fn multi_entry_per_word_commutes() {
     let d1 = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "c1".to_string(),
-        snippet: "s1".to_string(),
         tf_idf_score: 1000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
     let d2 = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "c2".to_string(),
-        snippet: "s2".to_string(),
         tf_idf_score: 2000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
     let d3 = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "c3".to_string(),
-        snippet: "s3".to_string(),
         tf_idf_score: 3000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let abc = apply_deltas_seq(&state, &[d1.clone(), d2.clone(), d3.clone()]);
@@ -250,8 +329,10 @@ fn stress_100_words() {
             make_shard_delta(vec![ShardDeltaEntry {
                 word: word.clone(),
                 contract_key: format!("c{}", i),
-                snippet: format!("s{}", i),
                 tf_idf_score: (i as u32 + 1) * 100,
+                term_frequency: 10000,
+                positions: vec![],
+                antiflood_token: AntifloodToken::default(),
             }])
         })
         .collect();
@@ -0,0 +1,234 @@
+use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
+use search_common::tokenization::Analyzer;
+use search_common::types::*;
+
+const POW_DIFFICULTY: u8 = 8;
+
+fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(val, &mut buf).unwrap();
+    buf
+}
+
+fn capped_params(
+    max_postings_per_term: u32,
+    max_state_bytes: u64,
+) -> freenet_stdlib::prelude::Parameters<'static> {
+    let params = ShardParameters {
+        shard_id: 0,
+        shard_count: 16,
+        protocol_version: 1,
+        analyzer: Analyzer::default(),
+        max_postings_per_term,
+        max_state_bytes,
+        min_pow_difficulty: POW_DIFFICULTY,
+    };
+    freenet_stdlib::prelude::Parameters::from(serialize(&params))
+}
+
+fn deserialize_state(bytes: &[u8]) -> ShardState {
+    ciborium::de::from_reader(bytes).unwrap()
+}
+
+fn mine(mut entry: ShardDeltaEntry) -> ShardDeltaEntry {
+    let challenge = antiflood::posting_challenge(
+        &entry.word,
+        &entry.contract_key,
+        entry.tf_idf_score,
+        entry.term_frequency,
+        &entry.positions,
+    );
+    entry.antiflood_token = antiflood::mine_token(&challenge, POW_DIFFICULTY);
+    entry
+}
+
+fn make_shard_delta(entries: Vec<ShardDeltaEntry>) -> ShardDelta {
+    ShardDelta {
+        entries: entries.into_iter().map(mine).collect(),
+    }
+}
+
+fn apply(
+    params: &freenet_stdlib::prelude::Parameters<'static>,
+    state: &ShardState,
+    updates: Vec<freenet_stdlib::prelude::UpdateData<'static>>,
+) -> ShardState {
+    let result = contract_fulltext_shard::Contract::update_state(
+        params.clone(),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        updates,
+    )
+    .expect("update_state failed");
+    deserialize_state(result.unwrap_valid().as_ref())
+}
+
+fn delta_update(entries: Vec<ShardDeltaEntry>) -> freenet_stdlib::prelude::UpdateData<'static> {
+    freenet_stdlib::prelude::UpdateData::Delta(freenet_stdlib::prelude::StateDelta::from(
+        serialize(&make_shard_delta(entries)),
+    ))
+}
+
+fn validate(params: &freenet_stdlib::prelude::Parameters<'static>, state: &ShardState) -> bool {
+    contract_fulltext_shard::Contract::validate_state(
+        params.clone(),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        freenet_stdlib::prelude::RelatedContracts::default(),
+    )
+    .is_ok()
+}
+
+fn words_for_shard(shard_id: u8, count: usize) -> Vec<String> {
+    (0..)
+        .map(|i| format!("w{}", i))
+        .filter(|w| search_common::hashing::shard_for_word(w, 16) == shard_id)
+        .take(count)
+        .collect()
+}
+
+fn posting(word: &str, contract_key: &str, score: u32) -> ShardDeltaEntry {
+    ShardDeltaEntry {
+        word: word.to_string(),
+        contract_key: contract_key.to_string(),
+        tf_idf_score: score,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
+    }
+}
+
+#[test]
+fn postings_per_term_keep_best() {
+    let params = capped_params(3, 0);
+    let word = &words_for_shard(0, 1)[0];
+    let entries = (0..6)
+        .map(|i| posting(word, &format!("c{}", i), 1000 * (i + 1)))
+        .collect();
+    let state = apply(&params, &ShardState::default(), vec![delta_update(entries)]);
+
+    let keys: Vec<&str> = state.index[word]
+        .iter()
+        .map(|e| e.contract_key.as_str())
+        .collect();
+    assert_eq!(keys, vec!["c3", "c4", "c5"]);
+    assert!(validate(&params, &state));
+}
+
+#[test]
+fn ties_keep_earlier_contract_keys() {
+    let params = capped_params(2, 0);
+    let word = &words_for_shard(0, 1)[0];
+    let entries = ["c2", "c0", "c3", "c1"]
+        .iter()
+        .map(|key| posting(word, key, 1000))
+        .collect();
+    let state = apply(&params, &ShardState::default(), vec![delta_update(entries)]);
+
+    let keys: Vec<&str> = state.index[word]
+        .iter()
+        .map(|e| e.contract_key.as_str())
+        .collect();
+    assert_eq!(keys, vec!["c0", "c1"]);
+}
+
+#[test]
+fn pruning_independent_of_arrival_order() {
+    let params = capped_params(4, 0);
+    let words = words_for_shard(0, 3);
+    let updates: Vec<Vec<ShardDeltaEntry>> = (0..6u32)
+        .map(|i| {
+            words
+                .iter()
+                .map(|w| posting(w, &format!("c{}", i), 1000 + (i * 7919) % 5000))
+                .collect()
+        })
+        .collect();
+
+    let forward = updates
+        .iter()
+        .fold(ShardState::default(), |state, entries| {
+            apply(&params, &state, vec![delta_update(entries.clone())])
+        });
+    let backward = updates
+        .iter()
+        .rev()
+        .fold(ShardState::default(), |state, entries| {
+            apply(&params, &state, vec![delta_update(entries.clone())])
+        });
+    assert_eq!(forward, backward);
+}
+
+#[test]
+fn state_bytes_capped() {
+    let max_state_bytes = 4096;
+    let params = capped_params(0, max_state_bytes);
+    let words = words_for_shard(0, 40);
+    let entries: Vec<ShardDeltaEntry> = words
+        .iter()
+        .enumerate()
+        .flat_map(|(i, w)| {
+            (0..4u32).map(move |k| posting(w, &format!("c{}", k), 1000 + (i as u32) * 10 + k))
+        })
+        .collect();
+    let scores: Vec<u32> = entries.iter().map(|e| e.tf_idf_score).collect();
+    let state = apply(&params, &ShardState::default(), vec![delta_update(entries)]);
+
+    assert!(serialize(&state).len() as u64 <= max_state_bytes);
+    assert!(validate(&params, &state));
+
+    // Only the worst-ranked postings were evicted
+    let kept: Vec<u32> = state
+        .index
+        .values()
+        .flatten()
+        .map(|e| e.tf_idf_score)
+        .collect();
+    let lowest_kept = *kept.iter().min().unwrap();
+    assert!(kept.len() < scores.len());
+    assert_eq!(
+        kept.len(),
+        scores.iter().filter(|s| **s >= lowest_kept).count()
+    );
+}
+
+#[test]
+fn merged_state_pruned() {
+    let params = capped_params(2, 0);
+    let word = &words_for_shard(0, 1)[0];
+    let entries = (0..5)
+        .map(|i| posting(word, &format!("c{}", i), 1000 * (i + 1)))
+        .collect();
+    let other = apply(
+        &capped_params(0, 0),
+        &ShardState::default(),
+        vec![delta_update(entries)],
+    );
+    let state = apply(
+        &params,
+        &ShardState::default(),
+        vec![freenet_stdlib::prelude::UpdateData::State(
+            freenet_stdlib::prelude::State::from(serialize(&other)),
+        )],
+    );
+    assert_eq!(state.index[word].len(), 2);
+    assert_eq!(state.index[word][0].contract_key, "c3");
+}
+
+#[test]
+fn validate_enforces_caps() {
+    let word = &words_for_shard(0, 1)[0];
+    let entries = (0..3)
+        .map(|i| posting(word, &format!("c{}", i), 1000))
+        .collect();
+    let state = apply(
+        &capped_params(0, 0),
+        &ShardState::default(),
+        vec![delta_update(entries)],
+    );
+
+    assert!(validate(&capped_params(3, 0), &state));
+    assert!(!validate(&capped_params(2, 0), &state));
+    let size = serialize(&state).len() as u64;
+    assert!(validate(&capped_params(0, size), &state));
+    assert!(!validate(&capped_params(0, size - 1), &state));
+}
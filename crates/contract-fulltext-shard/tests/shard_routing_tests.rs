@@ -1,5 +1,39 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
 use search_common::hashing::shard_for_word;
+use search_common::tokenization::Analyzer;
+use search_common::types::{
+    ShardParameters, TermEntry, DEFAULT_MAX_POSTINGS_PER_TERM, DEFAULT_MAX_SHARD_BYTES,
+};
+
+const POW_DIFFICULTY: u8 = antiflood::DEFAULT_POSTING_DIFFICULTY;
+
+/// A posting filed under `word`, with its proof-of-work mined.
+fn posting(word: &str, contract_key: &str, tf_idf_score: u32) -> TermEntry {
+    let challenge = antiflood::posting_challenge(word, contract_key, tf_idf_score, 10000, &[]);
+    TermEntry {
+        contract_key: contract_key.to_string(),
+        tf_idf_score,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: antiflood::mine_token(&challenge, POW_DIFFICULTY),
+    }
+}
+
+fn shard_params(shard_id: u8) -> freenet_stdlib::prelude::Parameters<'static> {
+    let params = ShardParameters {
+        shard_id,
+        shard_count: 16,
+        protocol_version: 1,
+        analyzer: Analyzer::default(),
+        max_postings_per_term: DEFAULT_MAX_POSTINGS_PER_TERM,
+        max_state_bytes: DEFAULT_MAX_SHARD_BYTES,
+        min_pow_difficulty: POW_DIFFICULTY,
+    };
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&params, &mut buf).unwrap();
+    freenet_stdlib::prelude::Parameters::from(buf)
+}
 
 #[test]
 fn routing_consistent() {
@@ -42,14 +76,7 @@ fn shard_id_matches_validation() {
 
     // State with correct shard_id should validate
     let mut index = BTreeMap::new();
-    index.insert(
-        word.to_string(),
-        vec![TermEntry {
-            contract_key: "c1".to_string(),
-            snippet: "s1".to_string(),
-            tf_idf_score: 1000,
-        }],
-    );
+    index.insert(word.to_string(), vec![posting(word, "c1", 1000)]);
 
     let correct_state = ShardState {
         shard_id: correct_shard,
@@ -60,7 +87,7 @@ fn shard_id_matches_validation() {
     ciborium::ser::into_writer(&correct_state, &mut buf).unwrap();
 
     let result = contract_fulltext_shard::Contract::validate_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(correct_state.shard_id),
         freenet_stdlib::prelude::State::from(buf),
         freenet_stdlib::prelude::RelatedContracts::default(),
     );
@@ -77,9 +104,104 @@ fn shard_id_matches_validation() {
     ciborium::ser::into_writer(&wrong_state, &mut buf2).unwrap();
 
     let result = contract_fulltext_shard::Contract::validate_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(wrong_state.shard_id),
         freenet_stdlib::prelude::State::from(buf2),
         freenet_stdlib::prelude::RelatedContracts::default(),
     );
     assert!(result.is_err());
 }
+
+fn params_bytes(shard_id: u8, shard_count: u8) -> Vec<u8> {
+    let params = ShardParameters {
+        shard_id,
+        shard_count,
+        protocol_version: 1,
+        analyzer: Analyzer::default(),
+        max_postings_per_term: DEFAULT_MAX_POSTINGS_PER_TERM,
+        max_state_bytes: DEFAULT_MAX_SHARD_BYTES,
+        min_pow_difficulty: POW_DIFFICULTY,
+    };
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&params, &mut buf).unwrap();
+    buf
+}
+
+fn single_word_state(shard_id: u8, word: &str) -> Vec<u8> {
+    use search_common::types::*;
+    let mut index = std::collections::BTreeMap::new();
+    index.insert(word.to_string(), vec![posting(word, "c1", 1000)]);
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&ShardState { shard_id, index }, &mut buf).unwrap();
+    buf
+}
+
+fn validate(params: Vec<u8>, state: Vec<u8>) -> bool {
+    contract_fulltext_shard::Contract::validate_state(
+        freenet_stdlib::prelude::Parameters::from(params),
+        freenet_stdlib::prelude::State::from(state),
+        freenet_stdlib::prelude::RelatedContracts::default(),
+    )
+    .is_ok()
+}
+
+#[test]
+fn routing_follows_shard_count_param() {
+    // Find a word whose shard differs between a 16- and a 32-shard index
+    let word = (0..)
+        .map(|i| format!("word{}", i))
+        .find(|w| shard_for_word(w, 16) != shard_for_word(w, 32))
+        .unwrap();
+    let shard = shard_for_word(&word, 32);
+    let state = single_word_state(shard, &word);
+
+    assert!(validate(params_bytes(shard, 32), state.clone()));
+    if shard < 16 {
+        assert!(!validate(params_bytes(shard, 16), state));
+    }
+}
+
+#[test]
+fn state_shard_id_must_match_params() {
+    let word = "mismatch";
+    let shard = shard_for_word(word, 16);
+    let state = single_word_state(shard, word);
+    assert!(validate(params_bytes(shard, 16), state.clone()));
+    assert!(!validate(params_bytes((shard + 1) % 16, 16), state));
+}
+
+#[test]
+fn invalid_params_rejected() {
+    let state = single_word_state(0, "");
+    assert!(!validate(vec![], state.clone()));
+    assert!(!validate(params_bytes(0, 0), state.clone()));
+    assert!(!validate(params_bytes(16, 16), state));
+}
+
+#[test]
+fn params_without_analyzer_accepted() {
+    // Parameters written before analyzers were configurable
+    #[derive(serde::Serialize)]
+    struct LegacyParams {
+        shard_id: u8,
+        shard_count: u8,
+        protocol_version: u16,
+    }
+    let shard_id = shard_for_word("legacy", 16);
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(
+        &LegacyParams {
+            shard_id,
+            shard_count: 16,
+            protocol_version: 1,
+        },
+        &mut buf,
+    )
+    .unwrap();
+    let params: ShardParameters = ciborium::de::from_reader(buf.as_slice()).unwrap();
+    assert_eq!(params.analyzer, Analyzer::default());
+    assert_eq!(
+        params.min_pow_difficulty,
+        antiflood::DEFAULT_POSTING_DIFFICULTY
+    );
+    assert!(validate(buf, single_word_state(shard_id, "legacy")));
+}
@@ -1,30 +1,60 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
+use search_common::sync::SummaryKind;
+use search_common::tokenization::Analyzer;
 use search_common::types::*;
 use std::collections::BTreeMap;
 
+/// Kept low so the 100k-posting sync test mines its tokens quickly.
+const POW_DIFFICULTY: u8 = 4;
+
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
     let mut buf = Vec::new();
     ciborium::ser::into_writer(val, &mut buf).unwrap();
     buf
 }
 
+fn shard_params(shard_id: u8) -> freenet_stdlib::prelude::Parameters<'static> {
+    let params = ShardParameters {
+        shard_id,
+        shard_count: 16,
+        protocol_version: 1,
+        analyzer: Analyzer::default(),
+        max_postings_per_term: DEFAULT_MAX_POSTINGS_PER_TERM,
+        // Uncapped: the 100k-posting test outgrows the default, and eviction_tests covers caps
+        max_state_bytes: 0,
+        min_pow_difficulty: POW_DIFFICULTY,
+    };
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&params, &mut buf).unwrap();
+    freenet_stdlib::prelude::Parameters::from(buf)
+}
+
 fn deserialize_state(bytes: &[u8]) -> ShardState {
     ciborium::de::from_reader(bytes).unwrap()
 }
 
+fn mine(mut entry: ShardDeltaEntry) -> ShardDeltaEntry {
+    let challenge = antiflood::posting_challenge(
+        &entry.word,
+        &entry.contract_key,
+        entry.tf_idf_score,
+        entry.term_frequency,
+        &entry.positions,
+    );
+    entry.antiflood_token = antiflood::mine_token(&challenge, POW_DIFFICULTY);
+    entry
+}
+
 fn make_shard_delta(entries: Vec<ShardDeltaEntry>) -> ShardDelta {
     ShardDelta {
-        entries,
-        antiflood_token: AntifloodToken {
-            nonce: vec![0u8; 8],
-            difficulty: 16,
-        },
+        entries: entries.into_iter().map(mine).collect(),
     }
 }
 
 fn apply_delta(state: &ShardState, delta: &ShardDelta) -> ShardState {
     let result = contract_fulltext_shard::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(serialize(state)),
         vec![freenet_stdlib::prelude::UpdateData::Delta(
             freenet_stdlib::prelude::StateDelta::from(serialize(delta)),
@@ -37,7 +67,7 @@ fn apply_delta(state: &ShardState, delta: &ShardDelta) -> ShardState {
 
 fn summarize(state: &ShardState) -> Vec<u8> {
     let result = contract_fulltext_shard::Contract::summarize_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(serialize(state)),
     )
     .expect("summarize_state failed");
@@ -46,7 +76,7 @@ fn summarize(state: &ShardState) -> Vec<u8> {
 
 fn get_delta(state: &ShardState, summary: &[u8]) -> Vec<u8> {
     let result = contract_fulltext_shard::Contract::get_state_delta(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(serialize(state)),
         freenet_stdlib::prelude::StateSummary::from(summary.to_vec()),
     )
@@ -86,8 +116,10 @@ fn bloom_roundtrip() {
             make_shard_delta(vec![ShardDeltaEntry {
                 word: word.clone(),
                 contract_key: format!("c{}", i),
-                snippet: format!("s{}", i),
                 tf_idf_score: 1000,
+                term_frequency: 10000,
+                positions: vec![],
+                antiflood_token: AntifloodToken::default(),
             }])
         })
         .collect();
@@ -103,7 +135,7 @@ fn bloom_roundtrip() {
 
     // Apply delta to empty state
     let result = contract_fulltext_shard::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(empty.shard_id),
         freenet_stdlib::prelude::State::from(serialize(&empty)),
         vec![freenet_stdlib::prelude::UpdateData::Delta(
             freenet_stdlib::prelude::StateDelta::from(delta_bytes),
@@ -131,8 +163,10 @@ fn missing_pairs_detected() {
         let d = make_shard_delta(vec![ShardDeltaEntry {
             word: word.clone(),
             contract_key: format!("ca{}", i),
-            snippet: format!("sa{}", i),
             tf_idf_score: 1000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         }]);
         state_a = apply_delta(&state_a, &d);
     }
@@ -143,8 +177,10 @@ fn missing_pairs_detected() {
         let d = make_shard_delta(vec![ShardDeltaEntry {
             word: word.clone(),
             contract_key: format!("cb{}", i),
-            snippet: format!("sb{}", i),
             tf_idf_score: 2000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         }]);
         state_b = apply_delta(&state_b, &d);
     }
@@ -172,8 +208,10 @@ fn sync_convergence() {
         let d = make_shard_delta(vec![ShardDeltaEntry {
             word: word.clone(),
             contract_key: format!("ca{}", i),
-            snippet: format!("sa{}", i),
             tf_idf_score: 1000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         }]);
         state_a = apply_delta(&state_a, &d);
     }
@@ -183,8 +221,10 @@ fn sync_convergence() {
         let d = make_shard_delta(vec![ShardDeltaEntry {
             word: word.clone(),
             contract_key: format!("cb{}", i),
-            snippet: format!("sb{}", i),
             tf_idf_score: 2000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         }]);
         state_b = apply_delta(&state_b, &d);
     }
@@ -199,7 +239,7 @@ fn sync_convergence() {
     // Apply
     let state_b_synced = if !delta_a_to_b.is_empty() {
         let result = contract_fulltext_shard::Contract::update_state(
-            freenet_stdlib::prelude::Parameters::from(vec![]),
+            shard_params(state_b.shard_id),
             freenet_stdlib::prelude::State::from(serialize(&state_b)),
             vec![freenet_stdlib::prelude::UpdateData::Delta(
                 freenet_stdlib::prelude::StateDelta::from(delta_a_to_b),
@@ -213,7 +253,7 @@ fn sync_convergence() {
 
     let state_a_synced = if !delta_b_to_a.is_empty() {
         let result = contract_fulltext_shard::Contract::update_state(
-            freenet_stdlib::prelude::Parameters::from(vec![]),
+            shard_params(state_a.shard_id),
             freenet_stdlib::prelude::State::from(serialize(&state_a)),
             vec![freenet_stdlib::prelude::UpdateData::Delta(
                 freenet_stdlib::prelude::StateDelta::from(delta_b_to_a),
@@ -228,3 +268,126 @@ fn sync_convergence() {
     assert_eq!(state_a_synced.index.len(), state_b_synced.index.len());
     assert_eq!(state_a_synced, state_b_synced);
 }
+
+/// Sync a shard missing half of 100k postings from one holding them all, with
+/// summaries of `kind`. Returns the rounds taken and the postings sent in the first.
+fn sync_100k_postings(kind: SummaryKind) -> (u32, usize) {
+    let shard_id = 0u8;
+    let words = words_for_shard(shard_id, 16, 1000);
+    // Shards keep each word's postings in contract key order
+    let postings = |word: &str| {
+        let mut entries: Vec<TermEntry> = (0..100u32)
+            .map(|key| {
+                let mut entry = TermEntry {
+                    contract_key: format!("c{}", key),
+                    tf_idf_score: 1000 + key,
+                    term_frequency: 10000,
+                    positions: vec![],
+                    antiflood_token: AntifloodToken::default(),
+                };
+                let challenge = antiflood::posting_challenge(
+                    word,
+                    &entry.contract_key,
+                    entry.tf_idf_score,
+                    entry.term_frequency,
+                    &entry.positions,
+                );
+                entry.antiflood_token = antiflood::mine_token(&challenge, POW_DIFFICULTY);
+                entry
+            })
+            .collect();
+        entries.sort_by(|a, b| a.contract_key.cmp(&b.contract_key));
+        entries
+    };
+
+    // A holds 100 postings for each of 1000 words, B the first half of each
+    let state_a = ShardState {
+        shard_id,
+        index: words.iter().map(|w| (w.clone(), postings(w))).collect(),
+    };
+    let mut state_b = state_a.clone();
+    for entries in state_b.index.values_mut() {
+        entries.retain(|e| e.tf_idf_score < 1050);
+    }
+
+    // Each round B summarizes, A answers with what the summary lacks
+    let mut rounds = 0;
+    let mut first_round = 0;
+    loop {
+        let delta = get_delta(
+            &state_a,
+            &contract_fulltext_shard::summarize_as(kind, &state_b),
+        );
+        if delta.is_empty() {
+            break;
+        }
+        if rounds == 0 {
+            first_round = deserialize_state(&delta).index.values().map(Vec::len).sum();
+        }
+        state_b = apply_sync_delta(&state_b, delta);
+        rounds += 1;
+        assert!(rounds <= 5, "sync did not converge");
+    }
+
+    assert_eq!(state_a, state_b);
+    (rounds, first_round)
+}
+
+#[test]
+fn sync_converges_at_100k_postings() {
+    // IBLT summaries hold nothing back, so one round suffices
+    let (rounds, _) = sync_100k_postings(SummaryKind::Iblt);
+    assert_eq!(rounds, 1);
+}
+
+#[test]
+fn bloom_sync_converges_at_100k_postings() {
+    // A 1% false-positive rate holds back about 500 of the 50k missing postings
+    let (_, first_round) = sync_100k_postings(SummaryKind::Bloom);
+    assert!(
+        first_round > 49_000,
+        "first round sent only {}",
+        first_round
+    );
+}
+
+#[test]
+fn stale_posting_resent() {
+    let shard_id = 0u8;
+    let words = words_for_shard(shard_id, 16, 1);
+    let entry = |score: u32| ShardDeltaEntry {
+        word: words[0].clone(),
+        contract_key: "c1".to_string(),
+        tf_idf_score: score,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
+    };
+    let empty = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+    let state_a = apply_delta(&empty, &make_shard_delta(vec![entry(3000)]));
+    let state_b = apply_delta(&empty, &make_shard_delta(vec![entry(1000)]));
+
+    // Same (word, contract_key), different score: B's copy is stale
+    let delta = get_delta(&state_a, &summarize(&state_b));
+    assert!(!delta.is_empty());
+    assert_eq!(apply_sync_delta(&state_b, delta), state_a);
+
+    // The reverse direction resends B's losing copy, which merging ignores
+    let delta = get_delta(&state_b, &summarize(&state_a));
+    assert_eq!(apply_sync_delta(&state_a, delta), state_a);
+}
+
+fn apply_sync_delta(state: &ShardState, delta: Vec<u8>) -> ShardState {
+    let result = contract_fulltext_shard::Contract::update_state(
+        shard_params(state.shard_id),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(delta),
+        )],
+    )
+    .expect("sync delta rejected");
+    deserialize_state(result.unwrap_valid().as_ref())
+}
@@ -1,33 +1,72 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
+use search_common::tokenization::Analyzer;
 use search_common::types::*;
 use std::collections::BTreeMap;
 
+const POW_DIFFICULTY: u8 = 8;
+
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
     let mut buf = Vec::new();
     ciborium::ser::into_writer(val, &mut buf).unwrap();
     buf
 }
 
+fn shard_params(shard_id: u8) -> freenet_stdlib::prelude::Parameters<'static> {
+    let params = ShardParameters {
+        shard_id,
+        shard_count: 16,
+        protocol_version: 1,
+        analyzer: Analyzer::default(),
+        max_postings_per_term: DEFAULT_MAX_POSTINGS_PER_TERM,
+        max_state_bytes: DEFAULT_MAX_SHARD_BYTES,
+        min_pow_difficulty: POW_DIFFICULTY,
+    };
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&params, &mut buf).unwrap();
+    freenet_stdlib::prelude::Parameters::from(buf)
+}
+
 fn deserialize_state(bytes: &[u8]) -> ShardState {
     ciborium::de::from_reader(bytes).unwrap()
 }
 
+fn mine(mut entry: ShardDeltaEntry) -> ShardDeltaEntry {
+    let challenge = antiflood::posting_challenge(
+        &entry.word,
+        &entry.contract_key,
+        entry.tf_idf_score,
+        entry.term_frequency,
+        &entry.positions,
+    );
+    entry.antiflood_token = antiflood::mine_token(&challenge, POW_DIFFICULTY);
+    entry
+}
+
 fn make_shard_delta(entries: Vec<ShardDeltaEntry>) -> ShardDelta {
     ShardDelta {
-        entries,
-        antiflood_token: AntifloodToken {
-            nonce: vec![0u8; 8],
-            difficulty: 16,
-        },
+        entries: entries.into_iter().map(mine).collect(),
     }
 }
 
+fn update(
+    state: &ShardState,
+    update: freenet_stdlib::prelude::UpdateData<'static>,
+) -> Result<ShardState, freenet_stdlib::prelude::ContractError> {
+    contract_fulltext_shard::Contract::update_state(
+        shard_params(state.shard_id),
+        freenet_stdlib::prelude::State::from(serialize(state)),
+        vec![update],
+    )
+    .map(|result| deserialize_state(result.unwrap_valid().as_ref()))
+}
+
 fn apply_shard_delta(state: &ShardState, delta: &ShardDelta) -> ShardState {
     let state_bytes = serialize(state);
     let delta_bytes = serialize(delta);
 
     let result = contract_fulltext_shard::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         vec![freenet_stdlib::prelude::UpdateData::Delta(
             freenet_stdlib::prelude::StateDelta::from(delta_bytes),
@@ -62,8 +101,10 @@ fn new_term_added() {
     let delta = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "contract-a".to_string(),
-        snippet: "test snippet".to_string(),
         tf_idf_score: 5000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let new_state = apply_shard_delta(&state, &delta);
@@ -80,8 +121,10 @@ fn existing_term_new_entry() {
         word.clone(),
         vec![TermEntry {
             contract_key: "contract-existing".to_string(),
-            snippet: "existing snippet".to_string(),
             tf_idf_score: 3000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         }],
     );
     let state = ShardState { shard_id, index };
@@ -89,8 +132,10 @@ fn existing_term_new_entry() {
     let delta = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "contract-new".to_string(),
-        snippet: "new snippet".to_string(),
         tf_idf_score: 4000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let new_state = apply_shard_delta(&state, &delta);
@@ -106,8 +151,10 @@ fn max_wins_score() {
         word.clone(),
         vec![TermEntry {
             contract_key: "contract-a".to_string(),
-            snippet: "old snippet".to_string(),
             tf_idf_score: 3000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         }],
     );
     let state = ShardState { shard_id, index };
@@ -116,8 +163,10 @@ fn max_wins_score() {
     let delta = make_shard_delta(vec![ShardDeltaEntry {
         word: word.clone(),
         contract_key: "contract-a".to_string(),
-        snippet: "updated snippet".to_string(),
         tf_idf_score: 5000, // higher score
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let new_state = apply_shard_delta(&state, &delta);
@@ -139,15 +188,17 @@ fn wrong_shard_rejected() {
     let delta = make_shard_delta(vec![ShardDeltaEntry {
         word: wrong_word,
         contract_key: "contract-a".to_string(),
-        snippet: "snippet".to_string(),
         tf_idf_score: 1000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let state_bytes = serialize(&state);
     let delta_bytes = serialize(&delta);
 
     let result = contract_fulltext_shard::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         vec![freenet_stdlib::prelude::UpdateData::Delta(
             freenet_stdlib::prelude::StateDelta::from(delta_bytes),
@@ -180,14 +231,18 @@ fn batch_multiple_words() {
         ShardDeltaEntry {
             word: word1.clone(),
             contract_key: "contract-1".to_string(),
-            snippet: "snippet 1".to_string(),
             tf_idf_score: 1000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         },
         ShardDeltaEntry {
             word: word2.clone(),
             contract_key: "contract-2".to_string(),
-            snippet: "snippet 2".to_string(),
             tf_idf_score: 2000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
         },
     ]);
 
@@ -209,20 +264,21 @@ fn invalid_token_rejected() {
         entries: vec![ShardDeltaEntry {
             word,
             contract_key: "contract-a".to_string(),
-            snippet: "snippet".to_string(),
             tf_idf_score: 1000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken {
+                nonce: vec![], // empty = invalid
+                difficulty: 0,
+            },
         }],
-        antiflood_token: AntifloodToken {
-            nonce: vec![], // empty = invalid
-            difficulty: 0,
-        },
     };
 
     let state_bytes = serialize(&state);
     let delta_bytes = serialize(&delta);
 
     let result = contract_fulltext_shard::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         vec![freenet_stdlib::prelude::UpdateData::Delta(
             freenet_stdlib::prelude::StateDelta::from(delta_bytes),
@@ -242,15 +298,17 @@ fn empty_word_rejected() {
     let delta = make_shard_delta(vec![ShardDeltaEntry {
         word: "".to_string(), // empty word
         contract_key: "contract-a".to_string(),
-        snippet: "snippet".to_string(),
         tf_idf_score: 1000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
     }]);
 
     let state_bytes = serialize(&state);
     let delta_bytes = serialize(&delta);
 
     let result = contract_fulltext_shard::Contract::update_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         vec![freenet_stdlib::prelude::UpdateData::Delta(
             freenet_stdlib::prelude::StateDelta::from(delta_bytes),
@@ -258,3 +316,253 @@ fn empty_word_rejected() {
     );
     assert!(result.is_err());
 }
+
+#[test]
+fn forged_token_rejected() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+
+    let delta = ShardDelta {
+        entries: vec![ShardDeltaEntry {
+            word,
+            contract_key: "contract-a".to_string(),
+            tf_idf_score: 1000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken {
+                nonce: vec![1u8],
+                difficulty: 1,
+            },
+        }],
+    };
+
+    let result = contract_fulltext_shard::Contract::update_state(
+        shard_params(state.shard_id),
+        freenet_stdlib::prelude::State::from(serialize(&state)),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(serialize(&delta)),
+        )],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn token_bound_to_posting() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+
+    // Mined for one score, then the payload is tampered with
+    let mut delta = make_shard_delta(vec![ShardDeltaEntry {
+        word,
+        contract_key: "contract-a".to_string(),
+        tf_idf_score: 1000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
+    }]);
+    delta.entries[0].tf_idf_score = 9999;
+
+    let result = contract_fulltext_shard::Contract::update_state(
+        shard_params(state.shard_id),
+        freenet_stdlib::prelude::State::from(serialize(&state)),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(serialize(&delta)),
+        )],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn term_frequency_out_of_range_rejected() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+
+    // A term can't make up more than the whole document
+    let delta = make_shard_delta(vec![ShardDeltaEntry {
+        word,
+        contract_key: "contract-a".to_string(),
+        tf_idf_score: 1000,
+        term_frequency: 10001,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
+    }]);
+
+    let result = contract_fulltext_shard::Contract::update_state(
+        shard_params(state.shard_id),
+        freenet_stdlib::prelude::State::from(serialize(&state)),
+        vec![freenet_stdlib::prelude::UpdateData::Delta(
+            freenet_stdlib::prelude::StateDelta::from(serialize(&delta)),
+        )],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn positions_stored_and_validated() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+    let entry = |positions: Vec<u32>| ShardDeltaEntry {
+        word: word.clone(),
+        contract_key: "contract-a".to_string(),
+        tf_idf_score: 1000,
+        term_frequency: 500,
+        positions,
+        antiflood_token: AntifloodToken::default(),
+    };
+
+    let new_state = apply_shard_delta(&state, &make_shard_delta(vec![entry(vec![2, 9, 30])]));
+    assert_eq!(new_state.index[&word][0].positions, vec![2, 9, 30]);
+
+    let too_many = (0..MAX_POSITIONS as u32 + 1).collect();
+    for bad in [vec![9, 2], vec![4, 4], too_many] {
+        let result = contract_fulltext_shard::Contract::update_state(
+            shard_params(state.shard_id),
+            freenet_stdlib::prelude::State::from(serialize(&state)),
+            vec![freenet_stdlib::prelude::UpdateData::Delta(
+                freenet_stdlib::prelude::StateDelta::from(serialize(&make_shard_delta(vec![
+                    entry(bad),
+                ]))),
+            )],
+        );
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+fn state_from_other_shard_rejected() {
+    let state = ShardState {
+        shard_id: 0,
+        index: BTreeMap::new(),
+    };
+    let other = ShardState {
+        shard_id: 1,
+        index: BTreeMap::new(),
+    };
+
+    let result = contract_fulltext_shard::Contract::update_state(
+        shard_params(state.shard_id),
+        freenet_stdlib::prelude::State::from(serialize(&state)),
+        vec![freenet_stdlib::prelude::UpdateData::State(
+            freenet_stdlib::prelude::State::from(serialize(&other)),
+        )],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn token_below_shard_difficulty_rejected() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+
+    let mut delta = make_shard_delta(vec![ShardDeltaEntry {
+        word,
+        contract_key: "contract-a".to_string(),
+        tf_idf_score: 1000,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
+    }]);
+    // The shard parameters set the minimum, whatever the token claims
+    delta.entries[0].antiflood_token.difficulty = POW_DIFFICULTY - 1;
+
+    let result = update(
+        &state,
+        freenet_stdlib::prelude::UpdateData::Delta(freenet_stdlib::prelude::StateDelta::from(
+            serialize(&delta),
+        )),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn score_above_term_frequency_bound_rejected() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+    let entry = |score: u32| ShardDeltaEntry {
+        word: word.clone(),
+        contract_key: "contract-a".to_string(),
+        tf_idf_score: score,
+        term_frequency: 500,
+        positions: vec![],
+        antiflood_token: AntifloodToken::default(),
+    };
+    let bound = search_common::scoring::max_tf_idf(500);
+
+    let delta_update = |score: u32| {
+        freenet_stdlib::prelude::UpdateData::Delta(freenet_stdlib::prelude::StateDelta::from(
+            serialize(&make_shard_delta(vec![entry(score)])),
+        ))
+    };
+    assert!(update(&state, delta_update(bound)).is_ok());
+    // Mined, but no document with this term frequency could score higher
+    assert!(update(&state, delta_update(bound + 1)).is_err());
+}
+
+#[test]
+fn merged_state_postings_checked() {
+    let shard_id = 0u8;
+    let word = word_for_shard(shard_id, 16);
+    let state = ShardState {
+        shard_id,
+        index: BTreeMap::new(),
+    };
+    let valid = apply_shard_delta(
+        &state,
+        &make_shard_delta(vec![ShardDeltaEntry {
+            word: word.clone(),
+            contract_key: "contract-a".to_string(),
+            tf_idf_score: 1000,
+            term_frequency: 10000,
+            positions: vec![],
+            antiflood_token: AntifloodToken::default(),
+        }]),
+    );
+    let as_state = |s: &ShardState| {
+        freenet_stdlib::prelude::UpdateData::State(freenet_stdlib::prelude::State::from(serialize(
+            s,
+        )))
+    };
+    let as_sync_delta = |s: &ShardState| {
+        freenet_stdlib::prelude::UpdateData::Delta(freenet_stdlib::prelude::StateDelta::from(
+            serialize(s),
+        ))
+    };
+    assert_eq!(update(&state, as_state(&valid)).unwrap(), valid);
+    assert_eq!(update(&state, as_sync_delta(&valid)).unwrap(), valid);
+
+    // Inflating a relayed posting's score invalidates its token
+    let mut inflated = valid.clone();
+    inflated.index.get_mut(&word).unwrap()[0].tf_idf_score = 300000;
+    assert!(update(&state, as_state(&inflated)).is_err());
+    assert!(update(&state, as_sync_delta(&inflated)).is_err());
+
+    // So does a posting with no token at all
+    let mut unmined = valid;
+    unmined.index.get_mut(&word).unwrap()[0].antiflood_token = AntifloodToken::default();
+    assert!(update(&state, as_state(&unmined)).is_err());
+    assert!(update(&state, as_sync_delta(&unmined)).is_err());
+}
@@ -1,20 +1,51 @@
 use freenet_stdlib::prelude::ContractInterface;
+use search_common::antiflood;
+use search_common::tokenization::Analyzer;
 use search_common::types::*;
 use std::collections::BTreeMap;
 
+const POW_DIFFICULTY: u8 = 8;
+
+/// A posting filed under `word`, with its proof-of-work mined.
+fn posting(word: &str, contract_key: &str, tf_idf_score: u32) -> TermEntry {
+    let challenge = antiflood::posting_challenge(word, contract_key, tf_idf_score, 10000, &[]);
+    TermEntry {
+        contract_key: contract_key.to_string(),
+        tf_idf_score,
+        term_frequency: 10000,
+        positions: vec![],
+        antiflood_token: antiflood::mine_token(&challenge, POW_DIFFICULTY),
+    }
+}
+
 fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
     let mut buf = Vec::new();
     ciborium::ser::into_writer(val, &mut buf).unwrap();
     buf
 }
 
+fn shard_params(shard_id: u8) -> freenet_stdlib::prelude::Parameters<'static> {
+    let params = ShardParameters {
+        shard_id,
+        shard_count: 16,
+        protocol_version: 1,
+        analyzer: Analyzer::default(),
+        max_postings_per_term: DEFAULT_MAX_POSTINGS_PER_TERM,
+        max_state_bytes: DEFAULT_MAX_SHARD_BYTES,
+        min_pow_difficulty: POW_DIFFICULTY,
+    };
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&params, &mut buf).unwrap();
+    freenet_stdlib::prelude::Parameters::from(buf)
+}
+
 #[test]
 fn valid_empty_shard() {
     let state = ShardState::default();
     let state_bytes = serialize(&state);
 
     let result = contract_fulltext_shard::Contract::validate_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         freenet_stdlib::prelude::RelatedContracts::default(),
     );
@@ -29,14 +60,7 @@ fn valid_shard_with_entries() {
     let actual_shard = search_common::hashing::shard_for_word(word, 16);
 
     let mut index = BTreeMap::new();
-    index.insert(
-        word.to_string(),
-        vec![TermEntry {
-            contract_key: "contract-abc".to_string(),
-            snippet: "Hello world snippet".to_string(),
-            tf_idf_score: 5000,
-        }],
-    );
+    index.insert(word.to_string(), vec![posting(word, "contract-abc", 5000)]);
 
     let state = ShardState {
         shard_id: actual_shard,
@@ -45,7 +69,7 @@ fn valid_shard_with_entries() {
     let state_bytes = serialize(&state);
 
     let result = contract_fulltext_shard::Contract::validate_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         freenet_stdlib::prelude::RelatedContracts::default(),
     );
@@ -61,11 +85,7 @@ fn wrong_shard_rejected() {
     let mut index = BTreeMap::new();
     index.insert(
         "hello".to_string(),
-        vec![TermEntry {
-            contract_key: "contract-xyz".to_string(),
-            snippet: "snippet".to_string(),
-            tf_idf_score: 1000,
-        }],
+        vec![posting("hello", "contract-xyz", 1000)],
     );
 
     let state = ShardState {
@@ -75,7 +95,7 @@ fn wrong_shard_rejected() {
     let state_bytes = serialize(&state);
 
     let result = contract_fulltext_shard::Contract::validate_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         freenet_stdlib::prelude::RelatedContracts::default(),
     );
@@ -89,16 +109,8 @@ fn duplicate_contract_key_per_word() {
     index.insert(
         "test".to_string(),
         vec![
-            TermEntry {
-                contract_key: "contract-dup".to_string(),
-                snippet: "snippet 1".to_string(),
-                tf_idf_score: 1000,
-            },
-            TermEntry {
-                contract_key: "contract-dup".to_string(), // duplicate!
-                snippet: "snippet 2".to_string(),
-                tf_idf_score: 2000,
-            },
+            posting("test", "contract-dup", 1000),
+            posting("test", "contract-dup", 2000),
         ],
     );
 
@@ -106,7 +118,7 @@ fn duplicate_contract_key_per_word() {
     let state_bytes = serialize(&state);
 
     let result = contract_fulltext_shard::Contract::validate_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(state.shard_id),
         freenet_stdlib::prelude::State::from(state_bytes),
         freenet_stdlib::prelude::RelatedContracts::default(),
     );
@@ -118,7 +130,7 @@ fn invalid_cbor() {
     let garbage = vec![0xFF, 0xFE, 0xFD];
 
     let result = contract_fulltext_shard::Contract::validate_state(
-        freenet_stdlib::prelude::Parameters::from(vec![]),
+        shard_params(0),
         freenet_stdlib::prelude::State::from(garbage),
         freenet_stdlib::prelude::RelatedContracts::default(),
     );
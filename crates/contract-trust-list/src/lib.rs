@@ -0,0 +1,131 @@
+//! Trust-list contract for the Freenet search engine.
+//!
+//! Holds one curator's published trust list: the keys they endorse, with a
+//! weight and how far to follow each key's own list. The contract is
+//! parameterized by the curator's ed25519 public key, so its instance ID names
+//! the curator, and only lists signed by that key are accepted. The highest
+//! version wins; summaries carry the version so peers send only a newer list.
+
+use delegate_identity::verify_trust_list;
+use freenet_stdlib::prelude::*;
+use search_common::trust::TrustListState;
+
+pub struct Contract;
+
+fn cbor_serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(val, &mut buf).expect("CBOR serialization failed");
+    buf
+}
+
+/// The owner key from the first 32 bytes of the parameters.
+/// Additional bytes (e.g. a vanity nonce) are ignored.
+fn owner_key(parameters: &Parameters<'static>) -> Result<[u8; 32], ContractError> {
+    parameters
+        .as_ref()
+        .get(..32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            ContractError::Other(
+                "Parameters must be at least 32 bytes (Ed25519 public key)".to_string(),
+            )
+        })
+}
+
+/// An empty state means nothing has been published yet.
+fn parse_list(bytes: &[u8]) -> Option<Option<TrustListState>> {
+    if bytes.is_empty() {
+        return Some(None);
+    }
+    ciborium::de::from_reader(bytes).ok().map(Some)
+}
+
+/// Whether `new` replaces `current`. Equal versions are ordered by signature
+/// so peers holding different lists of one version still converge.
+fn supersedes(new: &TrustListState, current: Option<&TrustListState>) -> bool {
+    current
+        .is_none_or(|current| (new.version, &new.signature) > (current.version, &current.signature))
+}
+
+#[contract]
+impl ContractInterface for Contract {
+    fn validate_state(
+        parameters: Parameters<'static>,
+        state: State<'static>,
+        _related: RelatedContracts<'static>,
+    ) -> Result<ValidateResult, ContractError> {
+        let owner = owner_key(&parameters)?;
+        match parse_list(state.as_ref()).ok_or(ContractError::InvalidState)? {
+            Some(list) if !verify_trust_list(&list, &owner) => Err(ContractError::InvalidState),
+            _ => Ok(ValidateResult::Valid),
+        }
+    }
+
+    fn update_state(
+        parameters: Parameters<'static>,
+        state: State<'static>,
+        data: Vec<UpdateData<'static>>,
+    ) -> Result<UpdateModification<'static>, ContractError> {
+        let owner = owner_key(&parameters)?;
+        let mut current = parse_list(state.as_ref()).ok_or(ContractError::InvalidUpdate)?;
+
+        // States and deltas alike carry a whole signed list
+        for update in &data {
+            let bytes = match update {
+                UpdateData::State(state_bytes) => state_bytes.as_ref(),
+                UpdateData::Delta(delta_bytes) => delta_bytes.as_ref(),
+                _ => continue,
+            };
+            let Some(list) = parse_list(bytes).ok_or(ContractError::InvalidUpdate)? else {
+                continue;
+            };
+            if !verify_trust_list(&list, &owner) {
+                return Err(ContractError::InvalidUpdate);
+            }
+            if supersedes(&list, current.as_ref()) {
+                current = Some(list);
+            }
+        }
+
+        let new_state_bytes = current.as_ref().map(cbor_serialize).unwrap_or_default();
+        Ok(UpdateModification::valid(State::from(new_state_bytes)))
+    }
+
+    fn summarize_state(
+        _parameters: Parameters<'static>,
+        state: State<'static>,
+    ) -> Result<StateSummary<'static>, ContractError> {
+        let summary = parse_list(state.as_ref())
+            .ok_or(ContractError::InvalidState)?
+            .map(|list| cbor_serialize(&(list.version, list.signature)))
+            .unwrap_or_default();
+        Ok(StateSummary::from(summary))
+    }
+
+    fn get_state_delta(
+        _parameters: Parameters<'static>,
+        state: State<'static>,
+        summary: StateSummary<'static>,
+    ) -> Result<StateDelta<'static>, ContractError> {
+        let Some(list) = parse_list(state.as_ref()).ok_or(ContractError::InvalidState)? else {
+            return Ok(StateDelta::from(vec![]));
+        };
+        let theirs: Option<(u64, Vec<u8>)> = if summary.as_ref().is_empty() {
+            None
+        } else {
+            Some(
+                ciborium::de::from_reader(summary.as_ref())
+                    .map_err(|_| ContractError::InvalidState)?,
+            )
+        };
+
+        let newer = theirs.is_none_or(|(version, signature)| {
+            (list.version, &list.signature) > (version, &signature)
+        });
+        if newer {
+            Ok(StateDelta::from(cbor_serialize(&list)))
+        } else {
+            Ok(StateDelta::from(vec![]))
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use freenet_stdlib::prelude::{
+    ContractInterface, Parameters, RelatedContracts, State, StateDelta, StateSummary, UpdateData,
+};
+use search_common::trust::*;
+
+const OWNER: [u8; 32] = [7u8; 32];
+
+fn serialize<T: serde::Serialize>(val: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(val, &mut buf).unwrap();
+    buf
+}
+
+/// Test identities use the 32-byte seed directly as the ed25519 secret key.
+fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    ed25519_dalek::SigningKey::from_bytes(secret)
+        .verifying_key()
+        .to_bytes()
+}
+
+fn params() -> Parameters<'static> {
+    Parameters::from(public_key(&OWNER).to_vec())
+}
+
+fn link(n: u8, weight: u32, depth: u8) -> TrustLink {
+    TrustLink {
+        pubkey: [n; 32],
+        weight,
+        depth,
+    }
+}
+
+fn signed_list(secret: [u8; 32], version: u64, endorsements: Vec<TrustLink>) -> TrustListState {
+    let owner = public_key(&secret);
+    let message = search_common::hashing::trust_list_message(&owner, version, &endorsements);
+    TrustListState {
+        owner,
+        version,
+        endorsements,
+        signature: delegate_identity::sign_data(&secret, &message).to_vec(),
+    }
+}
+
+fn list(version: u64) -> TrustListState {
+    signed_list(OWNER, version, vec![link(1, 80, 1), link(2, 40, 0)])
+}
+
+fn validate(list: &TrustListState) -> bool {
+    contract_trust_list::Contract::validate_state(
+        params(),
+        State::from(serialize(list)),
+        RelatedContracts::default(),
+    )
+    .is_ok()
+}
+
+fn update(
+    state: Vec<u8>,
+    updates: Vec<UpdateData<'static>>,
+) -> Result<Vec<u8>, freenet_stdlib::prelude::ContractError> {
+    contract_trust_list::Contract::update_state(params(), State::from(state), updates)
+        .map(|result| result.unwrap_valid().as_ref().to_vec())
+}
+
+fn parse(bytes: &[u8]) -> TrustListState {
+    ciborium::de::from_reader(bytes).unwrap()
+}
+
+#[test]
+fn signed_list_valid() {
+    assert!(validate(&list(1)));
+    assert!(contract_trust_list::Contract::validate_state(
+        params(),
+        State::from(vec![]),
+        RelatedContracts::default(),
+    )
+    .is_ok());
+}
+
+#[test]
+fn list_from_another_key_invalid() {
+    assert!(!validate(&signed_list([8u8; 32], 1, vec![link(1, 80, 1)])));
+
+    let mut tampered = list(1);
+    tampered.endorsements[0].weight = 100;
+    assert!(!validate(&tampered));
+}
+
+#[test]
+fn malformed_endorsements_invalid() {
+    let unsorted = signed_list(OWNER, 1, vec![link(2, 40, 0), link(1, 80, 1)]);
+    assert!(!validate(&unsorted));
+    let duplicate = signed_list(OWNER, 1, vec![link(1, 40, 0), link(1, 80, 1)]);
+    assert!(!validate(&duplicate));
+    let zero_weight = signed_list(OWNER, 1, vec![link(1, 0, 0)]);
+    assert!(!validate(&zero_weight));
+    let too_heavy = signed_list(OWNER, 1, vec![link(1, MAX_TRUST_WEIGHT + 1, 0)]);
+    assert!(!validate(&too_heavy));
+    let too_deep = signed_list(OWNER, 1, vec![link(1, 50, MAX_TRUST_DEPTH + 1)]);
+    assert!(!validate(&too_deep));
+}
+
+#[test]
+fn short_parameters_rejected() {
+    let result = contract_trust_list::Contract::validate_state(
+        Parameters::from(vec![1u8; 16]),
+        State::from(serialize(&list(1))),
+        RelatedContracts::default(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn higher_version_replaces_list() {
+    let state = update(vec![], vec![UpdateData::State(serialize(&list(1)).into())]).unwrap();
+    assert_eq!(parse(&state), list(1));
+
+    let state = update(
+        state,
+        vec![UpdateData::Delta(StateDelta::from(serialize(&list(2))))],
+    )
+    .unwrap();
+    assert_eq!(parse(&state).version, 2);
+
+    // A stale list is ignored rather than rolling the state back
+    let state = update(state, vec![UpdateData::State(serialize(&list(1)).into())]).unwrap();
+    assert_eq!(parse(&state).version, 2);
+}
+
+#[test]
+fn forged_update_rejected() {
+    let forged = signed_list([8u8; 32], 5, vec![link(1, 100, 3)]);
+    let current = serialize(&list(1));
+    assert!(update(current, vec![UpdateData::State(serialize(&forged).into())]).is_err());
+}
+
+#[test]
+fn same_version_forks_converge() {
+    let a = signed_list(OWNER, 3, vec![link(1, 80, 1)]);
+    let b = signed_list(OWNER, 3, vec![link(2, 80, 1)]);
+    let ab = update(serialize(&a), vec![UpdateData::State(serialize(&b).into())]).unwrap();
+    let ba = update(serialize(&b), vec![UpdateData::State(serialize(&a).into())]).unwrap();
+    assert_eq!(ab, ba);
+}
+
+#[test]
+fn delta_sent_only_when_newer() {
+    let summarize = |list: &TrustListState| {
+        contract_trust_list::Contract::summarize_state(params(), State::from(serialize(list)))
+            .unwrap()
+    };
+    let delta = |list: &TrustListState, summary: StateSummary<'static>| {
+        contract_trust_list::Contract::get_state_delta(
+            params(),
+            State::from(serialize(list)),
+            summary,
+        )
+        .unwrap()
+        .as_ref()
+        .to_vec()
+    };
+
+    assert!(delta(&list(2), summarize(&list(2))).is_empty());
+    assert!(delta(&list(1), summarize(&list(2))).is_empty());
+
+    let newer = delta(&list(2), summarize(&list(1)));
+    assert_eq!(parse(&newer), list(2));
+    // A peer with nothing published yet gets the whole list
+    let fresh = delta(&list(2), StateSummary::from(vec![]));
+    assert_eq!(parse(&fresh), list(2));
+
+    let synced = update(
+        serialize(&list(1)),
+        vec![UpdateData::Delta(StateDelta::from(newer))],
+    )
+    .unwrap();
+    assert_eq!(parse(&synced), list(2));
+}
@@ -0,0 +1,219 @@
+//! Passphrase-encrypted identity backups.
+//!
+//! A backup is a CBOR [`IdentityBackup`]: the 32-byte secret key sealed with
+//! XChaCha20-Poly1305 under a key derived from the passphrase by Argon2id.
+//! The name and public key are authenticated as associated data. Backups are
+//! passed around as text (`TEXT_PREFIX` followed by hex), which is what the
+//! Settings panel shows and what `web-container-tool` reads and writes.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Format version written by [`seal`].
+pub const BACKUP_VERSION: u32 = 1;
+/// Prefix of the text form of a backup.
+pub const TEXT_PREFIX: &str = "fse-identity:";
+
+/// Largest Argon2 memory cost accepted when opening a backup (256 MiB).
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 8;
+
+/// Argon2id cost parameters, stored with each backup.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// OWASP's recommended Argon2id minimum: 19 MiB, 2 passes, 1 lane.
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// An encrypted identity as exported by the delegate.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdentityBackup {
+    pub version: u32,
+    pub name: String,
+    pub public_key: [u8; 32],
+    pub kdf: KdfParams,
+    pub salt: [u8; 16],
+    pub nonce: [u8; 24],
+    /// Sealed secret key plus the 16-byte Poly1305 tag.
+    pub ciphertext: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackupError {
+    EmptyPassphrase,
+    Malformed,
+    UnsupportedVersion(u32),
+    /// Cost parameters outside what this build will spend on a backup.
+    KdfParams,
+    /// Wrong passphrase or tampered backup.
+    Decryption,
+    /// The decrypted key does not belong to the recorded public key.
+    KeyMismatch,
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::EmptyPassphrase => write!(f, "passphrase must not be empty"),
+            BackupError::Malformed => write!(f, "not an identity backup"),
+            BackupError::UnsupportedVersion(v) => write!(f, "unsupported backup version {}", v),
+            BackupError::KdfParams => write!(f, "backup key derivation parameters out of range"),
+            BackupError::Decryption => write!(f, "wrong passphrase or corrupted backup"),
+            BackupError::KeyMismatch => write!(f, "backup key does not match its public key"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+fn derive_key(passphrase: &str, salt: &[u8; 16], kdf: &KdfParams) -> Result<[u8; 32], BackupError> {
+    if kdf.memory_kib > MAX_MEMORY_KIB
+        || kdf.iterations > MAX_ITERATIONS
+        || kdf.parallelism == 0
+        || kdf.parallelism > MAX_PARALLELISM
+    {
+        return Err(BackupError::KdfParams);
+    }
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|_| BackupError::KdfParams)?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| BackupError::KdfParams)?;
+    Ok(key)
+}
+
+/// Associated data binding the name and public key to the ciphertext.
+fn associated_data(name: &str, public_key: &[u8; 32]) -> Vec<u8> {
+    [public_key.as_slice(), name.as_bytes()].concat()
+}
+
+/// Encrypt `secret_key` under `passphrase` with caller-chosen salt and nonce.
+///
+/// Both must be fresh random bytes for every backup; see [`export`].
+pub fn seal(
+    name: &str,
+    secret_key: &[u8; 32],
+    passphrase: &str,
+    kdf: KdfParams,
+    salt: [u8; 16],
+    nonce: [u8; 24],
+) -> Result<IdentityBackup, BackupError> {
+    if passphrase.is_empty() {
+        return Err(BackupError::EmptyPassphrase);
+    }
+    let public_key = SigningKey::from_bytes(secret_key)
+        .verifying_key()
+        .to_bytes();
+    let key = derive_key(passphrase, &salt, &kdf)?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: secret_key,
+                aad: &associated_data(name, &public_key),
+            },
+        )
+        .map_err(|_| BackupError::Decryption)?;
+    Ok(IdentityBackup {
+        version: BACKUP_VERSION,
+        name: name.to_string(),
+        public_key,
+        kdf,
+        salt,
+        nonce,
+        ciphertext,
+    })
+}
+
+/// Encrypt `secret_key` with a random salt and nonce and default KDF costs.
+#[cfg(feature = "keygen")]
+pub fn export(
+    name: &str,
+    secret_key: &[u8; 32],
+    passphrase: &str,
+) -> Result<IdentityBackup, BackupError> {
+    use rand::RngCore;
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 24];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+    seal(
+        name,
+        secret_key,
+        passphrase,
+        KdfParams::default(),
+        salt,
+        nonce,
+    )
+}
+
+/// Decrypt a backup, returning its secret key.
+pub fn open(backup: &IdentityBackup, passphrase: &str) -> Result<[u8; 32], BackupError> {
+    if backup.version != BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(backup.version));
+    }
+    let key = derive_key(passphrase, &backup.salt, &backup.kdf)?;
+    let plaintext = XChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            XNonce::from_slice(&backup.nonce),
+            Payload {
+                msg: &backup.ciphertext,
+                aad: &associated_data(&backup.name, &backup.public_key),
+            },
+        )
+        .map_err(|_| BackupError::Decryption)?;
+    let secret_key: [u8; 32] = plaintext.try_into().map_err(|_| BackupError::Malformed)?;
+    if SigningKey::from_bytes(&secret_key)
+        .verifying_key()
+        .to_bytes()
+        != backup.public_key
+    {
+        return Err(BackupError::KeyMismatch);
+    }
+    Ok(secret_key)
+}
+
+impl IdentityBackup {
+    /// Text form: `TEXT_PREFIX` followed by the hex-encoded CBOR backup.
+    pub fn to_text(&self) -> String {
+        let mut buf = Vec::new();
+        ciborium::ser::into_writer(self, &mut buf).expect("CBOR serialization failed");
+        let hex: String = buf.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}{}", TEXT_PREFIX, hex)
+    }
+
+    /// Parse the text form, ignoring surrounding whitespace.
+    pub fn from_text(text: &str) -> Result<Self, BackupError> {
+        let hex = text
+            .trim()
+            .strip_prefix(TEXT_PREFIX)
+            .ok_or(BackupError::Malformed)?;
+        if !hex.len().is_multiple_of(2) {
+            return Err(BackupError::Malformed);
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(BackupError::Malformed)?;
+        ciborium::de::from_reader(bytes.as_slice()).map_err(|_| BackupError::Malformed)
+    }
+}
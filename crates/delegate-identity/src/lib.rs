@@ -1,14 +1,199 @@
 //! Identity delegate for the Freenet search engine.
 //!
 //! Provides ed25519 key generation, signing, and signature verification for
-//! contributor identity management. Secret keys never leave the local node.
+//! contributor identity management. Applications send CBOR-encoded
+//! [`IdentityRequest`]s and get back [`IdentityResponse`]s, while the keys
+//! themselves live in delegate secrets. Only the applications listed in the
+//! delegate's [`IdentityParameters`] are answered, as the node attests them.
+//! A secret key leaves the node only as a passphrase-encrypted backup, and only
+//! to an application the parameters also allow to export.
 
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use freenet_stdlib::prelude::*;
+use search_common::hashing::trust_list_message;
+use search_common::trust::{
+    TrustLink, TrustListState, MAX_ENDORSEMENTS, MAX_TRUST_DEPTH, MAX_TRUST_WEIGHT,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub mod backup;
 
 pub struct IdentityDelegate;
 
+/// Secret holding the name → public key index of stored identities.
+const INDEX_SECRET: &[u8] = b"identities";
+/// Longest identity name accepted.
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Delegate parameters, part of the delegate key.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdentityParameters {
+    /// Contract instance IDs of the web apps allowed to use the delegate.
+    pub allowed_apps: Vec<[u8; 32]>,
+    /// Of those, the apps also allowed to export identity backups.
+    #[serde(default)]
+    pub export_apps: Vec<[u8; 32]>,
+}
+
+impl IdentityParameters {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        ciborium::de::from_reader(bytes).ok()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        ciborium::ser::into_writer(self, &mut buf).expect("CBOR serialization failed");
+        buf
+    }
+
+    /// Whether the app the node attests sent a message may use the delegate.
+    /// Messages the node can't attest are refused.
+    pub fn allows(&self, attested: Option<&[u8]>) -> bool {
+        attested.is_some_and(|app| self.allowed_apps.iter().any(|a| a.as_slice() == app))
+    }
+
+    /// Whether the attested app may also export identity backups.
+    pub fn allows_export(&self, attested: Option<&[u8]>) -> bool {
+        self.allows(attested)
+            && attested.is_some_and(|app| self.export_apps.iter().any(|a| a.as_slice() == app))
+    }
+}
+
+/// A request an application sends the identity delegate.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum IdentityRequest {
+    /// Generate a keypair and store it under `name`.
+    CreateIdentity {
+        name: String,
+    },
+    GetPublicKey {
+        name: String,
+    },
+    /// Sign a catalog attestation (see `hashing::attestation_message`) as `name`.
+    SignAttestation {
+        name: String,
+        contract_key: String,
+        metadata_hash: [u8; 32],
+        token_created_at: u64,
+        nonce: Vec<u8>,
+    },
+    ListIdentities,
+    DeleteIdentity {
+        name: String,
+    },
+    /// Encrypt the identity `name` under `passphrase` (see [`backup`]).
+    ExportIdentity {
+        name: String,
+        passphrase: String,
+    },
+    /// Restore a backup in text form, stored as `name` or the name it was exported under.
+    ImportIdentity {
+        backup: String,
+        passphrase: String,
+        name: Option<String>,
+    },
+    /// Store a raw secret key as `name`; for keys an application held itself
+    /// before the delegate kept them.
+    ImportKey {
+        name: String,
+        secret_key: [u8; 32],
+    },
+    /// Sign a trust list of `endorsements` owned by `name`, to publish to its
+    /// trust-list contract. Endorsements are sorted by key, keeping the first
+    /// for a key listed twice.
+    SignTrustList {
+        name: String,
+        version: u64,
+        endorsements: Vec<TrustLink>,
+    },
+}
+
+/// The delegate's answer to one [`IdentityRequest`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum IdentityResponse {
+    Created {
+        name: String,
+        public_key: [u8; 32],
+    },
+    PublicKey {
+        name: String,
+        public_key: [u8; 32],
+    },
+    /// A 64-byte signature over the attestation message for `contract_key`.
+    Signature {
+        name: String,
+        contract_key: String,
+        signature: Vec<u8>,
+    },
+    Identities(Vec<IdentityInfo>),
+    Deleted {
+        name: String,
+    },
+    /// A backup in text form (`backup::TEXT_PREFIX` followed by hex).
+    Exported {
+        name: String,
+        backup: String,
+    },
+    Imported {
+        name: String,
+        public_key: [u8; 32],
+    },
+    /// A trust list signed by its owner, as its trust-list contract accepts it.
+    TrustList(TrustListState),
+    Error {
+        message: String,
+        /// The contract key of the attestation that could not be signed.
+        #[serde(default)]
+        contract_key: Option<String>,
+    },
+}
+
+/// A stored identity as listed to applications.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdentityInfo {
+    pub name: String,
+    pub public_key: [u8; 32],
+}
+
+/// Persistent secret storage; `DelegateCtx` in the delegate, a map in tests.
+pub trait SecretStore {
+    fn get_secret(&self, key: &[u8]) -> Option<Vec<u8>>;
+    fn set_secret(&mut self, key: &[u8], value: &[u8]) -> bool;
+    fn remove_secret(&mut self, key: &[u8]) -> bool;
+}
+
+impl SecretStore for DelegateCtx {
+    fn get_secret(&self, key: &[u8]) -> Option<Vec<u8>> {
+        DelegateCtx::get_secret(self, key)
+    }
+
+    fn set_secret(&mut self, key: &[u8], value: &[u8]) -> bool {
+        DelegateCtx::set_secret(self, key, value)
+    }
+
+    fn remove_secret(&mut self, key: &[u8]) -> bool {
+        DelegateCtx::remove_secret(self, key)
+    }
+}
+
+impl SecretStore for BTreeMap<Vec<u8>, Vec<u8>> {
+    fn get_secret(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.get(key).cloned()
+    }
+
+    fn set_secret(&mut self, key: &[u8], value: &[u8]) -> bool {
+        self.insert(key.to_vec(), value.to_vec());
+        true
+    }
+
+    fn remove_secret(&mut self, key: &[u8]) -> bool {
+        self.remove(key).is_some()
+    }
+}
+
 /// Generate a new ed25519 keypair. Returns (secret_key_bytes, public_key_bytes).
+#[cfg(feature = "keygen")]
 pub fn generate_keypair() -> ([u8; 32], [u8; 32]) {
     let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
     let verifying_key = signing_key.verifying_key();
@@ -31,14 +216,301 @@ pub fn verify_signature(public_key: &[u8; 32], data: &[u8], signature: &[u8; 64]
     verifying_key.verify(data, &signature).is_ok()
 }
 
+/// Whether `list` is a well-formed trust list signed by `owner`: endorsements
+/// sorted by pubkey without duplicates and in range. The trust-list contract
+/// accepts only such lists, and readers check them again before trusting one.
+pub fn verify_trust_list(list: &TrustListState, owner: &[u8; 32]) -> bool {
+    let Ok(signature) = <[u8; 64]>::try_from(list.signature.as_slice()) else {
+        return false;
+    };
+    let message = trust_list_message(&list.owner, list.version, &list.endorsements);
+    list.owner == *owner
+        && list.version != 0
+        && list.endorsements.len() <= MAX_ENDORSEMENTS
+        && list
+            .endorsements
+            .windows(2)
+            .all(|w| w[0].pubkey < w[1].pubkey)
+        && list.endorsements.iter().all(|link| {
+            (1..=MAX_TRUST_WEIGHT).contains(&link.weight) && link.depth <= MAX_TRUST_DEPTH
+        })
+        && verify_signature(owner, &message, &signature)
+}
+
+fn secret_key_name(name: &str) -> Vec<u8> {
+    [b"identity:".as_slice(), name.as_bytes()].concat()
+}
+
+fn load_index(store: &impl SecretStore) -> BTreeMap<String, [u8; 32]> {
+    store
+        .get_secret(INDEX_SECRET)
+        .and_then(|bytes| ciborium::de::from_reader(bytes.as_slice()).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(store: &mut impl SecretStore, index: &BTreeMap<String, [u8; 32]>) -> bool {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(index, &mut buf).expect("CBOR serialization failed");
+    store.set_secret(INDEX_SECRET, &buf)
+}
+
+fn load_secret_key(store: &impl SecretStore, name: &str) -> Option<[u8; 32]> {
+    store.get_secret(&secret_key_name(name))?.try_into().ok()
+}
+
+fn error(message: impl Into<String>) -> IdentityResponse {
+    IdentityResponse::Error {
+        message: message.into(),
+        contract_key: None,
+    }
+}
+
+/// Answer one request against `store`.
+pub fn handle_request(store: &mut impl SecretStore, request: IdentityRequest) -> IdentityResponse {
+    match request {
+        IdentityRequest::CreateIdentity { name } => create_identity(store, name),
+        IdentityRequest::GetPublicKey { name } => match load_index(store).get(&name) {
+            Some(public_key) => IdentityResponse::PublicKey {
+                public_key: *public_key,
+                name,
+            },
+            None => error(format!("no identity named {:?}", name)),
+        },
+        IdentityRequest::SignAttestation {
+            name,
+            contract_key,
+            metadata_hash,
+            token_created_at,
+            nonce,
+        } => {
+            let Some(secret_key) = load_secret_key(store, &name) else {
+                return IdentityResponse::Error {
+                    message: format!("no identity named {:?}", name),
+                    contract_key: Some(contract_key),
+                };
+            };
+            let message = search_common::hashing::attestation_message(
+                &contract_key,
+                &metadata_hash,
+                token_created_at,
+                &nonce,
+            );
+            IdentityResponse::Signature {
+                name,
+                contract_key,
+                signature: sign_data(&secret_key, &message).to_vec(),
+            }
+        }
+        IdentityRequest::ListIdentities => IdentityResponse::Identities(
+            load_index(store)
+                .into_iter()
+                .map(|(name, public_key)| IdentityInfo { name, public_key })
+                .collect(),
+        ),
+        IdentityRequest::DeleteIdentity { name } => {
+            let mut index = load_index(store);
+            if index.remove(&name).is_none() {
+                return error(format!("no identity named {:?}", name));
+            }
+            if !save_index(store, &index) {
+                return error("failed to store identity index");
+            }
+            store.remove_secret(&secret_key_name(&name));
+            IdentityResponse::Deleted { name }
+        }
+        IdentityRequest::ExportIdentity { name, passphrase } => {
+            export_identity(store, name, &passphrase)
+        }
+        IdentityRequest::ImportIdentity {
+            backup,
+            passphrase,
+            name,
+        } => import_identity(store, &backup, &passphrase, name),
+        IdentityRequest::ImportKey { name, secret_key } => {
+            let public_key = SigningKey::from_bytes(&secret_key)
+                .verifying_key()
+                .to_bytes();
+            match store_identity(store, &name, &secret_key, public_key) {
+                Ok(()) => IdentityResponse::Imported { name, public_key },
+                Err(response) => response,
+            }
+        }
+        IdentityRequest::SignTrustList {
+            name,
+            version,
+            endorsements,
+        } => sign_trust_list(store, &name, version, endorsements),
+    }
+}
+
+/// Answer one request from the app the node attests, refusing apps the
+/// parameters don't allow and exports by apps they don't allow to export.
+pub fn handle_app_request(
+    store: &mut impl SecretStore,
+    params: &IdentityParameters,
+    attested: Option<&[u8]>,
+    request: IdentityRequest,
+) -> Result<IdentityResponse, DelegateError> {
+    if !params.allows(attested) {
+        return Err(DelegateError::Other(
+            "application is not allowed to use this delegate".to_string(),
+        ));
+    }
+    if matches!(request, IdentityRequest::ExportIdentity { .. }) && !params.allows_export(attested)
+    {
+        return Ok(error(
+            "this application is not allowed to export identities",
+        ));
+    }
+    Ok(handle_request(store, request))
+}
+
+fn check_name(name: &str) -> Result<(), IdentityResponse> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(error(format!(
+            "identity names must be 1-{} bytes long",
+            MAX_NAME_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Add `name` to the index and store its key, unless the name is taken.
+fn store_identity(
+    store: &mut impl SecretStore,
+    name: &str,
+    secret_key: &[u8; 32],
+    public_key: [u8; 32],
+) -> Result<(), IdentityResponse> {
+    check_name(name)?;
+    let mut index = load_index(store);
+    if index.contains_key(name) {
+        return Err(error(format!("identity {:?} already exists", name)));
+    }
+    // The key goes in before the index lists it, so a listed name always has a key
+    index.insert(name.to_string(), public_key);
+    if !store.set_secret(&secret_key_name(name), secret_key) || !save_index(store, &index) {
+        return Err(error("failed to store identity"));
+    }
+    Ok(())
+}
+
+fn import_identity(
+    store: &mut impl SecretStore,
+    text: &str,
+    passphrase: &str,
+    name: Option<String>,
+) -> IdentityResponse {
+    let opened = backup::IdentityBackup::from_text(text)
+        .and_then(|backup| Ok((backup::open(&backup, passphrase)?, backup)));
+    let (secret_key, backup) = match opened {
+        Ok(opened) => opened,
+        Err(e) => return error(e.to_string()),
+    };
+    let name = name.unwrap_or(backup.name);
+    match store_identity(store, &name, &secret_key, backup.public_key) {
+        Ok(()) => IdentityResponse::Imported {
+            name,
+            public_key: backup.public_key,
+        },
+        Err(response) => response,
+    }
+}
+
+fn sign_trust_list(
+    store: &impl SecretStore,
+    name: &str,
+    version: u64,
+    mut endorsements: Vec<TrustLink>,
+) -> IdentityResponse {
+    let Some(secret_key) = load_secret_key(store, name) else {
+        return error(format!("no identity named {:?}", name));
+    };
+    let owner = SigningKey::from_bytes(&secret_key)
+        .verifying_key()
+        .to_bytes();
+    endorsements.sort_by_key(|link| link.pubkey);
+    endorsements.dedup_by_key(|link| link.pubkey);
+    let message = trust_list_message(&owner, version, &endorsements);
+    let list = TrustListState {
+        owner,
+        version,
+        endorsements,
+        signature: sign_data(&secret_key, &message).to_vec(),
+    };
+    if !verify_trust_list(&list, &owner) {
+        return error(format!(
+            "trust lists need a version above 0 and at most {} endorsements, \
+             each weighted 1-{} with depth up to {}",
+            MAX_ENDORSEMENTS, MAX_TRUST_WEIGHT, MAX_TRUST_DEPTH
+        ));
+    }
+    IdentityResponse::TrustList(list)
+}
+
+#[cfg(feature = "keygen")]
+fn create_identity(store: &mut impl SecretStore, name: String) -> IdentityResponse {
+    let (secret_key, public_key) = generate_keypair();
+    match store_identity(store, &name, &secret_key, public_key) {
+        Ok(()) => IdentityResponse::Created { name, public_key },
+        Err(response) => response,
+    }
+}
+
+#[cfg(not(feature = "keygen"))]
+fn create_identity(_store: &mut impl SecretStore, _name: String) -> IdentityResponse {
+    error("key generation is not available in this build")
+}
+
+#[cfg(feature = "keygen")]
+fn export_identity(
+    store: &mut impl SecretStore,
+    name: String,
+    passphrase: &str,
+) -> IdentityResponse {
+    let Some(secret_key) = load_secret_key(store, &name) else {
+        return error(format!("no identity named {:?}", name));
+    };
+    match backup::export(&name, &secret_key, passphrase) {
+        Ok(backup) => IdentityResponse::Exported {
+            name,
+            backup: backup.to_text(),
+        },
+        Err(e) => error(e.to_string()),
+    }
+}
+
+#[cfg(not(feature = "keygen"))]
+fn export_identity(
+    _store: &mut impl SecretStore,
+    _name: String,
+    _passphrase: &str,
+) -> IdentityResponse {
+    error("identity export is not available in this build")
+}
+
 #[delegate]
 impl DelegateInterface for IdentityDelegate {
     fn process(
-        _ctx: &mut DelegateCtx,
-        _parameters: Parameters<'static>,
-        _attested: Option<&'static [u8]>,
-        _message: InboundDelegateMsg,
+        ctx: &mut DelegateCtx,
+        parameters: Parameters<'static>,
+        attested: Option<&'static [u8]>,
+        message: InboundDelegateMsg,
     ) -> Result<Vec<OutboundDelegateMsg>, DelegateError> {
-        Ok(vec![])
+        let InboundDelegateMsg::ApplicationMessage(incoming) = message else {
+            return Ok(vec![]);
+        };
+        let params = IdentityParameters::from_bytes(parameters.as_ref())
+            .ok_or_else(|| DelegateError::Deser("invalid identity parameters".to_string()))?;
+        let request: IdentityRequest = ciborium::de::from_reader(incoming.payload.as_slice())
+            .map_err(|e| DelegateError::Deser(format!("invalid identity request: {}", e)))?;
+        let response = handle_app_request(ctx, &params, attested, request)?;
+
+        let mut payload = Vec::new();
+        ciborium::ser::into_writer(&response, &mut payload)
+            .map_err(|e| DelegateError::Other(format!("CBOR serialization failed: {}", e)))?;
+        Ok(vec![OutboundDelegateMsg::ApplicationMessage(
+            ApplicationMessage::new(incoming.app, payload).processed(true),
+        )])
     }
 }
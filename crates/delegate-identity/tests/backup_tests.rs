@@ -0,0 +1,236 @@
+use delegate_identity::backup::{self, BackupError, IdentityBackup, KdfParams};
+use delegate_identity::{
+    handle_app_request, handle_request, IdentityInfo, IdentityParameters, IdentityRequest,
+    IdentityResponse,
+};
+use std::collections::BTreeMap;
+
+type Store = BTreeMap<Vec<u8>, Vec<u8>>;
+
+const SECRET_KEY: [u8; 32] = [9u8; 32];
+
+/// Cheap KDF costs so tests don't spend 19 MiB per derivation.
+fn light_kdf() -> KdfParams {
+    KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    }
+}
+
+fn sealed(name: &str, passphrase: &str) -> IdentityBackup {
+    backup::seal(
+        name,
+        &SECRET_KEY,
+        passphrase,
+        light_kdf(),
+        [1u8; 16],
+        [2u8; 24],
+    )
+    .unwrap()
+}
+
+fn create(store: &mut Store, name: &str) -> [u8; 32] {
+    match handle_request(
+        store,
+        IdentityRequest::CreateIdentity {
+            name: name.to_string(),
+        },
+    ) {
+        IdentityResponse::Created { public_key, .. } => public_key,
+        other => panic!("unexpected response: {:?}", other),
+    }
+}
+
+fn export(store: &mut Store, name: &str, passphrase: &str) -> String {
+    match handle_request(
+        store,
+        IdentityRequest::ExportIdentity {
+            name: name.to_string(),
+            passphrase: passphrase.to_string(),
+        },
+    ) {
+        IdentityResponse::Exported { backup, .. } => backup,
+        other => panic!("unexpected response: {:?}", other),
+    }
+}
+
+fn import(
+    store: &mut Store,
+    backup: &str,
+    passphrase: &str,
+    name: Option<&str>,
+) -> IdentityResponse {
+    handle_request(
+        store,
+        IdentityRequest::ImportIdentity {
+            backup: backup.to_string(),
+            passphrase: passphrase.to_string(),
+            name: name.map(str::to_string),
+        },
+    )
+}
+
+#[test]
+fn seal_open_roundtrip() {
+    let backup = sealed("laptop", "correct horse");
+    assert_eq!(backup::open(&backup, "correct horse"), Ok(SECRET_KEY));
+
+    let text = backup.to_text();
+    assert!(text.starts_with(backup::TEXT_PREFIX));
+    assert_eq!(
+        IdentityBackup::from_text(&format!("  {}\n", text)),
+        Ok(backup)
+    );
+}
+
+#[test]
+fn secret_key_not_in_backup() {
+    let backup = sealed("laptop", "correct horse");
+    assert!(!backup
+        .ciphertext
+        .windows(SECRET_KEY.len())
+        .any(|w| w == SECRET_KEY));
+}
+
+#[test]
+fn wrong_passphrase_rejected() {
+    let backup = sealed("laptop", "correct horse");
+    assert_eq!(
+        backup::open(&backup, "battery staple"),
+        Err(BackupError::Decryption)
+    );
+}
+
+#[test]
+fn tampered_backup_rejected() {
+    let mut renamed = sealed("laptop", "correct horse");
+    renamed.name = "desktop".to_string();
+    assert_eq!(
+        backup::open(&renamed, "correct horse"),
+        Err(BackupError::Decryption)
+    );
+
+    let mut flipped = sealed("laptop", "correct horse");
+    flipped.ciphertext[0] ^= 1;
+    assert_eq!(
+        backup::open(&flipped, "correct horse"),
+        Err(BackupError::Decryption)
+    );
+
+    assert_eq!(
+        IdentityBackup::from_text("fse-identity:zz"),
+        Err(BackupError::Malformed)
+    );
+}
+
+#[test]
+fn empty_passphrase_rejected() {
+    assert_eq!(
+        backup::seal("laptop", &SECRET_KEY, "", light_kdf(), [1u8; 16], [2u8; 24]),
+        Err(BackupError::EmptyPassphrase)
+    );
+}
+
+#[test]
+fn excessive_kdf_costs_rejected() {
+    let mut backup = sealed("laptop", "correct horse");
+    backup.kdf.memory_kib = 4 * 1024 * 1024;
+    assert_eq!(
+        backup::open(&backup, "correct horse"),
+        Err(BackupError::KdfParams)
+    );
+}
+
+#[test]
+fn export_delete_import_restores_identity() {
+    let mut store = Store::new();
+    let public_key = create(&mut store, "laptop");
+    let backup = export(&mut store, "laptop", "correct horse");
+
+    handle_request(
+        &mut store,
+        IdentityRequest::DeleteIdentity {
+            name: "laptop".to_string(),
+        },
+    );
+    assert_eq!(
+        import(&mut store, &backup, "correct horse", None),
+        IdentityResponse::Imported {
+            name: "laptop".to_string(),
+            public_key,
+        }
+    );
+    assert_eq!(
+        handle_request(&mut store, IdentityRequest::ListIdentities),
+        IdentityResponse::Identities(vec![IdentityInfo {
+            name: "laptop".to_string(),
+            public_key,
+        }])
+    );
+}
+
+#[test]
+fn import_into_another_store_under_new_name() {
+    let mut source = Store::new();
+    let public_key = create(&mut source, "laptop");
+    let backup = export(&mut source, "laptop", "correct horse");
+
+    let mut target = Store::new();
+    create(&mut target, "laptop");
+    assert!(matches!(
+        import(&mut target, &backup, "correct horse", None),
+        IdentityResponse::Error { .. }
+    ));
+    assert_eq!(
+        import(&mut target, &backup, "correct horse", Some("old laptop")),
+        IdentityResponse::Imported {
+            name: "old laptop".to_string(),
+            public_key,
+        }
+    );
+}
+
+#[test]
+fn import_with_wrong_passphrase_stores_nothing() {
+    let mut source = Store::new();
+    create(&mut source, "laptop");
+    let backup = export(&mut source, "laptop", "correct horse");
+
+    let mut target = Store::new();
+    assert!(matches!(
+        import(&mut target, &backup, "battery staple", None),
+        IdentityResponse::Error { .. }
+    ));
+    assert!(target.is_empty());
+}
+
+#[test]
+fn export_needs_an_exporting_app() {
+    let mut store = Store::new();
+    create(&mut store, "manual");
+    let params = IdentityParameters {
+        allowed_apps: vec![[1u8; 32], [2u8; 32]],
+        export_apps: vec![[1u8; 32], [3u8; 32]],
+    };
+    let request = || IdentityRequest::ExportIdentity {
+        name: "manual".to_string(),
+        passphrase: "correct horse".to_string(),
+    };
+    let mut export_as = |app: Option<&[u8; 32]>| {
+        handle_app_request(&mut store, &params, app.map(|a| a.as_slice()), request())
+    };
+
+    assert!(matches!(
+        export_as(Some(&[1u8; 32])),
+        Ok(IdentityResponse::Exported { .. })
+    ));
+    // Allowed to sign but not to export
+    assert!(matches!(
+        export_as(Some(&[2u8; 32])),
+        Ok(IdentityResponse::Error { .. })
+    ));
+    // Listed for export only, or not attested at all
+    assert!(export_as(Some(&[3u8; 32])).is_err());
+    assert!(export_as(None).is_err());
+}
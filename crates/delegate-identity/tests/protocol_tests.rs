@@ -0,0 +1,277 @@
+use delegate_identity::{
+    handle_request, verify_signature, verify_trust_list, IdentityInfo, IdentityParameters,
+    IdentityRequest, IdentityResponse,
+};
+use search_common::trust::TrustLink;
+use std::collections::BTreeMap;
+
+type Store = BTreeMap<Vec<u8>, Vec<u8>>;
+
+fn create(store: &mut Store, name: &str) -> [u8; 32] {
+    match handle_request(
+        store,
+        IdentityRequest::CreateIdentity {
+            name: name.to_string(),
+        },
+    ) {
+        IdentityResponse::Created { public_key, .. } => public_key,
+        other => panic!("unexpected response: {:?}", other),
+    }
+}
+
+fn sign_request(name: &str) -> IdentityRequest {
+    IdentityRequest::SignAttestation {
+        name: name.to_string(),
+        contract_key: "contract-1".to_string(),
+        metadata_hash: [7u8; 32],
+        token_created_at: 1_700_000_000_000,
+        nonce: vec![1, 2, 3, 4],
+    }
+}
+
+#[test]
+fn create_and_get_public_key() {
+    let mut store = Store::new();
+    let public_key = create(&mut store, "manual");
+
+    let response = handle_request(
+        &mut store,
+        IdentityRequest::GetPublicKey {
+            name: "manual".to_string(),
+        },
+    );
+    assert_eq!(
+        response,
+        IdentityResponse::PublicKey {
+            name: "manual".to_string(),
+            public_key,
+        }
+    );
+}
+
+#[test]
+fn duplicate_and_invalid_names_rejected() {
+    let mut store = Store::new();
+    let public_key = create(&mut store, "manual");
+
+    for name in ["manual".to_string(), String::new(), "x".repeat(65)] {
+        let response = handle_request(&mut store, IdentityRequest::CreateIdentity { name });
+        assert!(matches!(response, IdentityResponse::Error { .. }));
+    }
+    // The existing key was not replaced
+    let response = handle_request(&mut store, IdentityRequest::ListIdentities);
+    assert_eq!(
+        response,
+        IdentityResponse::Identities(vec![IdentityInfo {
+            name: "manual".to_string(),
+            public_key,
+        }])
+    );
+}
+
+#[test]
+fn signature_verifies_against_attestation_message() {
+    let mut store = Store::new();
+    let public_key = create(&mut store, "bot");
+
+    let IdentityResponse::Signature {
+        contract_key,
+        signature,
+        ..
+    } = handle_request(&mut store, sign_request("bot"))
+    else {
+        panic!("expected a signature");
+    };
+    assert_eq!(contract_key, "contract-1");
+
+    let message = search_common::hashing::attestation_message(
+        "contract-1",
+        &[7u8; 32],
+        1_700_000_000_000,
+        &[1, 2, 3, 4],
+    );
+    let signature: [u8; 64] = signature.try_into().unwrap();
+    assert!(verify_signature(&public_key, &message, &signature));
+}
+
+#[test]
+fn secret_keys_never_returned() {
+    let mut store = Store::new();
+    create(&mut store, "manual");
+    let secret = store[b"identity:manual".as_slice()].clone();
+
+    let responses = [
+        handle_request(&mut store, IdentityRequest::ListIdentities),
+        handle_request(&mut store, sign_request("manual")),
+        handle_request(
+            &mut store,
+            IdentityRequest::GetPublicKey {
+                name: "manual".to_string(),
+            },
+        ),
+    ];
+    for response in responses {
+        let mut buf = Vec::new();
+        ciborium::ser::into_writer(&response, &mut buf).unwrap();
+        assert!(!buf.windows(secret.len()).any(|w| w == secret.as_slice()));
+    }
+}
+
+#[test]
+fn list_and_delete_identities() {
+    let mut store = Store::new();
+    let manual = create(&mut store, "manual");
+    let bot = create(&mut store, "bot");
+
+    let response = handle_request(&mut store, IdentityRequest::ListIdentities);
+    assert_eq!(
+        response,
+        IdentityResponse::Identities(vec![
+            IdentityInfo {
+                name: "bot".to_string(),
+                public_key: bot,
+            },
+            IdentityInfo {
+                name: "manual".to_string(),
+                public_key: manual,
+            },
+        ])
+    );
+
+    let response = handle_request(
+        &mut store,
+        IdentityRequest::DeleteIdentity {
+            name: "bot".to_string(),
+        },
+    );
+    assert_eq!(
+        response,
+        IdentityResponse::Deleted {
+            name: "bot".to_string()
+        }
+    );
+    assert!(!store.contains_key(b"identity:bot".as_slice()));
+    assert!(matches!(
+        handle_request(&mut store, sign_request("bot")),
+        IdentityResponse::Error { .. }
+    ));
+    assert!(matches!(
+        handle_request(
+            &mut store,
+            IdentityRequest::DeleteIdentity {
+                name: "bot".to_string(),
+            },
+        ),
+        IdentityResponse::Error { .. }
+    ));
+
+    // The name can be reused for a fresh key
+    assert_ne!(create(&mut store, "bot"), bot);
+}
+
+#[test]
+fn failed_signature_names_its_contract() {
+    let mut store = Store::new();
+    let IdentityResponse::Error { contract_key, .. } =
+        handle_request(&mut store, sign_request("missing"))
+    else {
+        panic!("expected an error");
+    };
+    assert_eq!(contract_key.as_deref(), Some("contract-1"));
+}
+
+#[test]
+fn raw_key_imported_once() {
+    let mut store = Store::new();
+    let secret_key = [9u8; 32];
+    let import = || IdentityRequest::ImportKey {
+        name: "default".to_string(),
+        secret_key,
+    };
+
+    let IdentityResponse::Imported { public_key, .. } = handle_request(&mut store, import()) else {
+        panic!("expected the key to be imported");
+    };
+    let signature: [u8; 64] = match handle_request(&mut store, sign_request("default")) {
+        IdentityResponse::Signature { signature, .. } => signature.try_into().unwrap(),
+        other => panic!("unexpected response: {:?}", other),
+    };
+    let message = search_common::hashing::attestation_message(
+        "contract-1",
+        &[7u8; 32],
+        1_700_000_000_000,
+        &[1, 2, 3, 4],
+    );
+    assert!(verify_signature(&public_key, &message, &signature));
+
+    // The name is now taken
+    assert!(matches!(
+        handle_request(&mut store, import()),
+        IdentityResponse::Error { .. }
+    ));
+}
+
+#[test]
+fn only_allowed_apps_accepted() {
+    let params = IdentityParameters {
+        allowed_apps: vec![[1u8; 32]],
+        ..Default::default()
+    };
+    let params = IdentityParameters::from_bytes(&params.to_bytes()).unwrap();
+    assert!(params.allows(Some(&[1u8; 32])));
+    assert!(!params.allows(Some(&[2u8; 32])));
+    // Unattested messages are refused
+    assert!(!params.allows(None));
+    assert!(!IdentityParameters::default().allows(Some(&[1u8; 32])));
+    assert!(IdentityParameters::from_bytes(&[0xff]).is_none());
+}
+
+#[test]
+fn trust_list_signed_by_its_owner() {
+    let mut store = Store::new();
+    let owner = create(&mut store, "curator");
+    let link = |pubkey: u8, weight: u32| TrustLink {
+        pubkey: [pubkey; 32],
+        weight,
+        depth: 1,
+    };
+
+    let response = handle_request(
+        &mut store,
+        IdentityRequest::SignTrustList {
+            name: "curator".to_string(),
+            version: 5,
+            endorsements: vec![link(3, 10), link(1, 20), link(3, 30)],
+        },
+    );
+    let IdentityResponse::TrustList(list) = response else {
+        panic!("unexpected response: {:?}", response);
+    };
+    assert_eq!(list.owner, owner);
+    assert_eq!(list.version, 5);
+    // Sorted by key, the first endorsement of a key kept
+    assert_eq!(list.endorsements, vec![link(1, 20), link(3, 10)]);
+    assert!(verify_trust_list(&list, &owner));
+}
+
+#[test]
+fn unpublishable_trust_list_refused() {
+    let mut store = Store::new();
+    create(&mut store, "curator");
+    let sign = |version: u64, weight: u32| IdentityRequest::SignTrustList {
+        name: "curator".to_string(),
+        version,
+        endorsements: vec![TrustLink {
+            pubkey: [1u8; 32],
+            weight,
+            depth: 0,
+        }],
+    };
+
+    for request in [sign(0, 10), sign(1, 0), sign(1, 101)] {
+        assert!(matches!(
+            handle_request(&mut store, request),
+            IdentityResponse::Error { .. }
+        ));
+    }
+}
@@ -0,0 +1,108 @@
+use sha2::{Digest, Sha256};
+
+use crate::types::AntifloodToken;
+
+/// Minimum proof-of-work difficulty (leading zero bits) when parameters don't specify one.
+pub const DEFAULT_MIN_DIFFICULTY: u8 = 16;
+
+/// Default minimum difficulty of a shard posting's token. Lower than
+/// `DEFAULT_MIN_DIFFICULTY` since a contribution mines one per indexed term.
+pub const DEFAULT_POSTING_DIFFICULTY: u8 = 12;
+
+/// Maximum accepted nonce length in bytes.
+pub const MAX_NONCE_LEN: usize = 32;
+
+const ATTESTATION_DOMAIN: &[u8] = b"freenet-search:antiflood:attestation:v1";
+const POSTING_DOMAIN: &[u8] = b"freenet-search:antiflood:posting:v1";
+
+/// Challenge binding a catalog attestation's PoW to its payload:
+/// sha256(domain + pubkey + len(contract_key) + contract_key + metadata_hash + token_created_at).
+pub fn attestation_challenge(
+    contributor_pubkey: &[u8; 32],
+    contract_key: &str,
+    metadata_hash: &[u8; 32],
+    token_created_at: u64,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(ATTESTATION_DOMAIN);
+    hasher.update(contributor_pubkey);
+    hasher.update((contract_key.len() as u64).to_be_bytes());
+    hasher.update(contract_key.as_bytes());
+    hasher.update(metadata_hash);
+    hasher.update(token_created_at.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// Challenge binding a shard posting's PoW to its payload:
+/// sha256(domain + len(word) + word + len(contract_key) + contract_key + tf_idf_score
+/// + term_frequency + len(positions) + positions).
+pub fn posting_challenge(
+    word: &str,
+    contract_key: &str,
+    tf_idf_score: u32,
+    term_frequency: u32,
+    positions: &[u32],
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(POSTING_DOMAIN);
+    for field in [word, contract_key] {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.update(tf_idf_score.to_be_bytes());
+    hasher.update(term_frequency.to_be_bytes());
+    hasher.update((positions.len() as u64).to_be_bytes());
+    for pos in positions {
+        hasher.update(pos.to_be_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// Proof-of-work hash: sha256(challenge + nonce).
+pub fn pow_hash(challenge: &[u8; 32], nonce: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(challenge);
+    hasher.update(nonce);
+    hasher.finalize().into()
+}
+
+/// Count leading zero bits of a hash.
+pub fn leading_zero_bits(hash: &[u8; 32]) -> u32 {
+    let mut count = 0u32;
+    for &byte in hash {
+        if byte == 0 {
+            count += 8;
+        } else {
+            count += byte.leading_zeros();
+            break;
+        }
+    }
+    count
+}
+
+/// Verify a token against its challenge: the claimed difficulty must meet the minimum
+/// and sha256(challenge + nonce) must have at least that many leading zero bits.
+pub fn verify_token(token: &AntifloodToken, challenge: &[u8; 32], min_difficulty: u8) -> bool {
+    if token.nonce.is_empty() || token.nonce.len() > MAX_NONCE_LEN {
+        return false;
+    }
+    if token.difficulty == 0 || token.difficulty < min_difficulty {
+        return false;
+    }
+    leading_zero_bits(&pow_hash(challenge, &token.nonce)) >= token.difficulty as u32
+}
+
+/// Mine a token for the given challenge by incrementing a little-endian u64 nonce.
+pub fn mine_token(challenge: &[u8; 32], difficulty: u8) -> AntifloodToken {
+    let mut nonce = 0u64;
+    loop {
+        let nonce_bytes = nonce.to_le_bytes();
+        if leading_zero_bits(&pow_hash(challenge, &nonce_bytes)) >= difficulty as u32 {
+            return AntifloodToken {
+                nonce: nonce_bytes.to_vec(),
+                difficulty,
+            };
+        }
+        nonce += 1;
+    }
+}
@@ -1,28 +1,69 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-const K: usize = 7;
+/// Hash functions used by `BloomFilter::new`.
+const DEFAULT_HASHES: u32 = 7;
+/// Most hash functions accepted in a decoded filter, bounding the work a peer's
+/// summary can cause.
+pub const MAX_HASHES: u32 = 32;
+/// Target false-positive rate (x10000) of state summaries: 1%.
+pub const SUMMARY_FP_RATE: u32 = 100;
 
-/// A bloom filter with k=7 hash functions for StateSummary.
+/// A bloom filter for StateSummary. Its size, hash count and salt travel with
+/// the bits, so the receiver tests membership exactly as the sender inserted.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BloomFilter {
     bits: Vec<u8>,
     num_bits: usize,
+    num_hashes: u32,
+    /// Mixed into every hash, so differently salted filters collide on different items.
+    salt: u64,
 }
 
 impl BloomFilter {
-    /// Create a new bloom filter with the given number of bits.
+    /// Create a new bloom filter with the given number of bits and k=7 hash functions.
     pub fn new(num_bits: usize) -> Self {
+        Self::with_params(num_bits, DEFAULT_HASHES, 0)
+    }
+
+    /// Create a bloom filter sized for `items` insertions at a false-positive rate
+    /// of about `fp_rate` / 10000 (clamped to 1..=5000).
+    ///
+    /// Uses k = ceil(log2(1/p)) hash functions and k / ln 2 bits per item,
+    /// the optimum for that k.
+    pub fn with_capacity(items: usize, fp_rate: u32, salt: u64) -> Self {
+        let fp_rate = fp_rate.clamp(1, 5000);
+        let num_hashes = 10000u32
+            .div_ceil(fp_rate)
+            .next_power_of_two()
+            .ilog2()
+            .max(1);
+        // 1443 / 1000 ~ 1 / ln 2
+        let num_bits = (items.max(1) * num_hashes as usize * 1443 / 1000).max(64);
+        Self::with_params(num_bits, num_hashes, salt)
+    }
+
+    fn with_params(num_bits: usize, num_hashes: u32, salt: u64) -> Self {
         let num_bytes = num_bits.div_ceil(8);
         Self {
             bits: vec![0u8; num_bytes],
             num_bits,
+            num_hashes,
+            salt,
         }
     }
 
+    pub fn num_bits(&self) -> usize {
+        self.num_bits
+    }
+
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
     /// Insert an item into the bloom filter.
     pub fn insert(&mut self, item: &[u8]) {
-        for i in 0..K {
+        for i in 0..self.num_hashes {
             let pos = self.hash_position(item, i as u8);
             let byte_idx = pos / 8;
             let bit_idx = pos % 8;
@@ -34,7 +75,7 @@ impl BloomFilter {
 
     /// Check if an item might be in the bloom filter.
     pub fn contains(&self, item: &[u8]) -> bool {
-        for i in 0..K {
+        for i in 0..self.num_hashes {
             let pos = self.hash_position(item, i as u8);
             let byte_idx = pos / 8;
             let bit_idx = pos % 8;
@@ -52,14 +93,25 @@ impl BloomFilter {
         buf
     }
 
-    /// Deserialize from bytes.
+    /// Deserialize from bytes, rejecting filters whose parameters don't match
+    /// their bits or whose hash count exceeds `MAX_HASHES`.
     pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        ciborium::de::from_reader(bytes).ok()
+        let filter: Self = ciborium::de::from_reader(bytes).ok()?;
+        filter.is_well_formed().then_some(filter)
+    }
+
+    /// Whether a decoded filter's parameters match its bits and its hash count
+    /// is within `MAX_HASHES`.
+    pub fn is_well_formed(&self) -> bool {
+        self.num_bits > 0
+            && self.bits.len() == self.num_bits.div_ceil(8)
+            && (1..=MAX_HASHES).contains(&self.num_hashes)
     }
 
     fn hash_position(&self, item: &[u8], prefix: u8) -> usize {
         let mut hasher = Sha256::new();
         hasher.update([prefix]);
+        hasher.update(self.salt.to_be_bytes());
         hasher.update(item);
         let hash: [u8; 32] = hasher.finalize().into();
         let val = u64::from_be_bytes([
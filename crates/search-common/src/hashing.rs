@@ -1,18 +1,97 @@
 use sha2::{Digest, Sha256};
 
-/// Compute metadata hash: sha256(len(title) + title + len(description) + description + len(snippet) + snippet).
-/// Uses length-prefixed fields to avoid ambiguity with embedded null bytes.
-pub fn metadata_hash(title: &str, description: &str, snippet: &str) -> [u8; 32] {
+use crate::trust::TrustLink;
+
+/// Compute metadata hash: `variant_hash` over the full snippet's `snippet_hash`.
+/// This is what contributors attest to.
+pub fn metadata_hash(
+    title: &str,
+    description: &str,
+    mini_snippet: &str,
+    snippet: &str,
+) -> [u8; 32] {
+    variant_hash(title, description, mini_snippet, &snippet_hash(snippet))
+}
+
+/// sha256(len(snippet) + snippet). Catalog variants keep this beside the full
+/// snippet, so a variant can be verified before its snippet arrives.
+pub fn snippet_hash(snippet: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((snippet.len() as u64).to_be_bytes());
+    hasher.update(snippet.as_bytes());
+    hasher.finalize().into()
+}
+
+/// The key of a catalog variant: sha256(len(title) + title + len(description) +
+/// description + len(mini_snippet) + mini_snippet + snippet_hash). Uses
+/// length-prefixed fields to avoid ambiguity with embedded null bytes.
+pub fn variant_hash(
+    title: &str,
+    description: &str,
+    mini_snippet: &str,
+    snippet_hash: &[u8; 32],
+) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update((title.len() as u64).to_be_bytes());
     hasher.update(title.as_bytes());
     hasher.update((description.len() as u64).to_be_bytes());
     hasher.update(description.as_bytes());
-    hasher.update((snippet.len() as u64).to_be_bytes());
-    hasher.update(snippet.as_bytes());
+    hasher.update((mini_snippet.len() as u64).to_be_bytes());
+    hasher.update(mini_snippet.as_bytes());
+    hasher.update(snippet_hash);
     hasher.finalize().into()
 }
 
+/// Message a contributor signs for a catalog attestation:
+/// domain + len(contract_key) + contract_key + metadata_hash + token_created_at + len(nonce) + nonce.
+/// The PoW nonce is included so a signature can't be re-attached to a different token.
+pub fn attestation_message(
+    contract_key: &str,
+    metadata_hash: &[u8; 32],
+    token_created_at: u64,
+    nonce: &[u8],
+) -> Vec<u8> {
+    const DOMAIN: &[u8] = b"freenet-search:attestation:v1";
+    let mut msg = Vec::with_capacity(DOMAIN.len() + contract_key.len() + nonce.len() + 56);
+    msg.extend_from_slice(DOMAIN);
+    msg.extend_from_slice(&(contract_key.len() as u64).to_be_bytes());
+    msg.extend_from_slice(contract_key.as_bytes());
+    msg.extend_from_slice(metadata_hash);
+    msg.extend_from_slice(&token_created_at.to_be_bytes());
+    msg.extend_from_slice(&(nonce.len() as u64).to_be_bytes());
+    msg.extend_from_slice(nonce);
+    msg
+}
+
+/// Message the old key signs to rotate to a new one:
+/// domain + old_pubkey + new_pubkey + rotated_at.
+pub fn rotation_message(old_pubkey: &[u8; 32], new_pubkey: &[u8; 32], rotated_at: u64) -> Vec<u8> {
+    const DOMAIN: &[u8] = b"freenet-search:rotation:v1";
+    let mut msg = Vec::with_capacity(DOMAIN.len() + 72);
+    msg.extend_from_slice(DOMAIN);
+    msg.extend_from_slice(old_pubkey);
+    msg.extend_from_slice(new_pubkey);
+    msg.extend_from_slice(&rotated_at.to_be_bytes());
+    msg
+}
+
+/// Message a curator signs for their published trust list:
+/// domain + owner + version + len(endorsements) + (pubkey + weight + depth) per endorsement.
+pub fn trust_list_message(owner: &[u8; 32], version: u64, endorsements: &[TrustLink]) -> Vec<u8> {
+    const DOMAIN: &[u8] = b"freenet-search:trust-list:v1";
+    let mut msg = Vec::with_capacity(DOMAIN.len() + 48 + endorsements.len() * 37);
+    msg.extend_from_slice(DOMAIN);
+    msg.extend_from_slice(owner);
+    msg.extend_from_slice(&version.to_be_bytes());
+    msg.extend_from_slice(&(endorsements.len() as u64).to_be_bytes());
+    for link in endorsements {
+        msg.extend_from_slice(&link.pubkey);
+        msg.extend_from_slice(&link.weight.to_be_bytes());
+        msg.push(link.depth);
+    }
+    msg
+}
+
 /// Determine which shard a word belongs to: sha256(word) % shard_count.
 pub fn shard_for_word(word: &str, shard_count: u8) -> u8 {
     let mut hasher = Sha256::new();
@@ -1,14 +1,22 @@
 //! Shared types, hashing, tokenization, and scoring for the Freenet search engine.
 //!
 //! All cross-node data uses CBOR serialization (ciborium) and integer arithmetic
-//! with x10000 scaling (no floating-point). Provides bloom filters for state sync,
-//! SHA-256 metadata hashing, Unicode normalization, and web container parsing.
+//! with x10000 scaling (no floating-point). Provides versioned bloom filter and
+//! IBLT summaries for state sync, proof-of-work antiflood tokens, SHA-256
+//! metadata hashing, Unicode normalization, query parsing and evaluation,
+//! prefix/typo term expansion, web-of-trust weights for personalized ranking,
+//! and web container parsing.
 
+pub mod antiflood;
 pub mod bloom;
 pub mod extraction;
 pub mod hashing;
 pub mod normalization;
+pub mod query;
 pub mod scoring;
+pub mod sync;
 pub mod tokenization;
+pub mod trust;
 pub mod types;
+pub mod vocabulary;
 pub mod web_container;
@@ -0,0 +1,514 @@
+//! Query language: parsing into an AST and evaluating it against a document.
+//!
+//! Syntax (Lucene-like):
+//! - bare words are optional terms; a document must match at least one
+//! - `+word` is required, `-word` is excluded
+//! - `a b OR c` matches either side; parentheses group
+//! - `"chat room"` is an exact phrase, `"chat room"~3` lets up to 3 extra
+//!   tokens separate the words, in any order
+//! - `title:`, `description:` and `snippet:` scope a term, phrase or group
+//!   to one field; unscoped terms search the snippet index
+
+use crate::tokenization::{term_positions, Analyzer};
+use std::collections::{BTreeMap, HashMap};
+
+/// Score a title or description match earns (x10000), before phrase boosts.
+pub const FIELD_MATCH_SCORE: u32 = 10000;
+
+/// A quoted phrase from the query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Phrase {
+    pub terms: Vec<String>,
+    /// Extra tokens allowed between the terms; 0 requires them adjacent and in order.
+    pub slop: u32,
+}
+
+/// Which part of a document a clause matches against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    /// The default: the snippet postings in the shard index.
+    Any,
+    Title,
+    Description,
+    Snippet,
+}
+
+impl Field {
+    fn from_prefix(prefix: &str) -> Option<Field> {
+        match prefix.to_lowercase().as_str() {
+            "title" => Some(Field::Title),
+            "description" => Some(Field::Description),
+            "snippet" => Some(Field::Snippet),
+            _ => None,
+        }
+    }
+
+    fn uses_postings(self) -> bool {
+        matches!(self, Field::Any | Field::Snippet)
+    }
+}
+
+/// How a clause in a group constrains the match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Occur {
+    Should,
+    Must,
+    MustNot,
+}
+
+/// Query AST.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Query {
+    Term {
+        field: Field,
+        term: String,
+    },
+    Phrase {
+        field: Field,
+        phrase: Phrase,
+    },
+    /// Clauses side by side: all `Must`, no `MustNot`, and at least one
+    /// `Must` or `Should` has to match.
+    Group(Vec<(Occur, Query)>),
+    /// Alternatives separated by `OR`.
+    Or(Vec<Query>),
+}
+
+/// A parsed query and the terms needed to evaluate it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedQuery {
+    pub root: Query,
+    /// Distinct terms to look up in the shard index, including excluded ones.
+    pub terms: Vec<String>,
+    /// Distinct terms a result may be matched by, for highlighting.
+    pub highlight_terms: Vec<String>,
+    /// True if some clause can match on title or description alone, so
+    /// catalog entries without postings are candidates too.
+    pub scans_metadata: bool,
+}
+
+/// Parse a raw query string, analyzing its words with the index's analyzer.
+/// Malformed input degrades gracefully: unmatched parentheses are closed or
+/// dropped and an unterminated quote runs to the end.
+pub fn parse(raw: &str, analyzer: &Analyzer) -> ParsedQuery {
+    let tokens = lex(raw);
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        analyzer,
+    };
+    let root = parser.parse_or(Field::Any);
+
+    let mut parsed = ParsedQuery {
+        root: Query::Group(Vec::new()),
+        terms: Vec::new(),
+        highlight_terms: Vec::new(),
+        scans_metadata: false,
+    };
+    collect(&root, false, &mut parsed);
+    parsed.root = root;
+    parsed
+}
+
+fn collect(query: &Query, excluded: bool, parsed: &mut ParsedQuery) {
+    let add = |field: Field, term: &String, parsed: &mut ParsedQuery| {
+        if field.uses_postings() && !parsed.terms.contains(term) {
+            parsed.terms.push(term.clone());
+        }
+        if !excluded {
+            if !parsed.highlight_terms.contains(term) {
+                parsed.highlight_terms.push(term.clone());
+            }
+            if !field.uses_postings() {
+                parsed.scans_metadata = true;
+            }
+        }
+    };
+    match query {
+        Query::Term { field, term } => add(*field, term, parsed),
+        Query::Phrase { field, phrase } => {
+            for term in &phrase.terms {
+                add(*field, term, parsed);
+            }
+        }
+        Query::Group(clauses) => {
+            for (occur, clause) in clauses {
+                collect(clause, excluded || *occur == Occur::MustNot, parsed);
+            }
+        }
+        Query::Or(alternatives) => {
+            for alternative in alternatives {
+                collect(alternative, excluded, parsed);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    Must,
+    MustNot,
+    Field(Field),
+    Word(String),
+    Quoted(String, u32),
+}
+
+fn lex(raw: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut rest = raw;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c == '(' {
+            depth += 1;
+            tokens.push(Token::Open);
+            rest = &rest[1..];
+        } else if c == ')' {
+            // Drop unmatched closers
+            if depth > 0 {
+                depth -= 1;
+                tokens.push(Token::Close);
+            }
+            rest = &rest[1..];
+        } else if c == '"' {
+            let after = &rest[1..];
+            let (inner, tail) = match after.find('"') {
+                Some(close) => (&after[..close], &after[close + 1..]),
+                None => (after, ""),
+            };
+            let (slop, tail) = parse_slop(tail);
+            tokens.push(Token::Quoted(inner.to_string(), slop));
+            rest = tail;
+        } else if (c == '+' || c == '-')
+            && rest[1..]
+                .chars()
+                .next()
+                .is_some_and(|n| !n.is_whitespace() && n != ')')
+        {
+            tokens.push(if c == '+' {
+                Token::Must
+            } else {
+                Token::MustNot
+            });
+            rest = &rest[1..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = &rest[end..];
+
+            if word == "OR" {
+                tokens.push(Token::Or);
+                continue;
+            }
+            match word
+                .split_once(':')
+                .and_then(|(prefix, value)| Field::from_prefix(prefix).map(|f| (f, value)))
+            {
+                Some((field, value)) => {
+                    tokens.push(Token::Field(field));
+                    if !value.is_empty() {
+                        tokens.push(Token::Word(value.to_string()));
+                    }
+                }
+                None => tokens.push(Token::Word(word.to_string())),
+            }
+        }
+    }
+    tokens
+}
+
+/// Parse an optional `~N` suffix directly after a closing quote.
+fn parse_slop(tail: &str) -> (u32, &str) {
+    let Some(digits) = tail.strip_prefix('~') else {
+        return (0, tail);
+    };
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let slop = digits[..end]
+        .parse::<u32>()
+        .unwrap_or(if end == 0 { 0 } else { u32::MAX });
+    (slop, &digits[end..])
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    analyzer: &'a Analyzer,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// or := seq ("OR" seq)*
+    fn parse_or(&mut self, field: Field) -> Query {
+        let mut alternatives = vec![self.parse_seq(field)];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            alternatives.push(self.parse_seq(field));
+        }
+        alternatives.retain(|q| *q != Query::Group(Vec::new()));
+        match alternatives.len() {
+            0 => Query::Group(Vec::new()),
+            1 => alternatives.pop().unwrap(),
+            _ => Query::Or(alternatives),
+        }
+    }
+
+    /// seq := (("+" | "-")? primary)*
+    fn parse_seq(&mut self, field: Field) -> Query {
+        let mut clauses = Vec::new();
+        while let Some(token) = self.peek() {
+            let occur = match token {
+                Token::Close | Token::Or => break,
+                Token::Must => Occur::Must,
+                Token::MustNot => Occur::MustNot,
+                _ => Occur::Should,
+            };
+            if occur != Occur::Should {
+                self.pos += 1;
+            }
+            if let Some(query) = self.parse_primary(field) {
+                clauses.push((occur, query));
+            }
+        }
+        if clauses.len() == 1 && clauses[0].0 != Occur::MustNot {
+            return clauses.pop().unwrap().1;
+        }
+        Query::Group(clauses)
+    }
+
+    /// primary := field? ("(" or ")" | quoted | word)
+    fn parse_primary(&mut self, field: Field) -> Option<Query> {
+        match self.peek()? {
+            Token::Close | Token::Or => None,
+            _ => match self.next()? {
+                Token::Field(scoped) => self.parse_primary(scoped),
+                Token::Open => {
+                    let query = self.parse_or(field);
+                    if self.peek() == Some(&Token::Close) {
+                        self.pos += 1;
+                    }
+                    (query != Query::Group(Vec::new())).then_some(query)
+                }
+                Token::Word(text) => text_query(self.analyzer, field, &text, 0),
+                Token::Quoted(text, slop) => text_query(self.analyzer, field, &text, slop),
+                // A stray prefix, e.g. "+ -word"; the next primary picks up the rest
+                Token::Must | Token::MustNot | Token::Close | Token::Or => None,
+            },
+        }
+    }
+}
+
+/// A single token is a term, several (from a quote or a word like "wi-fi")
+/// form a phrase, none (stop words, punctuation) drop the clause.
+fn text_query(analyzer: &Analyzer, field: Field, text: &str, slop: u32) -> Option<Query> {
+    let mut terms = analyzer.tokenize(text);
+    match terms.len() {
+        0 => None,
+        1 => Some(Query::Term {
+            field,
+            term: terms.pop().unwrap(),
+        }),
+        _ => Some(Query::Phrase {
+            field,
+            phrase: Phrase { terms, slop },
+        }),
+    }
+}
+
+/// A term's entry in the snippet index for one document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Posting {
+    pub relevance: u32,
+    pub positions: Vec<u32>,
+}
+
+/// What the evaluator knows about one candidate document.
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    pub postings: HashMap<String, Posting>,
+    title: BTreeMap<String, Vec<u32>>,
+    description: BTreeMap<String, Vec<u32>>,
+}
+
+impl Document {
+    pub fn new(title: &str, description: &str, analyzer: &Analyzer) -> Self {
+        Document {
+            postings: HashMap::new(),
+            title: term_positions(&analyzer.tokenize(title)),
+            description: term_positions(&analyzer.tokenize(description)),
+        }
+    }
+
+    fn field(&self, field: Field) -> &BTreeMap<String, Vec<u32>> {
+        match field {
+            Field::Description => &self.description,
+            _ => &self.title,
+        }
+    }
+}
+
+/// Evaluate a query against a document: its relevance (x10000 scale) if it
+/// matches, `None` if it doesn't.
+pub fn evaluate(query: &Query, doc: &Document) -> Option<u32> {
+    match query {
+        Query::Term { field, term } if field.uses_postings() => {
+            doc.postings.get(term).map(|p| p.relevance)
+        }
+        Query::Term { field, term } => doc
+            .field(*field)
+            .contains_key(term)
+            .then_some(FIELD_MATCH_SCORE),
+        Query::Phrase { field, phrase } if field.uses_postings() => {
+            let boost = phrase_boost(phrase, |t| {
+                doc.postings.get(t).map(|p| p.positions.as_slice())
+            })?;
+            let sum: u64 = phrase
+                .terms
+                .iter()
+                .filter_map(|t| doc.postings.get(t))
+                .map(|p| p.relevance as u64)
+                .sum();
+            Some((sum * boost as u64 / 10000).min(u32::MAX as u64) as u32)
+        }
+        Query::Phrase { field, phrase } => {
+            let positions = doc.field(*field);
+            let boost = phrase_boost(phrase, |t| positions.get(t).map(|p| p.as_slice()))?;
+            Some((FIELD_MATCH_SCORE as u64 * boost as u64 / 10000) as u32)
+        }
+        Query::Group(clauses) => {
+            let mut score = 0u32;
+            let mut matched = false;
+            for (occur, clause) in clauses {
+                match occur {
+                    Occur::MustNot => {
+                        if evaluate(clause, doc).is_some() {
+                            return None;
+                        }
+                    }
+                    Occur::Must => {
+                        score = score.saturating_add(evaluate(clause, doc)?);
+                        matched = true;
+                    }
+                    Occur::Should => {
+                        if let Some(s) = evaluate(clause, doc) {
+                            score = score.saturating_add(s);
+                            matched = true;
+                        }
+                    }
+                }
+            }
+            matched.then_some(score)
+        }
+        Query::Or(alternatives) => alternatives
+            .iter()
+            .filter_map(|q| evaluate(q, doc))
+            .reduce(|a, b| a.saturating_add(b)),
+    }
+}
+
+/// How far a phrase's best occurrence is from perfect adjacency: `Some(0)`
+/// for the exact phrase, `None` if no occurrence fits within `slop`.
+/// `positions[i]` are the sorted token positions of the phrase's i-th term.
+pub fn phrase_slack(positions: &[&[u32]], slop: u32) -> Option<u32> {
+    if positions.is_empty() || positions.iter().any(|p| p.is_empty()) {
+        return None;
+    }
+    if slop == 0 {
+        let exact = positions[0].iter().any(|&start| {
+            positions.iter().enumerate().skip(1).all(|(i, p)| {
+                start
+                    .checked_add(i as u32)
+                    .is_some_and(|want| p.binary_search(&want).is_ok())
+            })
+        });
+        return exact.then_some(0);
+    }
+
+    // Repeated terms share a position list, and need that many distinct positions
+    let mut lists: Vec<(&[u32], usize)> = Vec::with_capacity(positions.len());
+    for p in positions {
+        match lists.iter_mut().find(|(list, _)| list == p) {
+            Some((_, need)) => *need += 1,
+            None => lists.push((p, 1)),
+        }
+    }
+    let span = min_window(&lists)?;
+    // Distinct terms could still share a position, so the span may fall short
+    let slack = span.saturating_sub(positions.len() as u32);
+    (slack <= slop).then_some(slack)
+}
+
+/// Smallest number of tokens covering `need` distinct positions from each
+/// list, or `None` if some list has too few.
+fn min_window(lists: &[(&[u32], usize)]) -> Option<u32> {
+    let mut events: Vec<(u32, usize)> = lists
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (list, _))| list.iter().map(move |&p| (p, i)))
+        .collect();
+    events.sort_unstable();
+
+    let mut have = vec![0usize; lists.len()];
+    let mut missing = lists.len();
+    let mut best: Option<u32> = None;
+    let mut start = 0;
+    for &(end, i) in &events {
+        have[i] += 1;
+        if have[i] == lists[i].1 {
+            missing -= 1;
+        }
+        while missing == 0 {
+            let (first, j) = events[start];
+            let span = end - first + 1;
+            best = Some(best.map_or(span, |b| b.min(span)));
+            if have[j] == lists[j].1 {
+                missing += 1;
+            }
+            have[j] -= 1;
+            start += 1;
+        }
+    }
+    best
+}
+
+/// Relevance multiplier (x10000) for a phrase match: 2x for the exact
+/// phrase, tapering toward 1x as the words drift apart.
+pub fn proximity_boost(slack: u32) -> u32 {
+    10000 + 10000 / (slack + 1)
+}
+
+/// Relevance multiplier (x10000) a document earns for `phrase`, or `None` if
+/// it fails the phrase. `positions_of` returns a term's positions in the
+/// document, or `None` if the document lacks the term. Postings without
+/// recorded positions can't be checked and pass unboosted.
+pub fn phrase_boost<'a>(
+    phrase: &Phrase,
+    positions_of: impl Fn(&str) -> Option<&'a [u32]>,
+) -> Option<u32> {
+    let positions: Vec<&[u32]> = phrase
+        .terms
+        .iter()
+        .map(|t| positions_of(t))
+        .collect::<Option<_>>()?;
+    if positions.iter().any(|p| p.is_empty()) {
+        return Some(10000);
+    }
+    phrase_slack(&positions, phrase.slop).map(proximity_boost)
+}
@@ -1,4 +1,5 @@
 use crate::types::Status;
+use std::collections::BTreeMap;
 
 /// Integer TF-IDF score (x10000 scaling, no floats).
 /// tf = (term_count * 10000) / total_terms
@@ -13,13 +14,53 @@ pub fn integer_tf_idf(
     if total_terms == 0 || docs_with_term == 0 {
         return 0;
     }
-    let tf = (term_count as u64 * 10000) / total_terms as u64;
+    tf_idf(
+        term_frequency(term_count, total_terms),
+        inverse_document_frequency(total_docs, docs_with_term),
+    )
+}
+
+/// Term frequency (x10000): share of a document's terms that are this term.
+pub fn term_frequency(term_count: u32, total_terms: u32) -> u32 {
+    if total_terms == 0 {
+        return 0;
+    }
+    ((term_count as u64 * 10000) / total_terms as u64) as u32
+}
+
+/// Inverse document frequency (x10000), at least 10000.
+/// `total_docs` is clamped to `docs_with_term` so a lagging catalog can't
+/// push the ratio below 1.
+pub fn inverse_document_frequency(total_docs: u32, docs_with_term: u32) -> u32 {
+    if docs_with_term == 0 {
+        return 10000;
+    }
     // idf = log2(total_docs / docs_with_term) * 10000
     // When total_docs == docs_with_term, log2(1) = 0, so we add a baseline of 10000
     // to ensure the score is always > 0 when term_count > 0
+    let total_docs = total_docs.max(docs_with_term);
     let ratio = total_docs as u64 * 10000 / docs_with_term as u64;
-    let idf = integer_log2_scaled(ratio) + 10000;
-    ((tf * idf) / 10000) as u32
+    (integer_log2_scaled(ratio) + 10000) as u32
+}
+
+/// Combine a term frequency and inverse document frequency (both x10000).
+pub fn tf_idf(tf: u32, idf: u32) -> u32 {
+    ((tf as u64 * idf as u64) / 10000) as u32
+}
+
+/// Highest score `integer_tf_idf` can give a term with this frequency,
+/// reached by a term in one document of the largest possible corpus.
+pub fn max_tf_idf(term_frequency: u32) -> u32 {
+    tf_idf(term_frequency, inverse_document_frequency(u32::MAX, 1))
+}
+
+/// Fold a token stream into per-term occurrence counts.
+pub fn term_counts(tokens: &[String]) -> BTreeMap<String, u32> {
+    let mut counts = BTreeMap::new();
+    for token in tokens {
+        *counts.entry(token.clone()).or_insert(0) += 1;
+    }
+    counts
 }
 
 /// Approximate log2(x/10000) * 10000, where x is already multiplied by 10000.
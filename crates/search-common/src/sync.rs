@@ -0,0 +1,264 @@
+//! State summaries for `summarize_state` / `get_state_delta`.
+//!
+//! A summary is a versioned envelope so the responder answers in kind:
+//! - version 1 is a bloom filter of items. Compact, but a false positive
+//!   hides an item from the peer for that round.
+//! - version 3 is an invertible bloom lookup table of item digests. The
+//!   responder subtracts its own table and peels the difference, finding exactly
+//!   the items the peer lacks or holds an older version of. When the difference
+//!   outgrows the table, it also sends every item still sitting in cells left
+//!   unpeeled, so nothing missing or stale at the peer is ever held back.
+//!
+//! A responder that doesn't know a summary's version, including the retired
+//! version 2 key digest, sends all its items; so does one given a summary from
+//! a peer that predates the envelope.
+
+use crate::bloom::{BloomFilter, SUMMARY_FP_RATE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Version tag of bloom filter summaries.
+pub const BLOOM_VERSION: u8 = 1;
+/// Version tag of invertible bloom lookup table summaries.
+pub const IBLT_VERSION: u8 = 3;
+/// Items summarized per table cell. A table decodes a difference of up to
+/// about 80% of its cells exactly.
+pub const ITEMS_PER_CELL: usize = 16;
+/// Fewest cells in a table, so small states still decode small differences.
+pub const MIN_CELLS: usize = 96;
+/// Most cells accepted in a decoded table.
+pub const MAX_CELLS: usize = 1 << 20;
+/// Cells each item is added to, one per equal part of the table.
+const CELLS_PER_ITEM: usize = 3;
+
+/// One unit of contract state as sync sees it. `key` identifies the item and
+/// `content` must change whenever the item does, so updates propagate too.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncItem {
+    pub key: Vec<u8>,
+    pub content: Vec<u8>,
+}
+
+/// How a summary is built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryKind {
+    Bloom,
+    Iblt,
+}
+
+impl SummaryKind {
+    pub fn version(self) -> u8 {
+        match self {
+            SummaryKind::Bloom => BLOOM_VERSION,
+            SummaryKind::Iblt => IBLT_VERSION,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u8,
+    body: ciborium::Value,
+}
+
+/// Invertible bloom lookup table over item digests. Each cell holds how many
+/// digests were added to it, their XOR and the XOR of their check hashes; a cell
+/// holding a single digest is recognised by its check hash and can be peeled.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Iblt {
+    counts: Vec<i32>,
+    digests: Vec<u64>,
+    checks: Vec<u64>,
+}
+
+impl Iblt {
+    /// A table of `items` with about `ITEMS_PER_CELL` items per cell.
+    pub fn new(items: &[SyncItem]) -> Self {
+        let cells = items.len().div_ceil(ITEMS_PER_CELL).max(MIN_CELLS);
+        let mut table = Self::with_cells(cells.next_multiple_of(CELLS_PER_ITEM));
+        for item in items {
+            table.toggle(item_digest(item), 1);
+        }
+        table
+    }
+
+    fn with_cells(num_cells: usize) -> Self {
+        Iblt {
+            counts: vec![0; num_cells],
+            digests: vec![0; num_cells],
+            checks: vec![0; num_cells],
+        }
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.counts.len()
+    }
+
+    fn is_well_formed(&self) -> bool {
+        let cells = self.counts.len();
+        cells > 0
+            && cells <= MAX_CELLS
+            && cells.is_multiple_of(CELLS_PER_ITEM)
+            && self.digests.len() == cells
+            && self.checks.len() == cells
+    }
+
+    /// The cells `digest` is added to, one in each part of the table.
+    fn cells(&self, digest: u64) -> [usize; CELLS_PER_ITEM] {
+        let hash = digest_hash(digest);
+        let part = self.counts.len() / CELLS_PER_ITEM;
+        std::array::from_fn(|i| {
+            let val = u64::from_be_bytes(hash[8 + 8 * i..16 + 8 * i].try_into().unwrap());
+            i * part + (val % part as u64) as usize
+        })
+    }
+
+    /// Add (`sign` 1) or remove (`sign` -1) one digest.
+    fn toggle(&mut self, digest: u64, sign: i32) {
+        let check = check_hash(digest);
+        for cell in self.cells(digest) {
+            self.counts[cell] = self.counts[cell].wrapping_add(sign);
+            self.digests[cell] ^= digest;
+            self.checks[cell] ^= check;
+        }
+    }
+
+    fn is_pure(&self, cell: usize) -> bool {
+        self.counts[cell].unsigned_abs() == 1 && self.checks[cell] == check_hash(self.digests[cell])
+    }
+
+    fn is_empty_cell(&self, cell: usize) -> bool {
+        self.counts[cell] == 0 && self.digests[cell] == 0 && self.checks[cell] == 0
+    }
+}
+
+fn item_digest(item: &SyncItem) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update((item.key.len() as u32).to_be_bytes());
+    hasher.update(&item.key);
+    hasher.update(&item.content);
+    let hash: [u8; 32] = hasher.finalize().into();
+    u64::from_be_bytes(hash[..8].try_into().unwrap())
+}
+
+/// Check hash and cell positions of a digest, all from one hash of it.
+fn digest_hash(digest: u64) -> [u8; 32] {
+    Sha256::digest(digest.to_be_bytes()).into()
+}
+
+fn check_hash(digest: u64) -> u64 {
+    u64::from_be_bytes(digest_hash(digest)[..8].try_into().unwrap())
+}
+
+fn bloom_item(item: &SyncItem) -> Vec<u8> {
+    let mut bytes = (item.key.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(&item.key);
+    bytes.extend_from_slice(&item.content);
+    bytes
+}
+
+/// Summarize a state's items as `kind`.
+pub fn summarize(kind: SummaryKind, items: &[SyncItem]) -> Vec<u8> {
+    let body = match kind {
+        SummaryKind::Bloom => {
+            // Salting with the item count changes which items collide whenever the
+            // state grows, so one hidden by a false positive gets through on a later round.
+            let mut bloom =
+                BloomFilter::with_capacity(items.len(), SUMMARY_FP_RATE, items.len() as u64);
+            for item in items {
+                bloom.insert(&bloom_item(item));
+            }
+            ciborium::Value::serialized(&bloom)
+        }
+        SummaryKind::Iblt => ciborium::Value::serialized(&Iblt::new(items)),
+    }
+    .expect("CBOR serialization failed");
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(
+        &Envelope {
+            version: kind.version(),
+            body,
+        },
+        &mut buf,
+    )
+    .expect("CBOR serialization failed");
+    buf
+}
+
+/// Indices of `items` that the peer behind `summary` may be missing or hold an
+/// older version of, or `None` if the summary is malformed.
+pub fn missing_items(summary: &[u8], items: &[SyncItem]) -> Option<Vec<usize>> {
+    let value: ciborium::Value = ciborium::de::from_reader(summary).ok()?;
+    // Summaries from before the envelope are answered like unknown versions
+    let Ok(envelope) = value.deserialized::<Envelope>() else {
+        return Some((0..items.len()).collect());
+    };
+    match envelope.version {
+        BLOOM_VERSION => {
+            let bloom: BloomFilter = envelope.body.deserialized().ok()?;
+            if !bloom.is_well_formed() {
+                return None;
+            }
+            Some(
+                (0..items.len())
+                    .filter(|&i| !bloom.contains(&bloom_item(&items[i])))
+                    .collect(),
+            )
+        }
+        IBLT_VERSION => {
+            let peer: Iblt = envelope.body.deserialized().ok()?;
+            if !peer.is_well_formed() {
+                return None;
+            }
+            Some(iblt_missing(peer, items))
+        }
+        _ => Some((0..items.len()).collect()),
+    }
+}
+
+/// Subtract `peer` from a table of `items` and peel the difference. Digests
+/// peeled as ours are the items the peer lacks; if peeling stalls, items whose
+/// cells are all still occupied may be among them too.
+fn iblt_missing(peer: Iblt, items: &[SyncItem]) -> Vec<usize> {
+    let digests: Vec<u64> = items.iter().map(item_digest).collect();
+    let mut by_digest: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, &digest) in digests.iter().enumerate() {
+        by_digest.entry(digest).or_default().push(i);
+    }
+
+    // The peer's digests count -1 and ours +1, so those both sides hold cancel
+    let mut diff = peer;
+    for count in diff.counts.iter_mut() {
+        *count = count.wrapping_neg();
+    }
+    for &digest in &digests {
+        diff.toggle(digest, 1);
+    }
+    let mut missing = vec![false; items.len()];
+    let mut pure: Vec<usize> = (0..diff.num_cells()).filter(|&c| diff.is_pure(c)).collect();
+    while let Some(cell) = pure.pop() {
+        if !diff.is_pure(cell) {
+            continue;
+        }
+        let digest = diff.digests[cell];
+        let sign = diff.counts[cell];
+        if sign == 1 {
+            for &i in by_digest.get(&digest).into_iter().flatten() {
+                missing[i] = true;
+            }
+        }
+        diff.toggle(digest, -sign);
+        pure.extend(diff.cells(digest).into_iter().filter(|&c| diff.is_pure(c)));
+    }
+
+    (0..items.len())
+        .filter(|&i| {
+            missing[i]
+                || diff
+                    .cells(digests[i])
+                    .iter()
+                    .all(|&c| !diff.is_empty_cell(c))
+        })
+        .collect()
+}
@@ -1,3 +1,8 @@
+use crate::types::MAX_POSITIONS;
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
 use unicode_normalization::UnicodeNormalization;
 
 /// English stop words list.
@@ -7,14 +12,369 @@ pub const STOP_WORDS: &[&str] = &[
     "they", "this", "to", "was", "will", "with",
 ];
 
-/// Tokenize text into words: split on non-alphanumeric, lowercase, strip accents, remove stop words.
+// Stop word lists below are accent-stripped, as they're matched after normalize_token.
+
+const GERMAN_STOP_WORDS: &[&str] = &[
+    "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da", "das", "dass",
+    "dem", "den", "der", "des", "die", "doch", "du", "ein", "eine", "einem", "einen", "einer",
+    "eines", "er", "es", "fur", "hat", "ich", "ihr", "im", "in", "ist", "ja", "mit", "nach",
+    "nicht", "noch", "nur", "oder", "sich", "sie", "sind", "so", "uber", "um", "und", "uns", "von",
+    "vor", "war", "wie", "wir", "wird", "zu", "zum", "zur",
+];
+
+const FRENCH_STOP_WORDS: &[&str] = &[
+    "a", "au", "aux", "avec", "ce", "ces", "cette", "dans", "de", "des", "du", "elle", "en", "est",
+    "et", "il", "ils", "je", "la", "le", "les", "leur", "lui", "mais", "me", "meme", "mes", "ne",
+    "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui", "sa", "se", "ses", "son", "sont",
+    "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vous",
+];
+
+const SPANISH_STOP_WORDS: &[&str] = &[
+    "a", "al", "algo", "como", "con", "de", "del", "el", "ella", "en", "es", "esta", "este",
+    "esto", "la", "las", "le", "les", "lo", "los", "mas", "me", "mi", "muy", "no", "nos", "o",
+    "para", "pero", "por", "que", "se", "si", "sin", "sobre", "su", "sus", "te", "tu", "un", "una",
+    "uno", "y", "ya", "yo",
+];
+
+const ITALIAN_STOP_WORDS: &[&str] = &[
+    "a", "al", "alla", "anche", "che", "chi", "ci", "come", "con", "da", "dal", "dei", "del",
+    "della", "di", "e", "ed", "gli", "ha", "ho", "i", "il", "in", "io", "la", "le", "lo", "ma",
+    "mi", "ne", "nel", "nella", "non", "o", "per", "piu", "se", "si", "sono", "su", "sua", "suo",
+    "tra", "tu", "un", "una", "uno",
+];
+
+const PORTUGUESE_STOP_WORDS: &[&str] = &[
+    "a", "ao", "aos", "as", "com", "como", "da", "das", "de", "do", "dos", "e", "ela", "ele", "em",
+    "esta", "este", "eu", "foi", "isso", "ja", "mais", "mas", "me", "na", "nao", "nas", "no",
+    "nos", "o", "os", "ou", "para", "pela", "pelo", "por", "que", "se", "sem", "seu", "sua", "um",
+    "uma",
+];
+
+const DUTCH_STOP_WORDS: &[&str] = &[
+    "aan", "al", "als", "bij", "dan", "dat", "de", "die", "dit", "door", "een", "en", "er", "had",
+    "heb", "het", "hij", "hoe", "ik", "in", "is", "je", "kan", "maar", "me", "met", "mij", "naar",
+    "niet", "nog", "nu", "of", "om", "ook", "op", "over", "te", "tot", "uit", "van", "voor", "was",
+    "wat", "we", "wel", "wij", "zij", "zo", "zou",
+];
+
+const SWEDISH_STOP_WORDS: &[&str] = &[
+    "att", "av", "de", "dem", "den", "det", "du", "en", "er", "ett", "for", "fran", "han", "har",
+    "hon", "i", "inte", "jag", "kan", "man", "med", "men", "mig", "min", "nar", "nu", "och", "om",
+    "pa", "sa", "sig", "sin", "ska", "som", "till", "under", "upp", "ut", "var", "vi", "vid", "ar",
+];
+
+const RUSSIAN_STOP_WORDS: &[&str] = &[
+    "а",
+    "без",
+    "бы",
+    "в",
+    "во",
+    "вот",
+    "все",
+    "вы",
+    "да",
+    "для",
+    "до",
+    "его",
+    "ее",
+    "если",
+    "есть",
+    "же",
+    "за",
+    "и",
+    "из",
+    "или",
+    "им",
+    "их",
+    "к",
+    "как",
+    "ко",
+    "когда",
+    "ли",
+    "мы",
+    "на",
+    "над",
+    "не",
+    "нет",
+    "ни",
+    "но",
+    "о",
+    "об",
+    "он",
+    "она",
+    "они",
+    "от",
+    "по",
+    "под",
+    "при",
+    "с",
+    "со",
+    "так",
+    "также",
+    "то",
+    "только",
+    "ты",
+    "у",
+    "уже",
+    "что",
+    "чтобы",
+    "это",
+    "я",
+];
+
+/// Language of an index, selecting its stop words and stemmer.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Portuguese,
+    Dutch,
+    Swedish,
+    Russian,
+}
+
+impl Language {
+    /// Parse an ISO 639-1 code such as "en" or "de".
+    pub fn from_code(code: &str) -> Option<Language> {
+        match code.to_ascii_lowercase().as_str() {
+            "en" => Some(Language::English),
+            "de" => Some(Language::German),
+            "fr" => Some(Language::French),
+            "es" => Some(Language::Spanish),
+            "it" => Some(Language::Italian),
+            "pt" => Some(Language::Portuguese),
+            "nl" => Some(Language::Dutch),
+            "sv" => Some(Language::Swedish),
+            "ru" => Some(Language::Russian),
+            _ => None,
+        }
+    }
+
+    /// Stop words, matched against normalized (lowercased, accent-stripped) words.
+    pub fn stop_words(self) -> &'static [&'static str] {
+        match self {
+            Language::English => STOP_WORDS,
+            Language::German => GERMAN_STOP_WORDS,
+            Language::French => FRENCH_STOP_WORDS,
+            Language::Spanish => SPANISH_STOP_WORDS,
+            Language::Italian => ITALIAN_STOP_WORDS,
+            Language::Portuguese => PORTUGUESE_STOP_WORDS,
+            Language::Dutch => DUTCH_STOP_WORDS,
+            Language::Swedish => SWEDISH_STOP_WORDS,
+            Language::Russian => RUSSIAN_STOP_WORDS,
+        }
+    }
+
+    fn algorithm(self) -> Algorithm {
+        match self {
+            Language::English => Algorithm::English,
+            Language::German => Algorithm::German,
+            Language::French => Algorithm::French,
+            Language::Spanish => Algorithm::Spanish,
+            Language::Italian => Algorithm::Italian,
+            Language::Portuguese => Algorithm::Portuguese,
+            Language::Dutch => Algorithm::Dutch,
+            Language::Swedish => Algorithm::Swedish,
+            Language::Russian => Algorithm::Russian,
+        }
+    }
+}
+
+/// Text analysis pipeline for an index. Every node must analyze with the same
+/// settings, so an index's analyzer is fixed by its shard parameters.
+/// The default (English stop words, no stemming) matches indexes built before
+/// analyzers were configurable.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Analyzer {
+    pub language: Language,
+    /// Reduce words to their Snowball stem, e.g. "messages" -> "messag".
+    #[serde(default)]
+    pub stemming: bool,
+}
+
+impl Analyzer {
+    /// Tokenize text into index terms. See `tokens`.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokens(text).into_iter().map(|t| t.term).collect()
+    }
+
+    /// Index terms with the span of `text` each was produced from.
+    ///
+    /// Text splits into words on non-alphanumeric characters, then each word
+    /// into runs of script. Runs of scripts written without spaces between
+    /// words (CJK, Thai, ...) become overlapping character bigrams, so
+    /// "北京大学" indexes as 北京, 京大, 大学 and a query for it is a phrase of
+    /// the same bigrams; a lone character is kept as a unigram. Other runs go
+    /// through `analyze_word`.
+    pub fn tokens(&self, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        for word in words(text) {
+            for (run, unsegmented) in script_runs(text, word) {
+                if unsegmented {
+                    push_bigrams(text, run, &mut tokens);
+                } else if let Some(term) = self.analyze_word(&text[run.clone()]) {
+                    tokens.push(Token { term, span: run });
+                }
+            }
+        }
+        tokens
+    }
+
+    /// The index term for a single space-delimited word, or `None` for empty
+    /// words and stop words.
+    pub fn analyze_word(&self, word: &str) -> Option<String> {
+        let normalized = normalize_token(word);
+        if normalized.is_empty() || self.language.stop_words().contains(&normalized.as_str()) {
+            return None;
+        }
+        if !self.stemming {
+            return Some(normalized);
+        }
+        // Snowball stemmers expect composed, accented input; strip accents after
+        let composed: String = word.to_lowercase().nfc().collect();
+        let stem = Stemmer::create(self.language.algorithm())
+            .stem(&composed)
+            .into_owned();
+        Some(strip_accents(&stem))
+    }
+}
+
+/// An index term and the byte range of the source text it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub term: String,
+    pub span: Range<usize>,
+}
+
+/// Byte ranges of maximal runs of word characters: alphanumerics, plus the
+/// dependent marks of scripts like Thai, some of which aren't alphabetic.
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        let is_word = c.is_alphanumeric() || is_dependent_mark(c);
+        match (start, is_word) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                words.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..text.len());
+    }
+    words
+}
+
+/// Split a word into runs that are, or aren't, in an unsegmented script.
+/// Dependent marks belong to the run of the character they follow.
+fn script_runs(text: &str, word: Range<usize>) -> Vec<(Range<usize>, bool)> {
+    let mut runs: Vec<(Range<usize>, bool)> = Vec::new();
+    for (i, c) in text[word.clone()].char_indices() {
+        let span = word.start + i..word.start + i + c.len_utf8();
+        match runs.last_mut() {
+            Some((run, unsegmented))
+                if is_dependent_mark(c) || *unsegmented == is_unsegmented(c) =>
+            {
+                run.end = span.end
+            }
+            _ => runs.push((span, is_unsegmented(c))),
+        }
+    }
+    runs
+}
+
+/// Overlapping bigrams of the clusters (a character plus its dependent marks)
+/// in `text[run]`, or a unigram if the run is a single cluster.
+fn push_bigrams(text: &str, run: Range<usize>, tokens: &mut Vec<Token>) {
+    let mut clusters: Vec<Range<usize>> = Vec::new();
+    for (i, c) in text[run.clone()].char_indices() {
+        let span = run.start + i..run.start + i + c.len_utf8();
+        match clusters.last_mut() {
+            Some(cluster) if is_dependent_mark(c) => cluster.end = span.end,
+            _ => clusters.push(span),
+        }
+    }
+    let spans: Vec<Range<usize>> = if clusters.len() == 1 {
+        clusters
+    } else {
+        clusters.windows(2).map(|w| w[0].start..w[1].end).collect()
+    };
+    for span in spans {
+        let term = normalize_token(&text[span.clone()]);
+        tokens.push(Token { term, span });
+    }
+}
+
+/// Scripts written without spaces between words: Han, kana, Hangul, Thai,
+/// Lao, Myanmar, Khmer.
+fn is_unsegmented(c: char) -> bool {
+    matches!(c as u32,
+        0x0E00..=0x0EFF |   // Thai, Lao
+        0x1000..=0x109F |   // Myanmar
+        0x1100..=0x11FF |   // Hangul Jamo
+        0x1780..=0x17FF |   // Khmer
+        0x2E80..=0x2FDF |   // CJK radicals
+        0x3040..=0x318F |   // Kana, Bopomofo, Hangul compatibility Jamo
+        0x31F0..=0x31FF |   // Katakana extensions
+        0x3400..=0x4DBF |   // CJK extension A
+        0x4E00..=0x9FFF |   // CJK unified ideographs
+        0xA960..=0xA97F |   // Hangul Jamo extended A
+        0xAC00..=0xD7FF |   // Hangul syllables, Jamo extended B
+        0xF900..=0xFAFF |   // CJK compatibility ideographs
+        0xFF66..=0xFFDC |   // Halfwidth kana and Hangul
+        0x20000..=0x323AF   // CJK extensions B-H
+    )
+}
+
+/// Vowel signs, tone marks and voicing marks that attach to the preceding
+/// character in unsegmented scripts.
+fn is_dependent_mark(c: char) -> bool {
+    matches!(c as u32,
+        0x0E31           |
+        0x0E34..=0x0E3A |
+        0x0E47..=0x0E4E |
+        0x0EB1           |
+        0x0EB4..=0x0EBC |
+        0x0EC8..=0x0ECE |
+        0x102B..=0x103E |
+        0x1056..=0x1059 |
+        0x105E..=0x1060 |
+        0x1062..=0x1064 |
+        0x1067..=0x106D |
+        0x1071..=0x1074 |
+        0x1082..=0x108D |
+        0x108F           |
+        0x109A..=0x109D |
+        0x17B6..=0x17D3 |
+        0x17DD           |
+        0x3099..=0x309A
+    )
+}
+
+/// Tokenize text into words: split on non-alphanumeric, lowercase, strip accents, remove stop words,
+/// bigram CJK and Thai runs.
+/// Equivalent to `Analyzer::default().tokenize(text)`.
 pub fn tokenize(text: &str) -> Vec<String> {
-    text.split(|c: char| !c.is_alphanumeric())
-        .filter(|s| !s.is_empty())
-        .map(normalize_token)
-        .filter(|s| !s.is_empty())
-        .filter(|s| !is_stop_word(s))
-        .collect()
+    Analyzer::default().tokenize(text)
+}
+
+/// Positions of each distinct token in a token stream, capped at `MAX_POSITIONS` per token.
+pub fn term_positions(tokens: &[String]) -> BTreeMap<String, Vec<u32>> {
+    let mut positions: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let list = positions.entry(token.clone()).or_default();
+        if list.len() < MAX_POSITIONS {
+            list.push(i as u32);
+        }
+    }
+    positions
 }
 
 /// Normalize a single token: lowercase, strip accents.
@@ -23,7 +383,7 @@ pub fn normalize_token(token: &str) -> String {
     strip_accents(&lower)
 }
 
-/// Check if a word is a stop word.
+/// Check if a word is an English stop word.
 pub fn is_stop_word(word: &str) -> bool {
     STOP_WORDS.contains(&word)
 }
@@ -0,0 +1,82 @@
+//! Web-of-trust weights for personalized ranking.
+//!
+//! A trust list names keys with a weight and how far to follow their own
+//! endorsements. Weights reachable from it replace the catalog's global
+//! contributor scores when results are ranked personally. Curators publish
+//! their own lists as signed `TrustListState`s for others to follow.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Highest trust weight; a path's weight is scaled by each hop's weight over this.
+pub const MAX_TRUST_WEIGHT: u32 = 100;
+/// Most endorsement hops followed from a trusted key.
+pub const MAX_TRUST_DEPTH: u8 = 3;
+
+/// A trusted key.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrustLink {
+    pub pubkey: [u8; 32],
+    /// 1..=`MAX_TRUST_WEIGHT`.
+    pub weight: u32,
+    /// Hops of the key's own endorsements to follow; 0 trusts the key alone.
+    #[serde(default)]
+    pub depth: u8,
+}
+
+/// Most endorsements a published trust list may hold.
+pub const MAX_ENDORSEMENTS: usize = 1000;
+
+/// A curator's published trust list, signed by its owner over
+/// `hashing::trust_list_message`. A higher version replaces a lower one.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrustListState {
+    pub owner: [u8; 32],
+    pub version: u64,
+    /// Sorted by pubkey, without duplicates.
+    pub endorsements: Vec<TrustLink>,
+    pub signature: Vec<u8>,
+}
+
+/// Weight of every key reachable from `links`, following `endorsements` (each
+/// key's own trust links) for as many hops as both the path and the link allow.
+/// A key reached along several paths gets the strongest one.
+pub fn trust_weights(
+    links: &[TrustLink],
+    endorsements: &BTreeMap<[u8; 32], Vec<TrustLink>>,
+) -> BTreeMap<[u8; 32], u32> {
+    const LEVELS: usize = MAX_TRUST_DEPTH as usize + 1;
+    // reach[key][r]: best weight reaching key with r hops left to follow
+    let mut reach: BTreeMap<[u8; 32], [u32; LEVELS]> = BTreeMap::new();
+    for link in links {
+        let hops = link.depth.min(MAX_TRUST_DEPTH) as usize;
+        let best = &mut reach.entry(link.pubkey).or_insert([0; LEVELS])[hops];
+        *best = (*best).max(link.weight.min(MAX_TRUST_WEIGHT));
+    }
+
+    // Every hop lowers the hops left, so one pass from the top level down settles each
+    for hops in (1..LEVELS).rev() {
+        let frontier: Vec<([u8; 32], u32)> = reach
+            .iter()
+            .filter(|(_, levels)| levels[hops] > 0)
+            .map(|(pk, levels)| (*pk, levels[hops]))
+            .collect();
+        for (pk, weight) in frontier {
+            for link in endorsements.get(&pk).into_iter().flatten() {
+                let next = (hops - 1).min(link.depth as usize);
+                let scaled = (weight as u64 * link.weight.min(MAX_TRUST_WEIGHT) as u64
+                    / MAX_TRUST_WEIGHT as u64) as u32;
+                let best = &mut reach.entry(link.pubkey).or_insert([0; LEVELS])[next];
+                *best = (*best).max(scaled);
+            }
+        }
+    }
+
+    reach
+        .into_iter()
+        .filter_map(|(pk, levels)| {
+            let weight = levels.into_iter().max().unwrap_or(0);
+            (weight > 0).then_some((pk, weight))
+        })
+        .collect()
+}
@@ -1,3 +1,4 @@
+use crate::tokenization::Analyzer;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -8,6 +9,36 @@ pub struct CatalogParameters {
     pub shard_count: u8,
     pub confirmation_weight_threshold: u32,
     pub entry_ttl_days: u16,
+    /// Minimum antiflood proof-of-work difficulty (leading zero bits) accepted in deltas.
+    #[serde(default = "default_min_pow_difficulty")]
+    pub min_pow_difficulty: u8,
+    /// Days a contributor identity must have been attesting before its
+    /// attestations count toward confirmation. 0 counts every identity at once.
+    #[serde(default)]
+    pub min_identity_age_days: u16,
+    /// Most attestations one contributor key may make per budget window,
+    /// bucketed by `token_created_at`. 0 disables the budget.
+    #[serde(default)]
+    pub max_attestations_per_window: u32,
+    /// Length of an attestation budget window in minutes.
+    #[serde(default = "default_attestation_window_mins")]
+    pub attestation_window_mins: u32,
+    /// Minutes a new attestation may be dated from the catalog's reference time,
+    /// either way; keep it well under `min_identity_age_days`. 0 disables the bound.
+    #[serde(default = "default_max_clock_skew_mins")]
+    pub max_clock_skew_mins: u32,
+}
+
+fn default_min_pow_difficulty() -> u8 {
+    crate::antiflood::DEFAULT_MIN_DIFFICULTY
+}
+
+fn default_attestation_window_mins() -> u32 {
+    60
+}
+
+fn default_max_clock_skew_mins() -> u32 {
+    24 * 60
 }
 
 /// Full state of the SearchCatalog contract.
@@ -15,6 +46,9 @@ pub struct CatalogParameters {
 pub struct CatalogState {
     pub entries: BTreeMap<String, CatalogEntry>,
     pub contributors: BTreeMap<[u8; 32], ContributorScore>,
+    /// Signed key rotations, sorted and deduplicated; a grow-only set.
+    #[serde(default)]
+    pub rotations: Vec<KeyRotation>,
 }
 
 /// A single indexed contract in the catalog.
@@ -35,6 +69,12 @@ pub struct HashVariant {
     pub title: String,
     pub description: String,
     pub mini_snippet: String,
+    /// `hashing::snippet_hash` of the full snippet the variant was extracted with.
+    #[serde(default)]
+    pub snippet_hash: [u8; 32],
+    /// The full snippet, once any delta has carried it; search results show it.
+    #[serde(default)]
+    pub snippet: String,
     pub attestations: Vec<Attestation>,
     pub total_weight: u32,
 }
@@ -46,10 +86,13 @@ pub struct Attestation {
     pub antiflood_token: AntifloodToken,
     pub token_created_at: u64,
     pub weight: u32,
+    /// ed25519 signature over `hashing::attestation_message` (64 bytes; empty if unsigned).
+    #[serde(default)]
+    pub signature: Vec<u8>,
 }
 
-/// Proof-of-work antiflood token.
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// Proof-of-work antiflood token. The default (empty nonce) never verifies.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AntifloodToken {
     pub nonce: Vec<u8>,
     pub difficulty: u8,
@@ -65,12 +108,70 @@ pub enum Status {
     Expired,
 }
 
-/// Reputation score for a contributor.
+/// Reputation score for a contributor, recomputed from the catalog's entries
+/// on every update.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ContributorScore {
     pub pubkey: [u8; 32],
     pub trust_score: u32,
     pub total_contributions: u32,
+    /// Earliest `token_created_at` among the identity's attestations; 0 for keys
+    /// with none of their own (rotated away or revoked).
+    #[serde(default)]
+    pub first_seen: u64,
+}
+
+/// A contributor moving to a new key: `old_pubkey` signs
+/// `hashing::rotation_message` naming `new_pubkey`. The catalog credits the old
+/// key's attestations to the new one, unless the old key was rotated to more
+/// than one successor, which revokes it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KeyRotation {
+    pub old_pubkey: [u8; 32],
+    pub new_pubkey: [u8; 32],
+    /// Unix milliseconds; the old key's later attestations no longer count.
+    pub rotated_at: u64,
+    /// ed25519 signature by `old_pubkey` (64 bytes).
+    pub signature: Vec<u8>,
+}
+
+/// Parameters for one FullTextShard contract instance, part of key derivation.
+/// Every shard of an index shares `shard_count`; `shard_id` makes each key unique.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ShardParameters {
+    pub shard_id: u8,
+    pub shard_count: u8,
+    pub protocol_version: u16,
+    /// How contributors and searchers turn text into index terms.
+    #[serde(default)]
+    pub analyzer: Analyzer,
+    /// Most postings kept per word; the best-ranked stay. 0 for no cap.
+    #[serde(default = "default_max_postings_per_term")]
+    pub max_postings_per_term: u32,
+    /// Largest serialized state; the worst-ranked postings are evicted past it.
+    /// 0 for no cap.
+    #[serde(default = "default_max_state_bytes")]
+    pub max_state_bytes: u64,
+    /// Minimum proof-of-work difficulty (leading zero bits) of each posting's token.
+    #[serde(default = "default_posting_difficulty")]
+    pub min_pow_difficulty: u8,
+}
+
+/// Default `ShardParameters::max_postings_per_term`.
+pub const DEFAULT_MAX_POSTINGS_PER_TERM: u32 = 1000;
+/// Default `ShardParameters::max_state_bytes`.
+pub const DEFAULT_MAX_SHARD_BYTES: u64 = 8 * 1024 * 1024;
+
+fn default_max_postings_per_term() -> u32 {
+    DEFAULT_MAX_POSTINGS_PER_TERM
+}
+
+fn default_max_state_bytes() -> u64 {
+    DEFAULT_MAX_SHARD_BYTES
+}
+
+fn default_posting_difficulty() -> u8 {
+    crate::antiflood::DEFAULT_POSTING_DIFFICULTY
 }
 
 /// Full state of a FullTextShard contract.
@@ -84,10 +185,27 @@ pub struct ShardState {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TermEntry {
     pub contract_key: String,
-    pub snippet: String,
     pub tf_idf_score: u32,
+    /// Term frequency within the document (x10000), so readers can re-weight
+    /// with current document frequencies. 0 for entries that predate it.
+    #[serde(default)]
+    pub term_frequency: u32,
+    /// Token positions of the term in the snippet, ascending, at most
+    /// `MAX_POSITIONS`. Empty when the contributor didn't record them.
+    #[serde(default)]
+    pub positions: Vec<u32>,
+    /// Proof-of-work over `antiflood::posting_challenge`, kept so any peer
+    /// merging the posting can re-check it.
+    #[serde(default)]
+    pub antiflood_token: AntifloodToken,
 }
 
+/// Maximum token positions stored per posting.
+pub const MAX_POSITIONS: usize = 64;
+
+/// Largest full snippet a catalog variant stores, in bytes (2000 chars).
+pub const MAX_SNIPPET_LEN: usize = 8000;
+
 /// Delta for updating the SearchCatalog.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CatalogDelta {
@@ -95,18 +213,31 @@ pub struct CatalogDelta {
     pub title: String,
     pub description: String,
     pub mini_snippet: String,
+    /// Full snippet; may be left empty when `snippet_hash` is given.
     pub snippet: String,
+    /// `hashing::snippet_hash` of `snippet`, required when `snippet` is empty.
+    #[serde(default)]
+    pub snippet_hash: [u8; 32],
     pub size_bytes: u64,
     pub version: Option<u64>,
     pub metadata_hash: [u8; 32],
     pub attestation: Attestation,
 }
 
-/// Delta for updating a FullTextShard.
+/// Delta the SearchCatalog sends in `get_state_delta`: whole entries the peer
+/// lacks, merged like a full state so attestations arrive exactly as stored.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CatalogSyncDelta {
+    pub entries: BTreeMap<String, CatalogEntry>,
+    /// Key rotations the peer lacks.
+    #[serde(default)]
+    pub rotations: Vec<KeyRotation>,
+}
+
+/// Delta for updating a FullTextShard. Each entry carries its own proof-of-work.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ShardDelta {
     pub entries: Vec<ShardDeltaEntry>,
-    pub antiflood_token: AntifloodToken,
 }
 
 /// A single entry in a shard delta.
@@ -114,6 +245,12 @@ pub struct ShardDelta {
 pub struct ShardDeltaEntry {
     pub word: String,
     pub contract_key: String,
-    pub snippet: String,
     pub tf_idf_score: u32,
+    #[serde(default)]
+    pub term_frequency: u32,
+    #[serde(default)]
+    pub positions: Vec<u32>,
+    /// Proof-of-work over `antiflood::posting_challenge` for this entry.
+    #[serde(default)]
+    pub antiflood_token: AntifloodToken,
 }
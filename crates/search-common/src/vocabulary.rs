@@ -0,0 +1,153 @@
+//! Term dictionary over shard indexes for prefix and typo-tolerant expansion.
+//!
+//! Words are hashed to shards, so a prefix can live in any shard. The
+//! vocabulary borrows every loaded shard's sorted index and range-scans each.
+
+use crate::types::{ShardState, TermEntry};
+use std::collections::BTreeMap;
+
+/// Terms shorter than this (in chars) aren't prefix-expanded.
+pub const MIN_PREFIX_LEN: usize = 3;
+/// Terms shorter than this (in chars) aren't typo-corrected.
+pub const MIN_TYPO_LEN: usize = 4;
+/// Maximum expansions per query term.
+pub const MAX_EXPANSIONS: usize = 8;
+/// Relevance weight (x10000) of a prefix expansion.
+pub const PREFIX_WEIGHT: u32 = 5000;
+/// Relevance weight (x10000) of a typo correction, divided by its edit distance.
+pub const TYPO_WEIGHT: u32 = 4000;
+
+/// A vocabulary term standing in for a query term, with a relevance weight
+/// (x10000) below the 10000 of an exact hit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expansion<'a> {
+    pub term: &'a str,
+    pub weight: u32,
+}
+
+/// Borrowed view of the words in a set of shard indexes.
+pub struct Vocabulary<'a> {
+    indexes: Vec<&'a BTreeMap<String, Vec<TermEntry>>>,
+}
+
+impl<'a> Vocabulary<'a> {
+    pub fn new(shards: impl IntoIterator<Item = &'a ShardState>) -> Self {
+        Vocabulary {
+            indexes: shards.into_iter().map(|s| &s.index).collect(),
+        }
+    }
+
+    pub fn contains(&self, term: &str) -> bool {
+        self.indexes.iter().any(|index| index.contains_key(term))
+    }
+
+    /// Words starting with `prefix` (excluding `prefix` itself), shortest first.
+    pub fn prefix_matches(&self, prefix: &str) -> Vec<&'a str> {
+        let mut matches: Vec<&'a str> = self
+            .indexes
+            .iter()
+            .flat_map(|index| {
+                index
+                    .range::<str, _>((
+                        std::ops::Bound::Excluded(prefix),
+                        std::ops::Bound::Unbounded,
+                    ))
+                    .map(|(word, _)| word.as_str())
+                    .take_while(|word| word.starts_with(prefix))
+            })
+            .collect();
+        matches.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        matches
+    }
+
+    /// Words within `max_distance` edits of `term` (excluding `term` itself),
+    /// closest first.
+    pub fn typo_matches(&self, term: &str, max_distance: u32) -> Vec<(&'a str, u32)> {
+        let mut matches: Vec<(&'a str, u32)> = self
+            .indexes
+            .iter()
+            .flat_map(|index| index.keys())
+            .filter(|word| word.as_str() != term)
+            .filter_map(|word| edit_distance(term, word, max_distance).map(|d| (word.as_str(), d)))
+            .collect();
+        matches.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(b.0)));
+        matches
+    }
+
+    /// Vocabulary terms to search in place of, or alongside, `term`: prefix
+    /// completions always, typo corrections only when `term` itself is unknown.
+    /// Highest weight first, at most `MAX_EXPANSIONS`.
+    pub fn expand(&self, term: &str) -> Vec<Expansion<'a>> {
+        let len = term.chars().count();
+        let mut expansions: Vec<Expansion<'a>> = Vec::new();
+
+        if len >= MIN_PREFIX_LEN {
+            expansions.extend(self.prefix_matches(term).into_iter().map(|t| Expansion {
+                term: t,
+                weight: PREFIX_WEIGHT,
+            }));
+        }
+        if len >= MIN_TYPO_LEN && !self.contains(term) {
+            for (t, distance) in self.typo_matches(term, max_typo_distance(len)) {
+                if !expansions.iter().any(|e| e.term == t) {
+                    expansions.push(Expansion {
+                        term: t,
+                        weight: TYPO_WEIGHT / distance,
+                    });
+                }
+            }
+        }
+
+        // Stable sort keeps shortest-first / closest-first within a weight
+        expansions.sort_by_key(|e| std::cmp::Reverse(e.weight));
+        expansions.truncate(MAX_EXPANSIONS);
+        expansions
+    }
+}
+
+/// Edits tolerated for a term of `len` chars: one for short words, two from eight chars.
+pub fn max_typo_distance(len: usize) -> u32 {
+    if len >= 8 {
+        2
+    } else {
+        1
+    }
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent
+/// transpositions) over chars, or `None` if it exceeds `max`.
+pub fn edit_distance(a: &str, b: &str, max: u32) -> Option<u32> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max as usize {
+        return None;
+    }
+
+    let mut prev2 = vec![0u32; b.len() + 1];
+    let mut prev: Vec<u32> = (0..=b.len() as u32).collect();
+    let mut cur = vec![0u32; b.len() + 1];
+    let mut prev_min = 0u32;
+    for i in 1..=a.len() {
+        cur[0] = i as u32;
+        let mut row_min = cur[0];
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as u32;
+            let mut d = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(prev2[j - 2] + 1);
+            }
+            cur[j] = d;
+            row_min = row_min.min(d);
+        }
+        // Later rows can't drop below this row's minimum, nor (via a
+        // transposition) below the previous row's minimum + 1
+        if row_min > max && prev_min >= max {
+            return None;
+        }
+        prev_min = row_min;
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    let d = prev[b.len()];
+    (d <= max).then_some(d)
+}
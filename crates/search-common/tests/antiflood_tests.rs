@@ -0,0 +1,119 @@
+use search_common::antiflood::*;
+use search_common::types::AntifloodToken;
+
+fn challenge() -> [u8; 32] {
+    attestation_challenge(&[1u8; 32], "contract-a", &[2u8; 32], 1000)
+}
+
+#[test]
+fn mined_token_verifies() {
+    let c = challenge();
+    let token = mine_token(&c, 10);
+    assert_eq!(token.difficulty, 10);
+    assert!(verify_token(&token, &c, 10));
+    assert!(leading_zero_bits(&pow_hash(&c, &token.nonce)) >= 10);
+}
+
+#[test]
+fn min_difficulty_enforced() {
+    let c = challenge();
+    let token = mine_token(&c, 6);
+    assert!(verify_token(&token, &c, 6));
+    assert!(!verify_token(&token, &c, 7));
+}
+
+#[test]
+fn claimed_difficulty_checked() {
+    let c = challenge();
+    let mut token = mine_token(&c, 6);
+    // Claiming more work than was done fails unless the hash happens to qualify
+    let actual = leading_zero_bits(&pow_hash(&c, &token.nonce));
+    token.difficulty = (actual + 1) as u8;
+    assert!(!verify_token(&token, &c, 1));
+}
+
+#[test]
+fn empty_or_oversized_nonce_rejected() {
+    let c = challenge();
+    let empty = AntifloodToken {
+        nonce: vec![],
+        difficulty: 1,
+    };
+    assert!(!verify_token(&empty, &c, 0));
+    let oversized = AntifloodToken {
+        nonce: vec![0u8; MAX_NONCE_LEN + 1],
+        difficulty: 1,
+    };
+    assert!(!verify_token(&oversized, &c, 0));
+}
+
+#[test]
+fn zero_difficulty_rejected() {
+    let token = AntifloodToken {
+        nonce: vec![0u8; 8],
+        difficulty: 0,
+    };
+    assert!(!verify_token(&token, &challenge(), 0));
+}
+
+#[test]
+fn attestation_challenge_binds_every_field() {
+    let base = attestation_challenge(&[1u8; 32], "contract-a", &[2u8; 32], 1000);
+    assert_ne!(
+        base,
+        attestation_challenge(&[9u8; 32], "contract-a", &[2u8; 32], 1000)
+    );
+    assert_ne!(
+        base,
+        attestation_challenge(&[1u8; 32], "contract-b", &[2u8; 32], 1000)
+    );
+    assert_ne!(
+        base,
+        attestation_challenge(&[1u8; 32], "contract-a", &[9u8; 32], 1000)
+    );
+    assert_ne!(
+        base,
+        attestation_challenge(&[1u8; 32], "contract-a", &[2u8; 32], 1001)
+    );
+}
+
+#[test]
+fn leading_zero_bits_counts() {
+    let mut hash = [0u8; 32];
+    assert_eq!(leading_zero_bits(&hash), 256);
+    hash[0] = 0x80;
+    assert_eq!(leading_zero_bits(&hash), 0);
+    hash[0] = 0;
+    hash[1] = 0x01;
+    assert_eq!(leading_zero_bits(&hash), 15);
+}
+
+#[test]
+fn posting_challenge_binds_every_field() {
+    let base = posting_challenge("word", "contract-a", 1000, 500, &[1, 4]);
+    assert_ne!(
+        base,
+        posting_challenge("wore", "contract-a", 1000, 500, &[1, 4])
+    );
+    assert_ne!(
+        base,
+        posting_challenge("word", "contract-b", 1000, 500, &[1, 4])
+    );
+    assert_ne!(
+        base,
+        posting_challenge("word", "contract-a", 1001, 500, &[1, 4])
+    );
+    assert_ne!(
+        base,
+        posting_challenge("word", "contract-a", 1000, 501, &[1, 4])
+    );
+    assert_ne!(
+        base,
+        posting_challenge("word", "contract-a", 1000, 500, &[1, 5])
+    );
+    // Length prefixes keep the word and contract key from sliding into each other
+    assert_ne!(
+        posting_challenge("ab", "c", 1000, 500, &[]),
+        posting_challenge("a", "bc", 1000, 500, &[])
+    );
+}
@@ -1,4 +1,4 @@
-use search_common::bloom::BloomFilter;
+use search_common::bloom::{BloomFilter, MAX_HASHES, SUMMARY_FP_RATE};
 
 #[test]
 fn bloom_insert_contains() {
@@ -76,3 +76,87 @@ fn bloom_empty() {
     assert!(!bf.contains(b"anything"));
     assert!(!bf.contains(b"at all"));
 }
+
+#[test]
+fn bloom_sized_for_capacity() {
+    let bf = BloomFilter::with_capacity(10_000, SUMMARY_FP_RATE, 0);
+    assert_eq!(bf.num_hashes(), 7);
+    assert!(bf.num_bits() >= 10_000 * 10);
+
+    let tiny = BloomFilter::with_capacity(0, SUMMARY_FP_RATE, 0);
+    assert!(tiny.num_bits() > 0);
+}
+
+#[test]
+fn bloom_capacity_false_positive_rate() {
+    let mut bf = BloomFilter::with_capacity(20_000, SUMMARY_FP_RATE, 0);
+    for i in 0..20_000 {
+        bf.insert(format!("inserted-{}", i).as_bytes());
+    }
+    let false_positives = (0..10_000)
+        .filter(|i| bf.contains(format!("not-inserted-{}", i).as_bytes()))
+        .count();
+    // Target is 1%
+    assert!(
+        false_positives < 200,
+        "False positive rate too high: {}/10000",
+        false_positives
+    );
+}
+
+#[test]
+fn bloom_salt_changes_collisions() {
+    let items: Vec<String> = (0..500).map(|i| format!("inserted-{}", i)).collect();
+    let mut a = BloomFilter::with_capacity(items.len(), 500, 1);
+    let mut b = BloomFilter::with_capacity(items.len(), 500, 2);
+    for item in &items {
+        a.insert(item.as_bytes());
+        b.insert(item.as_bytes());
+    }
+    let fp_a: Vec<usize> = (0..2000)
+        .filter(|i| a.contains(format!("probe-{}", i).as_bytes()))
+        .collect();
+    let both = fp_a
+        .iter()
+        .filter(|i| b.contains(format!("probe-{}", i).as_bytes()))
+        .count();
+    assert!(!fp_a.is_empty());
+    assert!(both < fp_a.len() / 2);
+}
+
+#[test]
+fn bloom_sizing_survives_serialization() {
+    let mut bf = BloomFilter::with_capacity(300, 10, 42);
+    bf.insert(b"alpha");
+    let bf2 = BloomFilter::from_bytes(&bf.to_bytes()).expect("deserialization failed");
+    assert_eq!(bf2.num_hashes(), bf.num_hashes());
+    assert_eq!(bf2.num_bits(), bf.num_bits());
+    assert!(bf2.contains(b"alpha"));
+}
+
+#[test]
+fn bloom_malformed_rejected() {
+    #[derive(serde::Serialize)]
+    struct RawFilter {
+        bits: Vec<u8>,
+        num_bits: usize,
+        num_hashes: u32,
+        salt: u64,
+    }
+    let encode = |bits: Vec<u8>, num_bits: usize, num_hashes: u32| {
+        let mut buf = Vec::new();
+        let raw = RawFilter {
+            bits,
+            num_bits,
+            num_hashes,
+            salt: 0,
+        };
+        ciborium::ser::into_writer(&raw, &mut buf).unwrap();
+        buf
+    };
+    assert!(BloomFilter::from_bytes(&encode(vec![0; 8], 64, 7)).is_some());
+    assert!(BloomFilter::from_bytes(&encode(vec![], 0, 7)).is_none());
+    assert!(BloomFilter::from_bytes(&encode(vec![0; 2], 64, 7)).is_none());
+    assert!(BloomFilter::from_bytes(&encode(vec![0; 8], 64, 0)).is_none());
+    assert!(BloomFilter::from_bytes(&encode(vec![0; 8], 64, MAX_HASHES + 1)).is_none());
+}
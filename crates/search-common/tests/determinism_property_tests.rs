@@ -1,6 +1,21 @@
 use proptest::prelude::*;
+use search_common::tokenization::{Analyzer, Language};
 use search_common::{extraction, hashing, normalization};
 
+fn any_language() -> impl Strategy<Value = Language> {
+    prop_oneof![
+        Just(Language::English),
+        Just(Language::German),
+        Just(Language::French),
+        Just(Language::Spanish),
+        Just(Language::Italian),
+        Just(Language::Portuguese),
+        Just(Language::Dutch),
+        Just(Language::Swedish),
+        Just(Language::Russian),
+    ]
+}
+
 proptest! {
     #[test]
     fn normalize_is_idempotent(s in ".*") {
@@ -13,10 +28,11 @@ proptest! {
     fn metadata_hash_is_deterministic(
         title in "[a-zA-Z0-9 ]{0,100}",
         desc in "[a-zA-Z0-9 ]{0,100}",
+        mini in "[a-zA-Z0-9 ]{0,100}",
         snippet in "[a-zA-Z0-9 ]{0,200}"
     ) {
-        let h1 = hashing::metadata_hash(&title, &desc, &snippet);
-        let h2 = hashing::metadata_hash(&title, &desc, &snippet);
+        let h1 = hashing::metadata_hash(&title, &desc, &mini, &snippet);
+        let h2 = hashing::metadata_hash(&title, &desc, &mini, &snippet);
         prop_assert_eq!(h1, h2);
     }
 
@@ -43,6 +59,25 @@ proptest! {
         }
     }
 
+    #[test]
+    fn analyzer_deterministic(s in "\\PC{0,200}", language in any_language(), stemming in any::<bool>()) {
+        let analyzer = Analyzer { language, stemming };
+        prop_assert_eq!(analyzer.tokenize(&s), analyzer.tokenize(&s));
+    }
+
+    #[test]
+    fn analyzer_tokens_reanalyze_from_spans(
+        s in "[a-zA-Z .,北京大学東京タワーภาษาไทยน\u{0E49}ำ한국어]{0,100}|\\PC{0,100}",
+        language in any_language(),
+    ) {
+        // Highlighting maps terms back to snippet text through their spans
+        let analyzer = Analyzer { language, stemming: true };
+        for token in analyzer.tokens(&s) {
+            let source = &s[token.span.clone()];
+            prop_assert_eq!(analyzer.tokenize(source), vec![token.term.clone()]);
+        }
+    }
+
     #[test]
     fn extract_title_deterministic(html in "<title>[a-zA-Z0-9 ]{1,50}</title>") {
         let r1 = extraction::extract_title_from_html(&html);
@@ -2,37 +2,54 @@ use search_common::hashing::*;
 
 #[test]
 fn metadata_hash_deterministic() {
-    let h1 = metadata_hash("title", "desc", "snippet");
-    let h2 = metadata_hash("title", "desc", "snippet");
+    let h1 = metadata_hash("title", "desc", "mini", "snippet");
+    let h2 = metadata_hash("title", "desc", "mini", "snippet");
     assert_eq!(h1, h2);
 }
 
 #[test]
 fn metadata_hash_different_title() {
-    let h1 = metadata_hash("title1", "desc", "snippet");
-    let h2 = metadata_hash("title2", "desc", "snippet");
+    let h1 = metadata_hash("title1", "desc", "mini", "snippet");
+    let h2 = metadata_hash("title2", "desc", "mini", "snippet");
     assert_ne!(h1, h2);
 }
 
 #[test]
 fn metadata_hash_different_description() {
-    let h1 = metadata_hash("title", "desc1", "snippet");
-    let h2 = metadata_hash("title", "desc2", "snippet");
+    let h1 = metadata_hash("title", "desc1", "mini", "snippet");
+    let h2 = metadata_hash("title", "desc2", "mini", "snippet");
     assert_ne!(h1, h2);
 }
 
 #[test]
 fn metadata_hash_different_snippet() {
-    let h1 = metadata_hash("title", "desc", "snippet1");
-    let h2 = metadata_hash("title", "desc", "snippet2");
+    let h1 = metadata_hash("title", "desc", "mini", "snippet1");
+    let h2 = metadata_hash("title", "desc", "mini", "snippet2");
     assert_ne!(h1, h2);
 }
 
+#[test]
+fn metadata_hash_different_mini_snippet() {
+    let h1 = metadata_hash("title", "desc", "mini1", "snippet");
+    let h2 = metadata_hash("title", "desc", "mini2", "snippet");
+    assert_ne!(h1, h2);
+}
+
+#[test]
+fn metadata_hash_is_variant_hash_of_snippet_hash() {
+    // Catalog variants keep only the snippet hash and must still reproduce the key
+    assert_eq!(
+        metadata_hash("title", "desc", "mini", "snippet"),
+        variant_hash("title", "desc", "mini", &snippet_hash("snippet"))
+    );
+    assert_ne!(snippet_hash("snippet"), snippet_hash("snippet2"));
+}
+
 #[test]
 fn metadata_hash_separator_matters() {
     // "a\0b" with desc "c" vs "a" with desc "\0b\0c" should differ
-    let h1 = metadata_hash("a\0b", "c", "d");
-    let h2 = metadata_hash("a", "b\0c", "d");
+    let h1 = metadata_hash("a\0b", "c", "mini", "d");
+    let h2 = metadata_hash("a", "b\0c", "mini", "d");
     assert_ne!(h1, h2);
 }
 
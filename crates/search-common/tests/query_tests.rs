@@ -0,0 +1,371 @@
+use search_common::query::*;
+use search_common::tokenization::Analyzer;
+
+fn phrase(terms: &[&str], slop: u32) -> Phrase {
+    Phrase {
+        terms: terms.iter().map(|s| s.to_string()).collect(),
+        slop,
+    }
+}
+
+fn term(t: &str) -> Query {
+    Query::Term {
+        field: Field::Any,
+        term: t.to_string(),
+    }
+}
+
+fn any_phrase(terms: &[&str], slop: u32) -> Query {
+    Query::Phrase {
+        field: Field::Any,
+        phrase: phrase(terms, slop),
+    }
+}
+
+fn should(clauses: Vec<Query>) -> Query {
+    Query::Group(clauses.into_iter().map(|q| (Occur::Should, q)).collect())
+}
+
+/// A document whose snippet postings hold `terms` at consecutive positions,
+/// each with relevance 1000.
+fn doc_with(title: &str, terms: &[&str]) -> Document {
+    let mut doc = Document::new(title, "", &Analyzer::default());
+    for (i, t) in terms.iter().enumerate() {
+        doc.postings
+            .entry(t.to_string())
+            .or_insert(Posting {
+                relevance: 1000,
+                positions: vec![],
+            })
+            .positions
+            .push(i as u32);
+    }
+    doc
+}
+
+fn matches(raw: &str, doc: &Document) -> bool {
+    evaluate(&parse(raw, &Analyzer::default()).root, doc).is_some()
+}
+
+#[test]
+fn bare_words_are_optional_terms() {
+    let q = parse("chat room", &Analyzer::default());
+    assert_eq!(q.root, should(vec![term("chat"), term("room")]));
+    assert_eq!(q.terms, vec!["chat", "room"]);
+}
+
+#[test]
+fn quoted_phrase_parsed() {
+    let q = parse("freenet \"Chat Room\" app", &Analyzer::default());
+    assert_eq!(
+        q.root,
+        should(vec![
+            term("freenet"),
+            any_phrase(&["chat", "room"], 0),
+            term("app")
+        ])
+    );
+    assert_eq!(q.terms, vec!["freenet", "chat", "room", "app"]);
+}
+
+#[test]
+fn proximity_operator_parsed() {
+    let q = parse("\"chat room\"~3 \"web app\"~", &Analyzer::default());
+    assert_eq!(
+        q.root,
+        should(vec![
+            any_phrase(&["chat", "room"], 3),
+            any_phrase(&["web", "app"], 0)
+        ])
+    );
+}
+
+#[test]
+fn single_word_quote_is_plain_term() {
+    let q = parse("\"chat\"", &Analyzer::default());
+    assert_eq!(q.root, term("chat"));
+}
+
+#[test]
+fn unterminated_quote_runs_to_end() {
+    let q = parse("find \"chat room", &Analyzer::default());
+    assert_eq!(
+        q.root,
+        should(vec![term("find"), any_phrase(&["chat", "room"], 0)])
+    );
+}
+
+#[test]
+fn phrase_terms_deduplicated() {
+    let q = parse("chat \"chat room\" chat", &Analyzer::default());
+    assert_eq!(q.terms, vec!["chat", "room"]);
+}
+
+#[test]
+fn required_and_excluded_parsed() {
+    let q = parse("+wiki -test", &Analyzer::default());
+    assert_eq!(
+        q.root,
+        Query::Group(vec![
+            (Occur::Must, term("wiki")),
+            (Occur::MustNot, term("test"))
+        ])
+    );
+    // Excluded terms are looked up but not highlighted
+    assert_eq!(q.terms, vec!["wiki", "test"]);
+    assert_eq!(q.highlight_terms, vec!["wiki"]);
+}
+
+#[test]
+fn or_groups_and_parentheses_parsed() {
+    let q = parse("+(chat OR forum) -spam", &Analyzer::default());
+    assert_eq!(
+        q.root,
+        Query::Group(vec![
+            (Occur::Must, Query::Or(vec![term("chat"), term("forum")])),
+            (Occur::MustNot, term("spam"))
+        ])
+    );
+}
+
+#[test]
+fn field_prefixes_parsed() {
+    let q = parse(
+        "title:river Description:\"open source\" snippet:(a OR boat)",
+        &Analyzer::default(),
+    );
+    assert_eq!(
+        q.root,
+        should(vec![
+            Query::Term {
+                field: Field::Title,
+                term: "river".to_string()
+            },
+            Query::Phrase {
+                field: Field::Description,
+                phrase: phrase(&["open", "source"], 0)
+            },
+            // "a" is a stop word
+            Query::Term {
+                field: Field::Snippet,
+                term: "boat".to_string()
+            },
+        ])
+    );
+    assert!(q.scans_metadata);
+    assert_eq!(q.terms, vec!["boat"]);
+}
+
+#[test]
+fn unknown_prefix_is_plain_text() {
+    let q = parse("http://example", &Analyzer::default());
+    assert_eq!(q.root, any_phrase(&["http", "example"], 0));
+    assert!(!q.scans_metadata);
+}
+
+#[test]
+fn malformed_input_degrades_gracefully() {
+    assert_eq!(
+        parse("(chat room", &Analyzer::default()).root,
+        should(vec![term("chat"), term("room")])
+    );
+    assert_eq!(
+        parse("chat)) room", &Analyzer::default()).root,
+        should(vec![term("chat"), term("room")])
+    );
+    assert_eq!(parse("OR chat OR", &Analyzer::default()).root, term("chat"));
+    assert_eq!(parse("- + chat", &Analyzer::default()).root, term("chat"));
+    assert!(parse("the () OR", &Analyzer::default())
+        .highlight_terms
+        .is_empty());
+}
+
+#[test]
+fn required_terms_must_match() {
+    let doc = doc_with("", &["wiki", "page"]);
+    assert!(matches("+wiki page", &doc));
+    assert!(!matches("+wiki +forum", &doc));
+    assert!(matches("forum wiki", &doc));
+    assert!(!matches("forum chat", &doc));
+}
+
+#[test]
+fn excluded_terms_filter() {
+    let doc = doc_with("", &["wiki", "test"]);
+    assert!(!matches("+wiki -test", &doc));
+    assert!(matches("+wiki -spam", &doc));
+    // A purely negative query matches nothing
+    assert!(!matches("-spam", &doc));
+}
+
+#[test]
+fn or_alternatives() {
+    let forum = doc_with("", &["forum"]);
+    let chat = doc_with("", &["chat"]);
+    let neither = doc_with("", &["wiki"]);
+    for doc in [&forum, &chat] {
+        assert!(matches("+chat OR +forum", doc));
+    }
+    assert!(!matches("+chat OR +forum", &neither));
+    // OR binds looser than juxtaposition
+    assert!(matches("+wiki +page OR chat", &chat));
+    assert!(!matches("+wiki +page OR chat", &neither));
+}
+
+#[test]
+fn scores_sum_matching_clauses() {
+    let doc = doc_with("", &["chat", "room"]);
+    let one = evaluate(&parse("chat forum", &Analyzer::default()).root, &doc).unwrap();
+    let two = evaluate(&parse("chat room", &Analyzer::default()).root, &doc).unwrap();
+    assert!(two > one);
+}
+
+#[test]
+fn field_scoped_terms_use_metadata() {
+    let doc = doc_with("River Chat", &["forum"]);
+    assert!(matches("title:river", &doc));
+    assert!(!matches("description:river", &doc));
+    // Unscoped terms search the snippet postings only
+    assert!(!matches("river", &doc));
+    assert!(matches("title:\"river chat\"", &doc));
+    assert!(!matches("title:\"chat river\"", &doc));
+    assert!(matches("+title:(lake OR river) +forum", &doc));
+    assert_eq!(
+        evaluate(&parse("title:river", &Analyzer::default()).root, &doc),
+        Some(FIELD_MATCH_SCORE)
+    );
+}
+
+#[test]
+fn phrase_within_boolean_query() {
+    let doc = doc_with("", &["chat", "room", "app"]);
+    assert!(matches("+\"chat room\" -spam", &doc));
+    assert!(!matches("+\"room chat\"", &doc));
+    assert!(matches("\"room chat\"~1", &doc));
+    // Exact phrase doubles the summed term relevance
+    assert_eq!(
+        evaluate(&parse("\"chat room\"", &Analyzer::default()).root, &doc),
+        Some(4000)
+    );
+}
+
+#[test]
+fn exact_phrase_requires_order_and_adjacency() {
+    let chat: &[u32] = &[4, 10];
+    let room: &[u32] = &[11];
+    assert_eq!(phrase_slack(&[chat, room], 0), Some(0));
+    // Reversed order
+    assert_eq!(phrase_slack(&[room, chat], 0), None);
+    // Far apart
+    assert_eq!(phrase_slack(&[&[0], &[9]], 0), None);
+}
+
+#[test]
+fn proximity_allows_gap_and_any_order() {
+    let chat: &[u32] = &[2];
+    let room: &[u32] = &[5];
+    // Window 2..=5 is 4 tokens for 2 terms: slack 2
+    assert_eq!(phrase_slack(&[chat, room], 2), Some(2));
+    assert_eq!(phrase_slack(&[room, chat], 2), Some(2));
+    assert_eq!(phrase_slack(&[chat, room], 1), None);
+}
+
+#[test]
+fn proximity_finds_tightest_window() {
+    let a: &[u32] = &[0, 20, 40];
+    let b: &[u32] = &[15, 41];
+    let c: &[u32] = &[30, 43];
+    // Best window is 40..=43
+    assert_eq!(phrase_slack(&[a, b, c], 5), Some(1));
+}
+
+#[test]
+fn repeated_phrase_term() {
+    let bora: &[u32] = &[7, 8];
+    assert_eq!(phrase_slack(&[bora, bora], 0), Some(0));
+    assert_eq!(phrase_slack(&[&[7, 9], &[7, 9]], 0), None);
+}
+
+#[test]
+fn repeated_term_needs_distinct_positions() {
+    // "foo foo"~2 against one "foo" must not reuse the position
+    let foo: &[u32] = &[3];
+    assert_eq!(phrase_slack(&[foo, foo], 2), None);
+    let foos: &[u32] = &[3, 5];
+    assert_eq!(phrase_slack(&[foos, foos], 2), Some(1));
+    // Overlapping lists of distinct terms don't underflow
+    assert_eq!(phrase_slack(&[&[3], &[3, 5]], 2), Some(0));
+}
+
+#[test]
+fn boost_favours_adjacency() {
+    assert_eq!(proximity_boost(0), 20000);
+    assert!(proximity_boost(1) < proximity_boost(0));
+    assert!(proximity_boost(5) > 10000);
+}
+
+#[test]
+fn phrase_boost_filters_and_boosts() {
+    let p = phrase(&["chat", "room"], 0);
+    let adjacent = |t: &str| match t {
+        "chat" => Some(&[3u32][..]),
+        "room" => Some(&[4u32][..]),
+        _ => None,
+    };
+    assert_eq!(phrase_boost(&p, adjacent), Some(20000));
+
+    let far_apart = |t: &str| match t {
+        "chat" => Some(&[3u32][..]),
+        "room" => Some(&[40u32][..]),
+        _ => None,
+    };
+    assert_eq!(phrase_boost(&p, far_apart), None);
+
+    let missing = |t: &str| match t {
+        "chat" => Some(&[3u32][..]),
+        _ => None,
+    };
+    assert_eq!(phrase_boost(&p, missing), None);
+}
+
+#[test]
+fn postings_without_positions_pass_unboosted() {
+    let p = phrase(&["chat", "room"], 0);
+    let legacy = |_: &str| Some(&[][..]);
+    assert_eq!(phrase_boost(&p, legacy), Some(10000));
+}
+
+#[test]
+fn stemming_analyzer_matches_inflected_title() {
+    let analyzer = Analyzer {
+        language: search_common::tokenization::Language::English,
+        stemming: true,
+    };
+    let q = parse("title:messages", &analyzer);
+    let doc = Document::new("Encrypted messaging", "", &analyzer);
+    assert_eq!(evaluate(&q.root, &doc), Some(FIELD_MATCH_SCORE));
+    let plain = Document::new("Encrypted messaging", "", &Analyzer::default());
+    assert_eq!(
+        evaluate(&parse("title:messages", &Analyzer::default()).root, &plain),
+        None
+    );
+}
+
+#[test]
+fn cjk_query_is_bigram_phrase() {
+    let q = parse("北京大学", &Analyzer::default());
+    assert_eq!(
+        q.root,
+        Query::Phrase {
+            field: Field::Any,
+            phrase: Phrase {
+                terms: vec!["北京".into(), "京大".into(), "大学".into()],
+                slop: 0,
+            },
+        }
+    );
+    let doc = Document::new("我在北京大学", "", &Analyzer::default());
+    assert!(evaluate(&parse("title:北京大学", &Analyzer::default()).root, &doc).is_some());
+    assert!(evaluate(&parse("title:北京大", &Analyzer::default()).root, &doc).is_some());
+    assert!(evaluate(&parse("title:京北", &Analyzer::default()).root, &doc).is_none());
+}
@@ -62,3 +62,62 @@ fn combined_score_overflow_safety() {
     // Just verify no panic
     assert!(score > 0);
 }
+
+#[test]
+fn term_counts_fold_duplicates() {
+    let tokens: Vec<String> = ["rust", "wasm", "rust", "rust"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let counts = term_counts(&tokens);
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts["rust"], 3);
+    assert_eq!(counts["wasm"], 1);
+}
+
+#[test]
+fn tfidf_composes_from_parts() {
+    for (count, total, docs, df) in [(5, 100, 10, 2), (1, 1, 1, 1), (3, 2000, 500, 7)] {
+        assert_eq!(
+            integer_tf_idf(count, total, docs, df),
+            tf_idf(
+                term_frequency(count, total),
+                inverse_document_frequency(docs, df)
+            )
+        );
+    }
+}
+
+#[test]
+fn main_topic_outranks_passing_mention() {
+    // Same corpus stats; one document repeats the term, the other mentions it once
+    let topic = integer_tf_idf(20, 300, 100, 5);
+    let mention = integer_tf_idf(1, 300, 100, 5);
+    assert!(topic > mention);
+}
+
+#[test]
+fn rare_terms_weigh_more() {
+    let rare = integer_tf_idf(1, 10, 100_000, 1);
+    let common = integer_tf_idf(1, 10, 100_000, 100_000);
+    assert!(rare > common);
+}
+
+#[test]
+fn idf_clamps_lagging_corpus_size() {
+    // Catalog hasn't caught up with the shard: treat as every doc having the term
+    assert_eq!(inverse_document_frequency(3, 10), 10000);
+    assert_eq!(inverse_document_frequency(10, 10), 10000);
+    assert!(inverse_document_frequency(10_000, 10) > 10000);
+}
+
+#[test]
+fn reweighting_is_independent_of_submission_time() {
+    // Two documents with equal term frequency, scored at different corpus sizes,
+    // compare equal once re-weighted with the same current idf
+    let early = term_frequency(4, 200);
+    let late = term_frequency(8, 400);
+    let idf = inverse_document_frequency(50_000, 12);
+    assert_eq!(tf_idf(early, idf), tf_idf(late, idf));
+    assert!(tf_idf(term_frequency(9, 400), idf) > tf_idf(early, idf));
+}
@@ -0,0 +1,188 @@
+use search_common::sync::*;
+
+fn item(key: &str, content: &str) -> SyncItem {
+    SyncItem {
+        key: key.as_bytes().to_vec(),
+        content: content.as_bytes().to_vec(),
+    }
+}
+
+fn items(range: std::ops::Range<u32>) -> Vec<SyncItem> {
+    range.map(|i| item(&format!("k{}", i), "v")).collect()
+}
+
+fn missing_keys(summary: &[u8], local: &[SyncItem]) -> Vec<Vec<u8>> {
+    missing_items(summary, local)
+        .expect("summary rejected")
+        .into_iter()
+        .map(|i| local[i].key.clone())
+        .collect()
+}
+
+fn keys(range: std::ops::Range<u32>) -> Vec<Vec<u8>> {
+    range.map(|i| format!("k{}", i).into_bytes()).collect()
+}
+
+#[test]
+fn iblt_of_equal_sets_sends_nothing() {
+    let local = items(0..1000);
+    let summary = summarize(SummaryKind::Iblt, &local);
+    assert!(missing_items(&summary, &local).unwrap().is_empty());
+}
+
+#[test]
+fn iblt_finds_exactly_the_missing_items() {
+    let peer = items(0..10_000);
+    let local = items(0..10_050);
+    let summary = summarize(SummaryKind::Iblt, &peer);
+    assert_eq!(missing_keys(&summary, &local), keys(10_000..10_050));
+}
+
+#[test]
+fn iblt_ignores_items_only_the_peer_holds() {
+    let mut peer = items(0..5_000);
+    peer.extend((0..40).map(|i| item(&format!("p{}", i), "v")));
+    let mut local = items(0..5_000);
+    local.extend((0..40).map(|i| item(&format!("l{}", i), "v")));
+    let summary = summarize(SummaryKind::Iblt, &peer);
+    let expected: Vec<Vec<u8>> = (0..40).map(|i| format!("l{}", i).into_bytes()).collect();
+    assert_eq!(missing_keys(&summary, &local), expected);
+}
+
+#[test]
+fn iblt_overflow_never_misses_an_item() {
+    // Far more new items than the peer's table can decode
+    let peer = items(0..100);
+    let local = items(0..2_000);
+    let summary = summarize(SummaryKind::Iblt, &peer);
+    let missing = missing_keys(&summary, &local);
+    for key in keys(100..2_000) {
+        assert!(missing.contains(&key));
+    }
+}
+
+#[test]
+fn larger_peer_table_answered_from_local_items() {
+    let peer = items(0..100_000);
+    let mut local = items(0..10);
+    local.push(item("extra", "v"));
+    let summary = summarize(SummaryKind::Iblt, &peer);
+    assert!(missing_keys(&summary, &local).contains(&b"extra".to_vec()));
+}
+
+#[test]
+fn iblt_detects_changed_content() {
+    let peer = vec![item("a", "old"), item("b", "same")];
+    let local = vec![item("a", "new"), item("b", "same")];
+    let summary = summarize(SummaryKind::Iblt, &peer);
+    assert_eq!(missing_keys(&summary, &local), vec![b"a".to_vec()]);
+}
+
+#[test]
+fn bloom_summary_still_answered() {
+    let peer = items(0..100);
+    let local = items(0..110);
+    let summary = summarize(SummaryKind::Bloom, &peer);
+    let missing = missing_keys(&summary, &local);
+    assert!(missing.iter().all(|k| !peer.iter().any(|p| &p.key == k)));
+    assert!(!missing.is_empty());
+}
+
+#[test]
+fn unknown_version_sends_everything() {
+    #[derive(serde::Serialize)]
+    struct Envelope {
+        version: u8,
+        body: Vec<u8>,
+    }
+    let mut summary = Vec::new();
+    ciborium::ser::into_writer(
+        &Envelope {
+            version: 99,
+            body: vec![],
+        },
+        &mut summary,
+    )
+    .unwrap();
+    let local = items(0..5);
+    assert_eq!(missing_items(&summary, &local), Some(vec![0, 1, 2, 3, 4]));
+}
+
+#[test]
+fn unversioned_summary_sends_everything() {
+    // Peers predating the envelope summarized with a bare bloom filter
+    let peer = items(0..3);
+    let mut bloom = search_common::bloom::BloomFilter::with_capacity(
+        peer.len(),
+        search_common::bloom::SUMMARY_FP_RATE,
+        0,
+    );
+    for item in &peer {
+        bloom.insert(&item.key);
+    }
+    let local = items(0..5);
+    assert_eq!(
+        missing_items(&bloom.to_bytes(), &local),
+        Some(vec![0, 1, 2, 3, 4])
+    );
+}
+
+#[test]
+fn malformed_summary_rejected() {
+    let local = items(0..5);
+    assert_eq!(missing_items(b"not cbor", &local), None);
+    assert_eq!(missing_items(&[], &local), None);
+
+    #[derive(serde::Serialize)]
+    struct Iblt {
+        counts: Vec<i32>,
+        digests: Vec<u64>,
+        checks: Vec<u64>,
+    }
+    #[derive(serde::Serialize)]
+    struct Envelope {
+        version: u8,
+        body: Iblt,
+    }
+    let tables = [
+        // No cells
+        Iblt {
+            counts: vec![],
+            digests: vec![],
+            checks: vec![],
+        },
+        // Columns of different lengths
+        Iblt {
+            counts: vec![0; 3],
+            digests: vec![0; 3],
+            checks: vec![0; 6],
+        },
+        // Cells not split evenly between the hashes
+        Iblt {
+            counts: vec![0; 4],
+            digests: vec![0; 4],
+            checks: vec![0; 4],
+        },
+    ];
+    for body in tables {
+        let mut summary = Vec::new();
+        let envelope = Envelope {
+            version: IBLT_VERSION,
+            body,
+        };
+        ciborium::ser::into_writer(&envelope, &mut summary).unwrap();
+        assert_eq!(missing_items(&summary, &local), None);
+    }
+}
+#[test]
+fn summary_carries_version() {
+    #[derive(serde::Deserialize)]
+    struct Envelope {
+        version: u8,
+    }
+    for kind in [SummaryKind::Bloom, SummaryKind::Iblt] {
+        let summary = summarize(kind, &items(0..3));
+        let envelope: Envelope = ciborium::de::from_reader(summary.as_slice()).unwrap();
+        assert_eq!(envelope.version, kind.version());
+    }
+}
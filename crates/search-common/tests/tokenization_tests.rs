@@ -58,3 +58,117 @@ fn is_stop_word_true() {
 fn is_stop_word_false() {
     assert!(!is_stop_word("hello"));
 }
+
+#[test]
+fn term_positions_index_token_stream() {
+    let tokens = tokenize("chat room, the best chat");
+    let positions = term_positions(&tokens);
+    assert_eq!(positions["chat"], vec![0, 3]);
+    assert_eq!(positions["room"], vec![1]);
+    assert_eq!(positions["best"], vec![2]);
+}
+
+#[test]
+fn term_positions_capped() {
+    let tokens = vec!["echo".to_string(); 500];
+    let positions = term_positions(&tokens);
+    assert_eq!(positions["echo"].len(), search_common::types::MAX_POSITIONS);
+}
+
+fn stemmed(language: Language) -> Analyzer {
+    Analyzer {
+        language,
+        stemming: true,
+    }
+}
+
+#[test]
+fn default_analyzer_matches_tokenize() {
+    let text = "The Messages, r\u{00E9}sum\u{00E9} and chat rooms";
+    assert_eq!(Analyzer::default().tokenize(text), tokenize(text));
+    assert_eq!(tokenize(text), vec!["messages", "resume", "chat", "rooms"]);
+}
+
+#[test]
+fn english_stemming_conflates_inflections() {
+    let analyzer = stemmed(Language::English);
+    let tokens = analyzer.tokenize("message messages messaging");
+    assert_eq!(tokens, vec!["messag", "messag", "messag"]);
+    assert_eq!(analyzer.analyze_word("Running"), Some("run".to_string()));
+}
+
+#[test]
+fn german_stemming_and_stop_words() {
+    let analyzer = stemmed(Language::German);
+    // "und", "die" and "für" are German stop words, not English ones
+    assert_eq!(
+        analyzer.tokenize("Häuser und die Häusern für"),
+        analyzer.tokenize("Haus Hauses")
+    );
+    assert!(analyzer.analyze_word("für").is_none());
+}
+
+#[test]
+fn french_stemming_strips_accents_after_stemming() {
+    let analyzer = stemmed(Language::French);
+    let tokens = analyzer.tokenize("les éditions éditées");
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0], tokens[1]);
+    assert!(tokens[0].is_ascii());
+}
+
+#[test]
+fn stop_words_are_per_language() {
+    let english = Analyzer::default();
+    let spanish = Analyzer {
+        language: Language::Spanish,
+        stemming: false,
+    };
+    assert_eq!(english.tokenize("el the"), vec!["el"]);
+    assert_eq!(spanish.tokenize("el the"), vec!["the"]);
+}
+
+#[test]
+fn language_from_code() {
+    assert_eq!(Language::from_code("en"), Some(Language::English));
+    assert_eq!(Language::from_code("DE"), Some(Language::German));
+    assert_eq!(Language::from_code("xx"), None);
+}
+
+#[test]
+fn cjk_text_becomes_overlapping_bigrams() {
+    assert_eq!(tokenize("北京大学"), vec!["北京", "京大", "大学"]);
+    // Punctuation ends a run; a lone character is a unigram
+    assert_eq!(tokenize("你好，世界。猫"), vec!["你好", "世界", "猫"]);
+}
+
+#[test]
+fn japanese_kana_and_kanji_bigrams() {
+    assert_eq!(tokenize("東京タワー"), vec!["東京", "京タ", "タワ", "ワー"]);
+}
+
+#[test]
+fn mixed_script_words_split_by_script() {
+    assert_eq!(
+        tokenize("Rust编程 version2"),
+        vec!["rust", "编程", "version2"]
+    );
+}
+
+#[test]
+fn thai_bigrams_keep_marks_with_their_consonant() {
+    assert_eq!(
+        tokenize("ภาษาไทย"),
+        vec!["ภา", "าษ", "ษา", "าไ", "ไท", "ทย"]
+    );
+    // The tone mark U+0E49 isn't alphanumeric but mustn't split "น้ำ" (water)
+    assert_eq!(tokenize("น\u{0E49}ำ"), vec!["น\u{0E49}ำ"]);
+}
+
+#[test]
+fn token_spans_cover_source_text() {
+    let text = "Chat 北京大学!";
+    let tokens = Analyzer::default().tokens(text);
+    let spans: Vec<&str> = tokens.iter().map(|t| &text[t.span.clone()]).collect();
+    assert_eq!(spans, vec!["Chat", "北京", "京大", "大学"]);
+}
@@ -0,0 +1,86 @@
+use search_common::trust::*;
+use std::collections::BTreeMap;
+
+fn link(n: u8, weight: u32, depth: u8) -> TrustLink {
+    TrustLink {
+        pubkey: [n; 32],
+        weight,
+        depth,
+    }
+}
+
+#[test]
+fn direct_links_keep_their_weight() {
+    let weights = trust_weights(&[link(1, 80, 0), link(2, 30, 0)], &BTreeMap::new());
+    assert_eq!(weights.len(), 2);
+    assert_eq!(weights[&[1; 32]], 80);
+    assert_eq!(weights[&[2; 32]], 30);
+}
+
+#[test]
+fn endorsements_ignored_at_depth_zero() {
+    let endorsements = BTreeMap::from([([1; 32], vec![link(2, 100, 0)])]);
+    let weights = trust_weights(&[link(1, 80, 0)], &endorsements);
+    assert!(!weights.contains_key(&[2; 32]));
+}
+
+#[test]
+fn transitive_weight_scales_per_hop() {
+    let endorsements = BTreeMap::from([
+        ([1; 32], vec![link(2, 50, 3)]),
+        ([2; 32], vec![link(3, 50, 3)]),
+        ([3; 32], vec![link(4, 50, 3)]),
+    ]);
+    let weights = trust_weights(&[link(1, 80, 2)], &endorsements);
+    assert_eq!(weights[&[2; 32]], 40);
+    assert_eq!(weights[&[3; 32]], 20);
+    // Two hops from key 1 is as far as its link allows
+    assert!(!weights.contains_key(&[4; 32]));
+}
+
+#[test]
+fn endorsement_depth_limits_further_hops() {
+    let endorsements = BTreeMap::from([
+        ([1; 32], vec![link(2, 100, 0)]),
+        ([2; 32], vec![link(3, 100, 0)]),
+    ]);
+    let weights = trust_weights(&[link(1, 100, 3)], &endorsements);
+    assert_eq!(weights[&[2; 32]], 100);
+    assert!(!weights.contains_key(&[3; 32]));
+}
+
+#[test]
+fn strongest_path_wins() {
+    let endorsements = BTreeMap::from([
+        ([1; 32], vec![link(3, 10, 0)]),
+        ([2; 32], vec![link(3, 90, 0)]),
+    ]);
+    let weights = trust_weights(&[link(1, 100, 1), link(2, 50, 1)], &endorsements);
+    assert_eq!(weights[&[3; 32]], 45);
+
+    // Trusting the key directly beats any path to it
+    let weights = trust_weights(
+        &[link(1, 100, 1), link(2, 50, 1), link(3, 60, 0)],
+        &endorsements,
+    );
+    assert_eq!(weights[&[3; 32]], 60);
+}
+
+#[test]
+fn cycles_terminate() {
+    let endorsements = BTreeMap::from([
+        ([1; 32], vec![link(2, 100, 3)]),
+        ([2; 32], vec![link(1, 100, 3)]),
+    ]);
+    let weights = trust_weights(&[link(1, 70, 3)], &endorsements);
+    assert_eq!(weights[&[1; 32]], 70);
+    assert_eq!(weights[&[2; 32]], 70);
+}
+
+#[test]
+fn out_of_range_values_clamped() {
+    let endorsements = BTreeMap::from([([1; 32], vec![link(2, 500, 0)])]);
+    let weights = trust_weights(&[link(1, 1000, 200)], &endorsements);
+    assert_eq!(weights[&[1; 32]], MAX_TRUST_WEIGHT);
+    assert_eq!(weights[&[2; 32]], MAX_TRUST_WEIGHT);
+}
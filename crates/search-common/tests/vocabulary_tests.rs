@@ -0,0 +1,94 @@
+use search_common::types::{ShardState, TermEntry};
+use search_common::vocabulary::*;
+use std::collections::BTreeMap;
+
+fn shard(shard_id: u8, words: &[&str]) -> ShardState {
+    let index: BTreeMap<String, Vec<TermEntry>> =
+        words.iter().map(|w| (w.to_string(), Vec::new())).collect();
+    ShardState { shard_id, index }
+}
+
+fn shards() -> Vec<ShardState> {
+    vec![
+        shard(0, &["freenet", "forum", "messenger"]),
+        shard(1, &["freenetproject", "free", "message"]),
+        shard(2, &["river", "rivers", "freedom"]),
+    ]
+}
+
+#[test]
+fn prefix_spans_shards() {
+    let shards = shards();
+    let vocab = Vocabulary::new(&shards);
+    assert_eq!(
+        vocab.prefix_matches("freen"),
+        vec!["freenet", "freenetproject"]
+    );
+    // The term itself isn't its own completion
+    assert_eq!(
+        vocab.prefix_matches("free"),
+        vec!["freedom", "freenet", "freenetproject"]
+    );
+    assert!(vocab.prefix_matches("zzz").is_empty());
+}
+
+#[test]
+fn edit_distance_bounded() {
+    assert_eq!(edit_distance("messanger", "messenger", 2), Some(1));
+    assert_eq!(edit_distance("river", "river", 1), Some(0));
+    assert_eq!(edit_distance("forum", "fourm", 1), Some(1)); // transposition
+    assert_eq!(edit_distance("river", "rover", 1), Some(1));
+    assert_eq!(edit_distance("river", "raven", 1), None);
+    assert_eq!(edit_distance("river", "riverbank", 2), None);
+    assert_eq!(edit_distance("caf\u{e9}", "cafe", 1), Some(1));
+}
+
+#[test]
+fn typo_corrected_when_unknown() {
+    let shards = shards();
+    let vocab = Vocabulary::new(&shards);
+    let expanded = vocab.expand("messanger");
+    assert_eq!(expanded[0].term, "messenger");
+    assert!(expanded[0].weight < 10000);
+}
+
+#[test]
+fn known_terms_not_typo_corrected() {
+    let shards = shards();
+    let vocab = Vocabulary::new(&shards);
+    // "rivers" is a prefix completion, but "river" is a real word so no typo fixes
+    let expanded = vocab.expand("river");
+    assert_eq!(
+        expanded,
+        vec![Expansion {
+            term: "rivers",
+            weight: PREFIX_WEIGHT
+        }]
+    );
+}
+
+#[test]
+fn short_terms_not_expanded() {
+    let shards = shards();
+    let vocab = Vocabulary::new(&shards);
+    assert!(vocab.expand("fr").is_empty());
+    // Three chars: prefix completion but no typo correction
+    assert!(vocab
+        .expand("fre")
+        .iter()
+        .all(|e| e.weight == PREFIX_WEIGHT));
+}
+
+#[test]
+fn expansions_weighted_below_exact_and_capped() {
+    let words: Vec<String> = (0..20).map(|i| format!("chat{}", i)).collect();
+    let refs: Vec<&str> = words.iter().map(|s| s.as_str()).collect();
+    let shards = vec![shard(0, &refs), shard(1, &["chart"])];
+    let vocab = Vocabulary::new(&shards);
+    let expanded = vocab.expand("chatt");
+    assert!(expanded.len() <= MAX_EXPANSIONS);
+    assert!(expanded.iter().all(|e| e.weight < 10000));
+    // Prefix completions outrank typo corrections
+    let expanded = vocab.expand("cha");
+    assert!(expanded.windows(2).all(|w| w[0].weight >= w[1].weight));
+}
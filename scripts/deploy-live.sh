@@ -81,6 +81,8 @@ echo ""
 echo "[2/7] Building contract WASMs..."
 cargo build --release -p contract-catalog --target wasm32-unknown-unknown
 cargo build --release -p contract-fulltext-shard --target wasm32-unknown-unknown
+cargo build --release -p contract-trust-list --target wasm32-unknown-unknown
+cargo build --release -p delegate-identity --target wasm32-unknown-unknown
 
 CATALOG_CODE="target/wasm32-unknown-unknown/release/contract_catalog.wasm"
 SHARD_CODE="target/wasm32-unknown-unknown/release/contract_fulltext_shard.wasm"
@@ -102,7 +104,8 @@ if [ -n "$SAVED_KEYS" ];     then mv "$SAVED_KEYS" "$WEBAPP_KEYS"; fi
 if [ -n "$SAVED_MANIFEST" ]; then mv "$SAVED_MANIFEST" "$MANIFEST"; fi
 if [ -n "$SAVED_NONCE" ];    then mv "$SAVED_NONCE" "$VANITY_NONCE"; fi
 
-cargo run -p deploy-helper -- "$DEPLOY_DIR" 2>/dev/null
+cargo run -p deploy-helper -- "$DEPLOY_DIR" "${SHARD_COUNT:-16}" ${INDEX_LANGUAGE:+"$INDEX_LANGUAGE"} 2>/dev/null
+SHARD_COUNT=$(cat "$DEPLOY_DIR/shard-count.txt")
 
 # --- Phase 4: Compute contract IDs, detect changes, query node ---
 echo ""
@@ -112,10 +115,10 @@ CATALOG_ID=$(fdev get-contract-id --code "$CATALOG_CODE" --parameters "$DEPLOY_D
 echo "  Catalog: $CATALOG_ID"
 
 SHARD_IDS=()
-for i in $(seq 0 15); do
+for i in $(seq 0 $((SHARD_COUNT - 1))); do
     SHARD_IDS+=($(fdev get-contract-id --code "$SHARD_CODE" --parameters "$DEPLOY_DIR/shard-${i}-params.cbor"))
 done
-echo "  Shards:  16 IDs computed"
+echo "  Shards:  $SHARD_COUNT IDs computed"
 
 # Webapp signing keys + parameters
 mkdir -p "$WEBAPP_DIR"
@@ -154,7 +157,7 @@ cur_shard_wasm_sha=$(sha256_file "$SHARD_CODE")
 cur_webapp_wasm_sha=$(sha256_file "$WEB_CONTAINER_WASM")
 cur_webapp_params_sha=$(sha256_file "$WEBAPP_DIR/webapp.parameters")
 declare -A cur_shard_params_sha
-for i in $(seq 0 15); do
+for i in $(seq 0 $((SHARD_COUNT - 1))); do
     cur_shard_params_sha[$i]=$(sha256_file "$DEPLOY_DIR/shard-${i}-params.cbor")
 done
 
@@ -166,14 +169,14 @@ declare -A shard_changed
 
 if $FORCE; then
     catalog_changed=true; shard_wasm_changed=true; webapp_changed=true
-    for i in $(seq 0 15); do shard_changed[$i]=true; done
+    for i in $(seq 0 $((SHARD_COUNT - 1))); do shard_changed[$i]=true; done
 else
     [ "$cur_catalog_wasm_sha" != "$(manifest_get catalog_wasm_sha256)" ] || \
     [ "$cur_catalog_params_sha" != "$(manifest_get catalog_params_sha256)" ] && catalog_changed=true || true
 
     [ "$cur_shard_wasm_sha" != "$(manifest_get shard_wasm_sha256)" ] && shard_wasm_changed=true || true
 
-    for i in $(seq 0 15); do
+    for i in $(seq 0 $((SHARD_COUNT - 1))); do
         if $shard_wasm_changed || [ "${cur_shard_params_sha[$i]}" != "$(manifest_get "shard_${i}_params_sha256")" ]; then
             shard_changed[$i]=true
         else
@@ -212,7 +215,7 @@ else
 fi
 
 shards_publish=0; shards_skip=0
-for i in $(seq 0 15); do
+for i in $(seq 0 $((SHARD_COUNT - 1))); do
     if [ "${shard_changed[$i]}" = "true" ]; then
         TO_PUBLISH+=("shard:$i"); shards_publish=$((shards_publish + 1))
     elif [ "${ON_NODE[${SHARD_IDS[$i]}]}" = "true" ]; then
@@ -258,6 +261,9 @@ if $WEBAPP_NEEDS_PUBLISH; then
     if [ -z "$DX_OUTPUT" ]; then echo "ERROR: dx build output not found"; exit 1; fi
 
     # Package + sign
+    # The UI registers the identity delegate and publishes trust lists from these copies
+    cp target/wasm32-unknown-unknown/release/delegate_identity.wasm "$DX_OUTPUT/"
+    cp target/wasm32-unknown-unknown/release/contract_trust_list.wasm "$DX_OUTPUT/"
     (cd "$DX_OUTPUT" && tar -cJf "$WEBAPP_DIR/webapp.tar.xz" *)
     version=$(( $(date +%s) / 60 ))
     "$WEB_CONTAINER_TOOL" sign \
@@ -369,7 +375,7 @@ if ! $DRY_RUN; then
 catalog_wasm_sha256=$cur_catalog_wasm_sha
 catalog_params_sha256=$cur_catalog_params_sha
 shard_wasm_sha256=$cur_shard_wasm_sha
-$(for i in $(seq 0 15); do echo "shard_${i}_params_sha256=${cur_shard_params_sha[$i]}"; done)
+$(for i in $(seq 0 $((SHARD_COUNT - 1))); do echo "shard_${i}_params_sha256=${cur_shard_params_sha[$i]}"; done)
 webapp_wasm_sha256=$cur_webapp_wasm_sha
 webapp_params_sha256=$cur_webapp_params_sha
 dx_output_sha256=${cur_dx_output_sha:-}
@@ -386,6 +392,6 @@ echo "Search engine: http://127.0.0.1:$NODE_PORT/v1/contract/web/$WEBAPP_ID/"
 echo ""
 echo "Contract IDs:"
 echo "  Catalog: $CATALOG_ID"
-for i in $(seq 0 15); do echo "  Shard $i: ${SHARD_IDS[$i]}"; done
+for i in $(seq 0 $((SHARD_COUNT - 1))); do echo "  Shard $i: ${SHARD_IDS[$i]}"; done
 echo "  Webapp:  $WEBAPP_ID"
 echo "=========================================="
@@ -10,6 +10,8 @@ set -euo pipefail
 #
 # Usage:
 #   ./scripts/deploy-local.sh
+#   SHARD_COUNT=32 ./scripts/deploy-local.sh   # override shard count (default 16)
+#   INDEX_LANGUAGE=de ./scripts/deploy-local.sh  # index language for stop words and stemming (default en)
 
 PROJECT_ROOT="$(cd "$(dirname "$0")/.." && pwd)"
 WEB_CONTAINER_TOOL="$PROJECT_ROOT/target/release/web-container-tool"
@@ -51,6 +53,8 @@ echo ""
 echo "[1/8] Building contract WASMs..."
 cargo build --release -p contract-catalog --target wasm32-unknown-unknown
 cargo build --release -p contract-fulltext-shard --target wasm32-unknown-unknown
+cargo build --release -p contract-trust-list --target wasm32-unknown-unknown
+cargo build --release -p delegate-identity --target wasm32-unknown-unknown
 echo "  catalog:  $(du -h target/wasm32-unknown-unknown/release/contract_catalog.wasm | cut -f1)"
 echo "  shard:    $(du -h target/wasm32-unknown-unknown/release/contract_fulltext_shard.wasm | cut -f1)"
 
@@ -58,7 +62,8 @@ echo "  shard:    $(du -h target/wasm32-unknown-unknown/release/contract_fulltex
 echo ""
 echo "[2/8] Generating initial CBOR state/parameter files..."
 mkdir -p "$DEPLOY_DIR"
-cargo run -p deploy-helper -- "$DEPLOY_DIR" 2>/dev/null
+cargo run -p deploy-helper -- "$DEPLOY_DIR" "${SHARD_COUNT:-16}" ${INDEX_LANGUAGE:+"$INDEX_LANGUAGE"} 2>/dev/null
+SHARD_COUNT=$(cat "$DEPLOY_DIR/shard-count.txt")
 
 # --- Step 3: Deploy catalog contract ---
 echo ""
@@ -74,11 +79,11 @@ fdev publish \
     contract \
     --state "$DEPLOY_DIR/catalog-state.cbor" || echo "  (may already exist)"
 
-# --- Step 4: Deploy 16 shard contracts ---
+# --- Step 4: Deploy shard contracts ---
 echo ""
-echo "[4/8] Deploying 16 shard contracts..."
+echo "[4/8] Deploying $SHARD_COUNT shard contracts..."
 SHARD_IDS=()
-for i in $(seq 0 15); do
+for i in $(seq 0 $((SHARD_COUNT - 1))); do
     SHARD_ID=$(fdev get-contract-id \
         --code target/wasm32-unknown-unknown/release/contract_fulltext_shard.wasm \
         --parameters "$DEPLOY_DIR/shard-${i}-params.cbor")
@@ -99,16 +104,16 @@ echo "[5/8] Writing contract IDs to $DEPLOY_DIR/contract-ids.json..."
     echo "{"
     echo "  \"catalog\": \"$CATALOG_ID\","
     echo "  \"shards\": ["
-    for i in $(seq 0 15); do
+    for i in $(seq 0 $((SHARD_COUNT - 1))); do
         comma=","
-        [ "$i" -eq 15 ] && comma=""
+        [ "$i" -eq $((SHARD_COUNT - 1)) ] && comma=""
         echo "    \"${SHARD_IDS[$i]}\"$comma"
     done
     echo "  ]"
     echo "}"
 } > "$DEPLOY_DIR/contract-ids.json"
 echo "  Contract IDs saved."
-echo "  NOTE: Update ui/src/api/contracts.rs with these IDs for production use."
+echo "  deploy-helper wrote the UI's copy to ui/src/api/contract_ids.rs."
 
 # --- Step 6: Build UI ---
 echo ""
@@ -141,6 +146,9 @@ if [ -z "$DX_OUTPUT" ]; then
 fi
 
 echo "  dx output: $DX_OUTPUT"
+# The UI registers the identity delegate and publishes trust lists from these copies
+cp target/wasm32-unknown-unknown/release/delegate_identity.wasm "$DX_OUTPUT/"
+cp target/wasm32-unknown-unknown/release/contract_trust_list.wasm "$DX_OUTPUT/"
 (cd "$DX_OUTPUT" && tar -cJf "$WEBAPP_DIR/webapp.tar.xz" *)
 
 # Generate webapp signing keys if they don't exist
@@ -185,7 +193,7 @@ echo "Webapp URL: http://127.0.0.1:7509/v1/contract/web/$WEBAPP_ID/"
 echo ""
 echo "Contract IDs:"
 echo "  Catalog:  $CATALOG_ID"
-for i in $(seq 0 15); do
+for i in $(seq 0 $((SHARD_COUNT - 1))); do
     echo "  Shard $i: ${SHARD_IDS[$i]}"
 done
 echo "  Webapp:   $WEBAPP_ID"
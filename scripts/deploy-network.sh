@@ -57,12 +57,15 @@ echo ""
 echo "[1/10] Building contract WASMs..."
 cargo build --release -p contract-catalog --target wasm32-unknown-unknown
 cargo build --release -p contract-fulltext-shard --target wasm32-unknown-unknown
+cargo build --release -p contract-trust-list --target wasm32-unknown-unknown
+cargo build --release -p delegate-identity --target wasm32-unknown-unknown
 
 # --- Step 2: Generate initial states ---
 echo ""
 echo "[2/10] Generating CBOR state/parameter files..."
 mkdir -p "$DEPLOY_DIR"
-cargo run -p deploy-helper -- "$DEPLOY_DIR" 2>/dev/null
+cargo run -p deploy-helper -- "$DEPLOY_DIR" "${SHARD_COUNT:-16}" ${INDEX_LANGUAGE:+"$INDEX_LANGUAGE"} 2>/dev/null
+SHARD_COUNT=$(cat "$DEPLOY_DIR/shard-count.txt")
 
 # --- Step 3: Deploy catalog to gateway ---
 echo ""
@@ -74,18 +77,18 @@ fdev network -p "$GW_PORT" publish \
     contract \
     --state "$DEPLOY_DIR/catalog-state.cbor" 2>&1 | grep -E "Publishing|published|updated" || true
 
-# --- Step 4: Deploy 16 shards to gateway ---
+# --- Step 4: Deploy shards to gateway ---
 echo ""
-echo "[4/10] Deploying 16 shard contracts to gateway..."
+echo "[4/10] Deploying $SHARD_COUNT shard contracts to gateway..."
 SHARD_WASM="target/wasm32-unknown-unknown/release/contract_fulltext_shard.wasm"
-for i in $(seq 0 15); do
+for i in $(seq 0 $((SHARD_COUNT - 1))); do
     fdev network -p "$GW_PORT" publish \
         --code "$SHARD_WASM" \
         --parameters "$DEPLOY_DIR/shard-${i}-params.cbor" \
         contract \
         --state "$DEPLOY_DIR/shard-${i}-state.cbor" 2>&1 | grep -E "Publishing|published|updated" || true
 done
-echo "  All 16 shards deployed."
+echo "  All $SHARD_COUNT shards deployed."
 
 # --- Step 5: Compute webapp ID (before building UI, so we can set base_path) ---
 echo ""
@@ -140,6 +143,9 @@ if [ ! -d "$DX_OUTPUT" ]; then
     exit 1
 fi
 
+# The UI registers the identity delegate and publishes trust lists from these copies
+cp target/wasm32-unknown-unknown/release/delegate_identity.wasm "$DX_OUTPUT/"
+cp target/wasm32-unknown-unknown/release/contract_trust_list.wasm "$DX_OUTPUT/"
 (cd "$DX_OUTPUT" && tar -cJf "$WEBAPP_DIR/webapp.tar.xz" *)
 
 seconds=$(date +%s)
@@ -60,6 +60,8 @@ if [ "$SKIP_BUILD" = false ]; then
     sed -i "/\[web\.app\]/a base_path = \"v1/contract/web/$WEBAPP_ID\"" "$DIOXUS_TOML"
 
     (cd ui && dx build --release 2>&1)
+    cargo build --release -p delegate-identity --target wasm32-unknown-unknown
+    cargo build --release -p contract-trust-list --target wasm32-unknown-unknown
 
     sed -i '/^base_path/d' "$DIOXUS_TOML"
     echo "  Done."
@@ -77,6 +79,9 @@ if [ ! -d "$DX_OUTPUT" ]; then
     exit 1
 fi
 
+# The UI registers the identity delegate and publishes trust lists from these copies
+cp "$PROJECT_ROOT/target/wasm32-unknown-unknown/release/delegate_identity.wasm" "$DX_OUTPUT/"
+cp "$PROJECT_ROOT/target/wasm32-unknown-unknown/release/contract_trust_list.wasm" "$DX_OUTPUT/"
 (cd "$DX_OUTPUT" && tar -cJf "$WEBAPP_DIR/webapp.tar.xz" *)
 
 version=$(( $(date +%s) / 60 ))
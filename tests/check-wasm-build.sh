@@ -4,4 +4,6 @@ echo "Checking WASM build targets..."
 cargo check -p freenet-search-engine --target wasm32-unknown-unknown
 cargo check -p contract-catalog --target wasm32-unknown-unknown
 cargo check -p contract-fulltext-shard --target wasm32-unknown-unknown
+cargo check -p contract-trust-list --target wasm32-unknown-unknown
+cargo check -p delegate-identity --target wasm32-unknown-unknown
 echo "All WASM targets OK"
@@ -1,4 +1,9 @@
-use search_common::types::{CatalogParameters, CatalogState, ShardState};
+use search_common::antiflood;
+use search_common::tokenization::{Analyzer, Language};
+use search_common::types::{
+    CatalogParameters, CatalogState, ShardParameters, ShardState, DEFAULT_MAX_POSTINGS_PER_TERM,
+    DEFAULT_MAX_SHARD_BYTES,
+};
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
@@ -7,6 +12,19 @@ use std::process::Command;
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let output_dir = args.get(1).map(|s| s.as_str()).unwrap_or("target/deploy");
+    let shard_count: u8 = args
+        .get(2)
+        .map(|s| s.parse().expect("shard count must be 1-255"))
+        .unwrap_or(16);
+    assert!(shard_count > 0, "shard count must be 1-255");
+    // Index language as an ISO 639-1 code; every index gets stop words and stemming
+    let analyzer = Analyzer {
+        language: args
+            .get(3)
+            .map(|s| Language::from_code(s).expect("unsupported index language"))
+            .unwrap_or_default(),
+        stemming: true,
+    };
 
     let out = Path::new(output_dir);
     fs::create_dir_all(out).expect("failed to create output dir");
@@ -14,9 +32,14 @@ fn main() {
     // Generate catalog parameters
     let catalog_params = CatalogParameters {
         protocol_version: 1,
-        shard_count: 16,
+        shard_count,
         confirmation_weight_threshold: 3,
         entry_ttl_days: 90,
+        min_pow_difficulty: 16,
+        min_identity_age_days: 7,
+        max_attestations_per_window: 120,
+        attestation_window_mins: 60,
+        max_clock_skew_mins: 24 * 60,
     };
     write_cbor(out.join("catalog-params.cbor"), &catalog_params);
     println!("wrote catalog-params.cbor");
@@ -25,13 +48,27 @@ fn main() {
     let catalog_state = CatalogState {
         entries: BTreeMap::new(),
         contributors: BTreeMap::new(),
+        rotations: Vec::new(),
     };
     write_cbor(out.join("catalog-state.cbor"), &catalog_state);
     println!("wrote catalog-state.cbor");
 
-    // Generate 16 shard states and parameters (empty, with shard_id)
+    fs::write(out.join("shard-count.txt"), shard_count.to_string())
+        .expect("failed to write shard count");
+    println!("wrote shard-count.txt ({})", shard_count);
+
+    // Generate shard states and parameters (empty, with shard_id)
     // Each shard needs distinct parameters so fdev derives a unique contract key.
-    for shard_id in 0u8..16 {
+    let index_params = ShardParameters {
+        shard_id: 0,
+        shard_count,
+        protocol_version: 1,
+        analyzer,
+        max_postings_per_term: DEFAULT_MAX_POSTINGS_PER_TERM,
+        max_state_bytes: DEFAULT_MAX_SHARD_BYTES,
+        min_pow_difficulty: antiflood::DEFAULT_POSTING_DIFFICULTY,
+    };
+    for shard_id in 0..shard_count {
         let shard_state = ShardState {
             shard_id,
             index: BTreeMap::new(),
@@ -39,9 +76,13 @@ fn main() {
         let state_file = format!("shard-{}-state.cbor", shard_id);
         write_cbor(out.join(&state_file), &shard_state);
 
-        // Parameters: CBOR-encoded shard_id (unique per shard → unique contract key)
+        // Parameters: shard_id is unique per shard → unique contract key
+        let shard_params = ShardParameters {
+            shard_id,
+            ..index_params.clone()
+        };
         let params_file = format!("shard-{}-params.cbor", shard_id);
-        write_cbor(out.join(&params_file), &shard_id);
+        write_cbor(out.join(&params_file), &shard_params);
 
         println!("wrote {} + {}", state_file, params_file);
     }
@@ -60,37 +101,112 @@ fn main() {
         .expect("failed to write webapp secret");
     println!("wrote webapp-secret.bin (keep secret!)");
 
-    // Generate Rust source snippet with contract IDs for the UI
-    generate_contract_ids_snippet(out);
+    // Generate the UI's contract IDs module
+    generate_contract_ids(out, &catalog_params, &index_params);
 
     println!("\nAll deploy artifacts written to {}", output_dir);
 }
 
-fn generate_contract_ids_snippet(out: &Path) {
+/// The UI's view of the deployed contracts, written where the UI builds it from.
+const UI_CONTRACT_IDS: &str = "ui/src/api/contract_ids.rs";
+
+/// Write the UI's contract IDs module. The shard IDs, their count and the
+/// `SHARD_PARAMETERS` the UI analyzes text with all come from `index_params`, and
+/// `CATALOG_PARAMETERS` from `catalog_params`, so they always match the contracts
+/// the IDs name. Trust lists are per curator, so
+/// only their code hash is emitted; the UI derives each list's ID from it and the
+/// curator's key.
+fn generate_contract_ids(
+    out: &Path,
+    catalog_params: &CatalogParameters,
+    index_params: &ShardParameters,
+) {
+    let shard_count = index_params.shard_count;
     let catalog_wasm = "target/wasm32-unknown-unknown/release/contract_catalog.wasm";
     let shard_wasm = "target/wasm32-unknown-unknown/release/contract_fulltext_shard.wasm";
+    let trust_list_wasm = "target/wasm32-unknown-unknown/release/contract_trust_list.wasm";
 
     let catalog_id = fdev_get_contract_id(catalog_wasm, &out.join("catalog-params.cbor"));
 
-    let mut snippet = String::new();
-    snippet.push_str("// Auto-generated by deploy-helper. Do not edit.\n");
-    snippet.push_str("// Regenerate with: cargo run -p deploy-helper\n\n");
-    snippet.push_str(&format!(
-        "const CATALOG_INSTANCE_ID: [u8; 32] = {:?};\n\n",
+    let mut module = String::new();
+    module.push_str("//! Contract IDs and parameters of the deployed search index.\n");
+    module.push_str("//!\n");
+    module.push_str("//! Auto-generated by deploy-helper. Do not edit.\n");
+    module.push_str("//! Regenerate with: cargo run -p deploy-helper -- <output dir> [shard count] [language]\n\n");
+    module.push_str("use search_common::tokenization::{Analyzer, Language};\n");
+    module.push_str("use search_common::types::{CatalogParameters, ShardParameters};\n\n");
+    module.push_str(&format!(
+        "pub const CATALOG_INSTANCE_ID: [u8; 32] = {:?};\n\n",
         catalog_id
     ));
 
-    snippet.push_str("const SHARD_INSTANCE_IDS: [[u8; 32]; 16] = [\n");
-    for shard_id in 0u8..16 {
+    module.push_str("/// Number of shards in the deployed index.\n");
+    module.push_str(&format!("pub const SHARD_COUNT: u8 = {};\n\n", shard_count));
+    module.push_str("pub const SHARD_INSTANCE_IDS: [[u8; 32]; SHARD_COUNT as usize] = [\n");
+    for shard_id in 0..shard_count {
         let params_path = out.join(format!("shard-{}-params.cbor", shard_id));
         let shard_instance_id = fdev_get_contract_id(shard_wasm, &params_path);
-        snippet.push_str(&format!("    {:?},\n", shard_instance_id));
+        module.push_str(&format!("    {:?},\n", shard_instance_id));
     }
-    snippet.push_str("];\n");
+    module.push_str("];\n\n");
+    module.push_str(&format!(
+        "pub const TRUST_LIST_CODE_HASH: [u8; 32] = {:?};\n\n",
+        code_hash(trust_list_wasm)
+    ));
+    module.push_str(&format!(
+        "/// Parameters every shard of the deployed index shares; each shard's own\n\
+         /// differ only in `shard_id`.\n\
+         pub const SHARD_PARAMETERS: ShardParameters = ShardParameters {{\n    \
+         shard_id: 0,\n    \
+         shard_count: SHARD_COUNT,\n    \
+         protocol_version: {},\n    \
+         analyzer: Analyzer {{\n        \
+         language: Language::{:?},\n        \
+         stemming: {},\n    \
+         }},\n    \
+         max_postings_per_term: {},\n    \
+         max_state_bytes: {},\n    \
+         min_pow_difficulty: {},\n\
+         }};\n",
+        index_params.protocol_version,
+        index_params.analyzer.language,
+        index_params.analyzer.stemming,
+        index_params.max_postings_per_term,
+        index_params.max_state_bytes,
+        index_params.min_pow_difficulty
+    ));
+    module.push_str(&format!(
+        "\n/// Parameters of the deployed catalog.\n\
+         pub const CATALOG_PARAMETERS: CatalogParameters = CatalogParameters {{\n    \
+         protocol_version: {},\n    \
+         shard_count: SHARD_COUNT,\n    \
+         confirmation_weight_threshold: {},\n    \
+         entry_ttl_days: {},\n    \
+         min_pow_difficulty: {},\n    \
+         min_identity_age_days: {},\n    \
+         max_attestations_per_window: {},\n    \
+         attestation_window_mins: {},\n    \
+         max_clock_skew_mins: {},\n\
+         }};\n",
+        catalog_params.protocol_version,
+        catalog_params.confirmation_weight_threshold,
+        catalog_params.entry_ttl_days,
+        catalog_params.min_pow_difficulty,
+        catalog_params.min_identity_age_days,
+        catalog_params.max_attestations_per_window,
+        catalog_params.attestation_window_mins,
+        catalog_params.max_clock_skew_mins
+    ));
+
+    fs::write(UI_CONTRACT_IDS, &module).expect("failed to write UI contract IDs");
+    println!("wrote {}", UI_CONTRACT_IDS);
+}
 
-    let snippet_path = out.join("contract_ids.rs");
-    fs::write(&snippet_path, &snippet).unwrap();
-    println!("wrote contract_ids.rs (copy into ui/src/api/contracts.rs)");
+/// The code hash a node assigns to a contract: BLAKE3 of its WASM.
+fn code_hash(code_path: &str) -> [u8; 32] {
+    let code =
+        fs::read(code_path).unwrap_or_else(|e| panic!("failed to read {}: {}", code_path, e));
+    blake3::hash(&code).into()
 }
 
 /// Call `fdev get-contract-id` and decode the base58 output to [u8; 32].
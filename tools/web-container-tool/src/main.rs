@@ -1,8 +1,10 @@
 use clap::{Parser, Subcommand};
+use delegate_identity::backup::{self, IdentityBackup};
 use ed25519_dalek::{Signature, Signer, SigningKey};
+use search_common::trust::{TrustLink, TrustListState, MAX_TRUST_DEPTH, MAX_TRUST_WEIGHT};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::Write;
+use std::io::{BufRead, Write};
 use std::path::PathBuf;
 
 /// Metadata stored in web container state, signed by the publisher.
@@ -46,6 +48,45 @@ enum Commands {
         #[arg(long, short)]
         key_file: Option<String>,
     },
+    /// Sign a trust list for the trust-list contract
+    SignTrustList {
+        /// Input file with one endorsement per line: <pubkey hex> <weight> [depth]
+        #[arg(long, short)]
+        input: String,
+        /// Output file for the CBOR contract state
+        #[arg(long, short)]
+        output: String,
+        /// Output file for contract parameters (32-byte verifying key)
+        #[arg(long)]
+        parameters: String,
+        /// Version number (must be higher than previously published)
+        #[arg(long, short)]
+        version: u64,
+        /// Key file to use (default: ~/.config/freenet-search-engine/web-container-keys.toml)
+        #[arg(long, short)]
+        key_file: Option<String>,
+    },
+    /// Decrypt an exported contributor identity into a key file
+    DecryptIdentity {
+        /// Backup exported from the Settings panel (fse-identity:...)
+        #[arg(long, short)]
+        input: String,
+        /// Output key file, in the same format `generate` writes
+        #[arg(long, short)]
+        output: String,
+    },
+    /// Encrypt a key file as a contributor identity backup the Settings panel can import
+    EncryptIdentity {
+        /// Identity name recorded in the backup
+        #[arg(long, short)]
+        name: String,
+        /// Output file for the backup text
+        #[arg(long, short)]
+        output: String,
+        /// Key file to use (default: ~/.config/freenet-search-engine/web-container-keys.toml)
+        #[arg(long, short)]
+        key_file: Option<String>,
+    },
 }
 
 fn default_keys_path() -> PathBuf {
@@ -130,6 +171,133 @@ fn sign_webapp(
     Ok(())
 }
 
+/// Parse one `<pubkey hex> <weight> [depth]` line; blank lines and `#` comments yield `None`.
+fn parse_trust_line(line: &str) -> Result<Option<TrustLink>, Box<dyn std::error::Error>> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (hex, weight, depth) = match fields.as_slice() {
+        [hex, weight] => (*hex, weight.parse::<u32>()?, 0),
+        [hex, weight, depth] => (*hex, weight.parse::<u32>()?, depth.parse::<u8>()?),
+        _ => return Err(format!("Expected <pubkey hex> <weight> [depth]: {}", line).into()),
+    };
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(format!("Public key must be 64 hex characters: {}", hex).into());
+    }
+    let mut pubkey = [0u8; 32];
+    for (i, byte) in pubkey.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    if !(1..=MAX_TRUST_WEIGHT).contains(&weight) || depth > MAX_TRUST_DEPTH {
+        return Err(format!(
+            "Weight must be 1-{} and depth at most {}: {}",
+            MAX_TRUST_WEIGHT, MAX_TRUST_DEPTH, line
+        )
+        .into());
+    }
+    Ok(Some(TrustLink {
+        pubkey,
+        weight,
+        depth,
+    }))
+}
+
+fn sign_trust_list(
+    input: String,
+    output: String,
+    parameters: String,
+    version: u64,
+    key_file: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signing_key = read_signing_key(key_file.as_deref())?;
+    let owner = signing_key.verifying_key().to_bytes();
+
+    let mut endorsements = Vec::new();
+    for line in fs::read_to_string(&input)?.lines() {
+        endorsements.extend(parse_trust_line(line)?);
+    }
+    // The contract requires endorsements sorted by key; a later line for a key wins
+    endorsements.reverse();
+    endorsements.sort_by_key(|link| link.pubkey);
+    endorsements.dedup_by_key(|link| link.pubkey);
+
+    let message = search_common::hashing::trust_list_message(&owner, version, &endorsements);
+    let state = TrustListState {
+        owner,
+        version,
+        signature: signing_key.sign(&message).to_bytes().to_vec(),
+        endorsements,
+    };
+    let mut state_bytes = Vec::new();
+    ciborium::ser::into_writer(&state, &mut state_bytes)?;
+    fs::write(&output, &state_bytes)?;
+    println!(
+        "Trust list with {} endorsements written to: {} ({} bytes)",
+        state.endorsements.len(),
+        output,
+        state_bytes.len()
+    );
+
+    fs::write(&parameters, owner)?;
+    println!("Parameters written to: {} (32 bytes)", parameters);
+    let owner_hex: String = owner.iter().map(|b| format!("{:02x}", b)).collect();
+    println!("Followers add this curator key: {}", owner_hex);
+
+    Ok(())
+}
+
+/// Prompt on stderr and read one line from stdin.
+fn read_passphrase(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    eprint!("{}", prompt);
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn decrypt_identity(input: String, output: String) -> Result<(), Box<dyn std::error::Error>> {
+    let backup =
+        IdentityBackup::from_text(&fs::read_to_string(&input)?).map_err(|e| e.to_string())?;
+    let passphrase = read_passphrase("Backup passphrase: ")?;
+    let secret_key = backup::open(&backup, &passphrase).map_err(|e| e.to_string())?;
+
+    let sk_str = bs58::encode(secret_key).into_string();
+    let vk_str = bs58::encode(backup.public_key).into_string();
+    let name = backup.name.clone();
+    let config = toml::toml! {
+        [identity]
+        name = name
+
+        [keys]
+        signing_key = sk_str
+        verifying_key = vk_str
+    };
+
+    fs::write(&output, toml::to_string(&config)?)?;
+    println!("Identity {:?} written to: {}", backup.name, output);
+    Ok(())
+}
+
+fn encrypt_identity(
+    name: String,
+    output: String,
+    key_file: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signing_key = read_signing_key(key_file.as_deref())?;
+    let passphrase = read_passphrase("New backup passphrase: ")?;
+    if read_passphrase("Repeat passphrase: ")? != passphrase {
+        return Err("Passphrases do not match".into());
+    }
+    let backup =
+        backup::export(&name, &signing_key.to_bytes(), &passphrase).map_err(|e| e.to_string())?;
+
+    fs::write(&output, backup.to_text() + "\n")?;
+    println!("Backup of {:?} written to: {}", name, output);
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
     match cli.command {
@@ -141,5 +309,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             version,
             key_file,
         } => sign_webapp(input, output, parameters, version, key_file),
+        Commands::SignTrustList {
+            input,
+            output,
+            parameters,
+            version,
+            key_file,
+        } => sign_trust_list(input, output, parameters, version, key_file),
+        Commands::DecryptIdentity { input, output } => decrypt_identity(input, output),
+        Commands::EncryptIdentity {
+            name,
+            output,
+            key_file,
+        } => encrypt_identity(name, output, key_file),
     }
 }
@@ -0,0 +1,61 @@
+//! Contract IDs and parameters of the deployed search index.
+//!
+//! Auto-generated by deploy-helper. Do not edit.
+//! Regenerate with: cargo run -p deploy-helper -- <output dir> [shard count] [language]
+
+use search_common::tokenization::{Analyzer, Language};
+use search_common::types::{CatalogParameters, ShardParameters};
+
+pub const CATALOG_INSTANCE_ID: [u8; 32] = [143, 10, 66, 224, 203, 215, 29, 116, 59, 1, 152, 71, 32, 123, 31, 64, 166, 76, 65, 168, 14, 79, 65, 37, 196, 24, 16, 19, 3, 230, 21, 215];
+
+/// Number of shards in the deployed index.
+pub const SHARD_COUNT: u8 = 16;
+
+pub const SHARD_INSTANCE_IDS: [[u8; 32]; SHARD_COUNT as usize] = [
+    [202, 83, 23, 225, 169, 154, 126, 1, 105, 27, 73, 247, 84, 127, 175, 27, 41, 141, 171, 243, 251, 27, 192, 2, 148, 98, 49, 107, 49, 9, 60, 104],
+    [196, 22, 95, 58, 248, 71, 117, 199, 32, 116, 204, 136, 2, 13, 139, 50, 113, 178, 50, 19, 140, 32, 221, 32, 218, 234, 201, 5, 200, 16, 19, 5],
+    [231, 179, 235, 172, 217, 247, 183, 4, 132, 215, 177, 84, 159, 210, 145, 240, 128, 149, 118, 179, 121, 255, 75, 149, 34, 185, 11, 109, 225, 14, 150, 183],
+    [167, 52, 50, 2, 125, 241, 76, 59, 36, 191, 99, 112, 116, 221, 188, 181, 127, 251, 57, 169, 66, 251, 126, 158, 36, 185, 27, 216, 153, 15, 78, 187],
+    [255, 101, 114, 192, 132, 64, 134, 16, 204, 10, 46, 204, 169, 203, 201, 156, 94, 239, 118, 230, 102, 197, 227, 85, 204, 209, 128, 23, 178, 182, 166, 237],
+    [165, 163, 71, 53, 27, 226, 165, 113, 9, 194, 214, 229, 253, 149, 116, 60, 75, 181, 231, 57, 7, 8, 205, 236, 39, 69, 137, 101, 69, 63, 194, 148],
+    [128, 115, 1, 185, 254, 155, 88, 60, 11, 85, 168, 25, 153, 50, 194, 85, 130, 129, 95, 206, 103, 157, 132, 175, 129, 90, 144, 0, 173, 182, 50, 150],
+    [33, 93, 134, 25, 136, 158, 212, 179, 50, 14, 225, 192, 40, 176, 240, 77, 216, 18, 45, 214, 126, 241, 2, 87, 127, 213, 6, 56, 7, 108, 86, 75],
+    [232, 67, 20, 26, 139, 72, 208, 129, 51, 129, 222, 60, 218, 204, 42, 232, 16, 251, 32, 167, 110, 79, 251, 12, 247, 72, 204, 172, 182, 218, 115, 74],
+    [125, 222, 85, 67, 154, 78, 169, 227, 87, 231, 83, 174, 11, 156, 21, 179, 64, 31, 71, 82, 2, 64, 207, 68, 129, 237, 12, 206, 245, 230, 173, 78],
+    [16, 146, 209, 112, 20, 133, 128, 111, 38, 62, 214, 144, 159, 216, 23, 63, 105, 113, 42, 115, 203, 251, 175, 162, 190, 52, 14, 250, 50, 113, 230, 102],
+    [111, 37, 110, 179, 225, 243, 243, 104, 37, 98, 176, 8, 101, 124, 217, 135, 92, 92, 36, 32, 104, 150, 215, 79, 113, 168, 232, 76, 166, 48, 29, 25],
+    [249, 134, 245, 56, 72, 164, 125, 199, 83, 120, 196, 220, 240, 124, 224, 222, 66, 93, 199, 123, 102, 27, 205, 48, 216, 230, 236, 189, 185, 23, 214, 118],
+    [225, 135, 35, 173, 236, 9, 147, 52, 251, 223, 214, 10, 25, 43, 244, 68, 97, 211, 210, 154, 78, 151, 220, 95, 240, 117, 20, 117, 116, 104, 121, 198],
+    [159, 22, 86, 202, 209, 134, 237, 182, 96, 216, 216, 140, 14, 35, 164, 83, 38, 116, 137, 230, 245, 232, 170, 177, 49, 43, 200, 0, 157, 188, 152, 7],
+    [193, 124, 4, 70, 235, 141, 97, 24, 126, 53, 198, 97, 85, 14, 30, 1, 186, 23, 252, 141, 28, 237, 211, 81, 86, 68, 75, 238, 67, 211, 229, 122],
+];
+
+pub const TRUST_LIST_CODE_HASH: [u8; 32] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// Parameters every shard of the deployed index shares; each shard's own
+/// differ only in `shard_id`.
+pub const SHARD_PARAMETERS: ShardParameters = ShardParameters {
+    shard_id: 0,
+    shard_count: SHARD_COUNT,
+    protocol_version: 1,
+    analyzer: Analyzer {
+        language: Language::English,
+        stemming: true,
+    },
+    max_postings_per_term: 1000,
+    max_state_bytes: 8388608,
+    min_pow_difficulty: 12,
+};
+
+/// Parameters of the deployed catalog.
+pub const CATALOG_PARAMETERS: CatalogParameters = CatalogParameters {
+    protocol_version: 1,
+    shard_count: SHARD_COUNT,
+    confirmation_weight_threshold: 3,
+    entry_ttl_days: 90,
+    min_pow_difficulty: 16,
+    min_identity_age_days: 7,
+    max_attestations_per_window: 120,
+    attestation_window_mins: 60,
+    max_clock_skew_mins: 1440,
+};
@@ -1,21 +1,30 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use dioxus::prelude::*;
 use freenet_stdlib::client_api::{ClientRequest, ContractRequest};
 use freenet_stdlib::prelude::{CodeHash, ContractKey, StateDelta, UpdateData};
-use sha2::{Digest, Sha256};
 
+use delegate_identity::IdentityRequest;
+use search_common::antiflood;
 use search_common::hashing::shard_for_word;
-use search_common::tokenization::tokenize;
+use search_common::scoring::{integer_tf_idf, term_counts, term_frequency};
+use search_common::tokenization::term_positions;
 use search_common::types::{
     AntifloodToken, Attestation, CatalogDelta, ShardDelta, ShardDeltaEntry,
 };
 
 use crate::discovery::pipeline::extract_metadata;
 use crate::state::{
-    ContractType, ContributionRecord, ContributionStatus, CATALOG_STATE, CONTRACT_TYPES,
-    CONTRIBUTION_ENABLED, CONTRIBUTION_HISTORY, CONTRIBUTOR_PUBKEY,
+    ContractType, ContributionRecord, ContributionStatus, ACTIVE_IDENTITY, CATALOG_STATE,
+    CONTRACT_TYPES, CONTRIBUTION_ENABLED, CONTRIBUTION_HISTORY, CONTRIBUTOR_PUBKEY, SHARD_STATES,
 };
 
-use super::contracts::{catalog_contract_key, shard_contract_key};
+use super::contracts::{
+    catalog_contract_key, shard_contract_key, ANALYZER, CATALOG_POW_DIFFICULTY, SHARD_COUNT,
+    SHARD_POW_DIFFICULTY,
+};
+use super::identity::send_identity_request;
 use super::node_api::{send_request, with_current_ws};
 
 /// Build a placeholder ContractKey from an instance ID (code hash zeroed).
@@ -25,8 +34,10 @@ fn placeholder_contract_key(
     ContractKey::from_id_and_code(instance_id, CodeHash::new([0u8; 32]))
 }
 
-const POW_DIFFICULTY: u8 = 16;
-const SHARD_COUNT: u8 = 16;
+// Catalog deltas waiting for the identity delegate's signature, by contract key.
+thread_local! {
+    static PENDING_SIGNATURES: RefCell<HashMap<String, CatalogDelta>> = RefCell::new(HashMap::new());
+}
 
 /// Re-trigger contribution for already-discovered WebApp contracts.
 /// Called when the contribution toggle is turned ON in settings.
@@ -95,36 +106,99 @@ pub fn contribute_entry(contract_key: String, state_bytes: Vec<u8>) {
     let title = metadata.title.unwrap_or_default();
     let description = metadata.description.unwrap_or_default();
 
-    // Generate antiflood PoW token
-    let antiflood_token = generate_antiflood_token(POW_DIFFICULTY);
+    // The identity delegate holds the key; contributions wait until it's known
+    let Some(public_key) = *CONTRIBUTOR_PUBKEY.read() else {
+        tracing::debug!("No contributor identity yet, skipping {}", contract_key);
+        return;
+    };
 
-    // Get or create contributor keypair
-    let (secret_key, public_key) = get_or_create_keypair();
+    let now = js_sys::Date::now() as u64;
+    // Mined in a task that yields to the browser, so the page stays responsive
+    wasm_bindgen_futures::spawn_local(async move {
+        // Generate antiflood PoW token bound to this attestation's payload
+        let antiflood_token = generate_antiflood_token(
+            &antiflood::attestation_challenge(
+                &public_key,
+                &contract_key,
+                &metadata.metadata_hash,
+                now,
+            ),
+            CATALOG_POW_DIFFICULTY,
+        )
+        .await;
+
+        // Ask the delegate to sign contract key, metadata hash, timestamp and PoW nonce
+        let sign_request = IdentityRequest::SignAttestation {
+            name: ACTIVE_IDENTITY.read().clone(),
+            contract_key: contract_key.clone(),
+            metadata_hash: metadata.metadata_hash,
+            token_created_at: now,
+            nonce: antiflood_token.nonce.clone(),
+        };
 
-    // Sign the metadata hash
-    let signature = sign_attestation(&secret_key, &metadata.metadata_hash);
+        let attestation = Attestation {
+            contributor_pubkey: public_key,
+            antiflood_token,
+            token_created_at: now,
+            weight: 1,
+            signature: Vec::new(),
+        };
 
-    let now = js_sys::Date::now() as u64;
+        // Build the CatalogDelta; it is submitted once the signature arrives
+        let catalog_delta = CatalogDelta {
+            contract_key: contract_key.clone(),
+            title: title.clone(),
+            description: description.clone(),
+            mini_snippet: metadata.mini_snippet.clone(),
+            snippet: metadata.snippet.clone(),
+            snippet_hash: search_common::hashing::snippet_hash(&metadata.snippet),
+            size_bytes: state_bytes.len() as u64,
+            version: search_common::extraction::extract_version_from_state(&state_bytes),
+            metadata_hash: metadata.metadata_hash,
+            attestation,
+        };
 
-    let attestation = Attestation {
-        contributor_pubkey: public_key,
-        antiflood_token: antiflood_token.clone(),
-        token_created_at: now,
-        weight: 1,
+        PENDING_SIGNATURES.with(|pending| {
+            pending
+                .borrow_mut()
+                .insert(contract_key.clone(), catalog_delta)
+        });
+        if !send_identity_request(&sign_request) {
+            PENDING_SIGNATURES.with(|pending| pending.borrow_mut().remove(&contract_key));
+            record_contribution(
+                &contract_key,
+                now,
+                ContributionStatus::Failed("Identity delegate unavailable".to_string()),
+            );
+        }
+    });
+}
+
+/// Drop a pending contribution the identity delegate refused to sign.
+pub fn fail_contribution(contract_key: &str, message: String) {
+    let Some(catalog_delta) =
+        PENDING_SIGNATURES.with(|pending| pending.borrow_mut().remove(contract_key))
+    else {
+        return;
     };
+    record_contribution(
+        contract_key,
+        catalog_delta.attestation.token_created_at,
+        ContributionStatus::Failed(message),
+    );
+}
 
-    // Build and submit CatalogDelta
-    let catalog_delta = CatalogDelta {
-        contract_key: contract_key.clone(),
-        title: title.clone(),
-        description: description.clone(),
-        mini_snippet: metadata.mini_snippet.clone(),
-        snippet: metadata.snippet.clone(),
-        size_bytes: state_bytes.len() as u64,
-        version: search_common::extraction::extract_version_from_state(&state_bytes),
-        metadata_hash: metadata.metadata_hash,
-        attestation,
+/// Submit a pending contribution once the identity delegate has signed its attestation.
+pub fn complete_contribution(contract_key: &str, signature: Vec<u8>) {
+    let Some(mut catalog_delta) =
+        PENDING_SIGNATURES.with(|pending| pending.borrow_mut().remove(contract_key))
+    else {
+        tracing::debug!("No pending contribution for {}", contract_key);
+        return;
     };
+    catalog_delta.attestation.signature = signature;
+    let contract_key = catalog_delta.contract_key.clone();
+    let now = catalog_delta.attestation.token_created_at;
 
     // Serialize and send catalog delta
     let mut delta_bytes = Vec::new();
@@ -157,56 +231,102 @@ pub fn contribute_entry(contract_key: String, state_bytes: Vec<u8>) {
         return;
     }
 
-    // Tokenize snippet and group by shard
-    let tokens = tokenize(&metadata.snippet);
-    let mut shard_entries: std::collections::HashMap<u8, Vec<ShardDeltaEntry>> =
-        std::collections::HashMap::new();
-
-    for word in &tokens {
-        let shard_id = shard_for_word(word, SHARD_COUNT);
-        shard_entries
-            .entry(shard_id)
-            .or_default()
-            .push(ShardDeltaEntry {
-                word: word.clone(),
-                contract_key: contract_key.clone(),
-                snippet: metadata.snippet.clone(),
-                tf_idf_score: 10000, // base score, refined by contract
-            });
-    }
+    // Postings are mined a term at a time between turns of the browser
+    wasm_bindgen_futures::spawn_local(async move {
+        // Tokenize snippet, fold repeats into term counts and positions, and group by shard
+        let tokens = ANALYZER.tokenize(&catalog_delta.snippet);
+        let total_terms = tokens.len() as u32;
+        let counts = term_counts(&tokens);
+        let positions = term_positions(&tokens);
+        let total_docs = corpus_size(&contract_key);
+        let mut shard_entries: HashMap<u8, Vec<ShardDeltaEntry>> = HashMap::new();
+
+        for (word, &count) in &counts {
+            let shard_id = shard_for_word(word, SHARD_COUNT);
+            let docs_with_term = docs_with_term(shard_id, word, &contract_key);
+            let tf_idf_score = integer_tf_idf(count, total_terms, total_docs, docs_with_term);
+            let frequency = term_frequency(count, total_terms);
+            let word_positions = positions.get(word).cloned().unwrap_or_default();
+            // Each posting carries its own PoW, bound to its payload
+            let antiflood_token = generate_antiflood_token(
+                &antiflood::posting_challenge(
+                    word,
+                    &contract_key,
+                    tf_idf_score,
+                    frequency,
+                    &word_positions,
+                ),
+                SHARD_POW_DIFFICULTY,
+            )
+            .await;
+            shard_entries
+                .entry(shard_id)
+                .or_default()
+                .push(ShardDeltaEntry {
+                    word: word.clone(),
+                    contract_key: contract_key.clone(),
+                    tf_idf_score,
+                    term_frequency: frequency,
+                    positions: word_positions,
+                    antiflood_token,
+                });
+        }
 
-    // Submit shard deltas
-    for (shard_id, entries) in shard_entries {
-        let shard_delta = ShardDelta {
-            entries,
-            antiflood_token: antiflood_token.clone(),
-        };
+        // Submit one delta per shard
+        for (shard_id, entries) in shard_entries {
+            let shard_delta = ShardDelta { entries };
 
-        let mut shard_delta_bytes = Vec::new();
-        if let Err(e) = ciborium::into_writer(&shard_delta, &mut shard_delta_bytes) {
-            tracing::error!("Failed to serialize shard delta {}: {}", shard_id, e);
-            continue;
-        }
+            let mut shard_delta_bytes = Vec::new();
+            if let Err(e) = ciborium::into_writer(&shard_delta, &mut shard_delta_bytes) {
+                tracing::error!("Failed to serialize shard delta {}: {}", shard_id, e);
+                continue;
+            }
 
-        with_current_ws(|ws| {
-            let request = ClientRequest::ContractOp(ContractRequest::Update {
-                key: placeholder_contract_key(shard_contract_key(shard_id)),
-                data: UpdateData::Delta(StateDelta::from(shard_delta_bytes.clone())),
+            with_current_ws(|ws| {
+                let request = ClientRequest::ContractOp(ContractRequest::Update {
+                    key: placeholder_contract_key(shard_contract_key(shard_id)),
+                    data: UpdateData::Delta(StateDelta::from(shard_delta_bytes.clone())),
+                });
+                send_request(ws, &request);
             });
-            send_request(ws, &request);
-        });
-    }
+        }
 
-    tracing::info!(
-        "Contributed entry {} ({} tokens across shards)",
-        contract_key,
-        tokens.len()
-    );
+        tracing::info!(
+            "Contributed entry {} ({} terms across shards)",
+            contract_key,
+            counts.len()
+        );
+
+        record_contribution(&contract_key, now, ContributionStatus::Submitted);
+    });
+}
 
-    record_contribution(&contract_key, now, ContributionStatus::Submitted);
+/// Number of documents in the index once `contract_key` is added.
+fn corpus_size(contract_key: &str) -> u32 {
+    let catalog = CATALOG_STATE.read();
+    let entries = catalog.as_ref().map(|c| &c.entries);
+    let existing = entries.map_or(0, |e| e.len() as u32);
+    if entries.is_some_and(|e| e.contains_key(contract_key)) {
+        existing
+    } else {
+        existing + 1
+    }
+}
 
-    // Store signature alongside contribution for potential future verification
-    let _ = signature;
+/// Number of documents containing `word` once `contract_key` is added,
+/// from the locally synced shard state.
+fn docs_with_term(shard_id: u8, word: &str, contract_key: &str) -> u32 {
+    let others = SHARD_STATES
+        .read()
+        .get(&shard_id)
+        .and_then(|shard| shard.index.get(word))
+        .map_or(0, |entries| {
+            entries
+                .iter()
+                .filter(|e| e.contract_key != contract_key)
+                .count() as u32
+        });
+    others + 1
 }
 
 fn record_contribution(contract_key: &str, timestamp: u64, status: ContributionStatus) {
@@ -217,106 +337,25 @@ fn record_contribution(contract_key: &str, timestamp: u64, status: ContributionS
     });
 }
 
-/// Generate a proof-of-work antiflood token.
-fn generate_antiflood_token(difficulty: u8) -> AntifloodToken {
-    let mut nonce = 0u64;
-    loop {
-        let nonce_bytes = nonce.to_le_bytes();
-        let mut hasher = Sha256::new();
-        hasher.update(nonce_bytes);
-        let hash: [u8; 32] = hasher.finalize().into();
-
-        if count_leading_zero_bits(&hash) >= difficulty {
-            return AntifloodToken {
-                nonce: nonce_bytes.to_vec(),
-                difficulty,
-            };
-        }
-        nonce += 1;
-    }
+/// Generate a proof-of-work antiflood token over the given challenge, after
+/// giving the browser a turn so a run of tokens doesn't freeze the page.
+/// Contracts recompute the challenge from the payload and re-verify the hash.
+async fn generate_antiflood_token(challenge: &[u8; 32], difficulty: u8) -> AntifloodToken {
+    yield_to_browser().await;
+    antiflood::mine_token(challenge, difficulty)
 }
 
-fn count_leading_zero_bits(hash: &[u8; 32]) -> u8 {
-    let mut count = 0u8;
-    for &byte in hash {
-        if byte == 0 {
-            count += 8;
-        } else {
-            count += byte.leading_zeros() as u8;
-            break;
+/// Resolve on the browser's next timer tick, letting it handle input and paint.
+async fn yield_to_browser() {
+    let tick = js_sys::Promise::new(&mut |resolve, _| {
+        let scheduled = web_sys::window()
+            .and_then(|w| w.set_timeout_with_callback(&resolve).ok())
+            .is_some();
+        if !scheduled {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
         }
-    }
-    count
-}
-
-/// Get or create an ed25519 keypair from localStorage.
-fn get_or_create_keypair() -> ([u8; 32], [u8; 32]) {
-    if let Some((secret, public)) = load_keypair_from_storage() {
-        return (secret, public);
-    }
-
-    // Generate new keypair
-    let signing_key = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
-    let verifying_key = signing_key.verifying_key();
-
-    let secret = signing_key.to_bytes();
-    let public = verifying_key.to_bytes();
-
-    save_keypair_to_storage(&secret, &public);
-    *CONTRIBUTOR_PUBKEY.write() = Some(public);
-
-    (secret, public)
-}
-
-/// Sign a metadata hash with the contributor's secret key.
-fn sign_attestation(secret_key: &[u8; 32], metadata_hash: &[u8; 32]) -> [u8; 64] {
-    let signing_key = ed25519_dalek::SigningKey::from_bytes(secret_key);
-    use ed25519_dalek::Signer;
-    signing_key.sign(metadata_hash).to_bytes()
-}
-
-/// Load contributor keypair from localStorage.
-pub fn load_keypair_from_storage() -> Option<([u8; 32], [u8; 32])> {
-    let window = web_sys::window()?;
-    let storage = window.local_storage().ok()??;
-    let hex = storage.get_item("contributor_keypair").ok()??;
-
-    // 64 bytes hex-encoded = 128 chars (32 secret + 32 public)
-    if hex.len() != 128 {
-        return None;
-    }
-
-    let bytes = super::hex_decode(&hex)?;
-    if bytes.len() != 64 {
-        return None;
-    }
-
-    let mut secret = [0u8; 32];
-    let mut public = [0u8; 32];
-    secret.copy_from_slice(&bytes[..32]);
-    public.copy_from_slice(&bytes[32..]);
-    Some((secret, public))
-}
-
-/// Save a keypair to localStorage (public API for settings UI).
-pub fn save_keypair(secret: &[u8; 32], public: &[u8; 32]) {
-    save_keypair_to_storage(secret, public);
-}
-
-fn save_keypair_to_storage(secret: &[u8; 32], public: &[u8; 32]) {
-    let window = match web_sys::window() {
-        Some(w) => w,
-        None => return,
-    };
-    let storage = match window.local_storage() {
-        Ok(Some(s)) => s,
-        _ => return,
-    };
-    let mut hex = String::with_capacity(128);
-    for b in secret.iter().chain(public.iter()) {
-        hex.push_str(&format!("{:02x}", b));
-    }
-    let _ = storage.set_item("contributor_keypair", &hex);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(tick).await;
 }
 
 /// Load contribution_enabled flag from localStorage.
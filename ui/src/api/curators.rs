@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use dioxus::prelude::*;
+use freenet_stdlib::client_api::{ClientRequest, ContractRequest};
+use freenet_stdlib::prelude::{
+    ContractCode, ContractContainer, ContractKey, ContractWasmAPIVersion, Parameters,
+    RelatedContracts, WrappedContract, WrappedState,
+};
+use search_common::trust::TrustListState;
+use web_sys::WebSocket;
+
+use delegate_identity::IdentityRequest;
+
+use crate::state::{
+    PublishStatus, ACTIVE_IDENTITY, CURATORS, CURATOR_LISTS, TRUST_LIST, TRUST_LIST_PUBLISH,
+};
+
+use super::contracts::trust_list_instance_id;
+use super::identity::{fetch_bundled, send_identity_request};
+use super::node_api::{send_request, with_current_ws};
+
+/// Trust-list contract WASM shipped alongside the UI in the webapp archive;
+/// the same build `TRUST_LIST_CODE_HASH` was taken from.
+const TRUST_LIST_WASM_PATH: &str = "contract_trust_list.wasm";
+
+thread_local! {
+    static TRUST_LIST_CODE: RefCell<Option<Arc<ContractCode<'static>>>> = const { RefCell::new(None) };
+}
+
+/// Subscribe to a curator's trust-list contract state with updates.
+pub fn subscribe_curator(ws: &WebSocket, owner: [u8; 32]) {
+    let key = trust_list_instance_id(&owner);
+    let request = ClientRequest::ContractOp(ContractRequest::Get {
+        key,
+        return_contract_code: false,
+        subscribe: true,
+        blocking_subscribe: false,
+    });
+    send_request(ws, &request);
+    tracing::debug!("Subscribed to trust list {}", key);
+}
+
+/// Subscribe to every followed curator's trust list.
+pub fn subscribe_curators(ws: &WebSocket) {
+    let owners: Vec<[u8; 32]> = CURATORS.read().iter().map(|c| c.owner).collect();
+    for owner in owners {
+        subscribe_curator(ws, owner);
+    }
+}
+
+/// Check if a contract key matches a followed curator's list, returning the curator's key.
+pub fn matching_curator(key: &ContractKey) -> Option<[u8; 32]> {
+    CURATORS
+        .read()
+        .iter()
+        .map(|c| c.owner)
+        .find(|owner| *key.id() == trust_list_instance_id(owner))
+}
+
+/// Handle a trust-list contract GET response: deserialize CBOR and update signals.
+/// An empty state means the curator has not published yet. A list not signed
+/// by the curator is dropped, whatever node served it.
+pub fn handle_curator_response(owner: [u8; 32], state_bytes: &[u8]) {
+    if state_bytes.is_empty() {
+        CURATOR_LISTS.write().remove(&owner);
+        return;
+    }
+    match ciborium::from_reader::<TrustListState, _>(state_bytes) {
+        Ok(list) if !delegate_identity::verify_trust_list(&list, &owner) => {
+            tracing::warn!(
+                "Ignoring trust list {} not signed by its curator",
+                trust_list_instance_id(&owner)
+            );
+        }
+        Ok(list) => {
+            tracing::debug!(
+                "Trust list {} loaded: version {}, {} endorsements",
+                trust_list_instance_id(&owner),
+                list.version,
+                list.endorsements.len()
+            );
+            CURATOR_LISTS.write().insert(owner, list);
+        }
+        Err(e) => {
+            tracing::warn!("Failed to deserialize trust list: {}", e);
+        }
+    }
+}
+
+/// Publish the local trust list as the active identity's own: the delegate
+/// signs it, then `put_trust_list` stores it in the identity's contract.
+pub fn publish_trust_list() {
+    let request = IdentityRequest::SignTrustList {
+        name: ACTIVE_IDENTITY.read().clone(),
+        // Milliseconds, so each publish supersedes the last
+        version: js_sys::Date::now() as u64,
+        endorsements: TRUST_LIST.read().clone(),
+    };
+    let status = if send_identity_request(&request) {
+        PublishStatus::Signing
+    } else {
+        PublishStatus::Failed("Identity delegate unavailable".to_string())
+    };
+    *TRUST_LIST_PUBLISH.write() = Some(status);
+}
+
+/// Put a signed list to its owner's trust-list contract, creating the
+/// contract on first publish.
+pub fn put_trust_list(list: TrustListState) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let code = match trust_list_code().await {
+            Ok(code) => code,
+            Err(e) => {
+                tracing::error!("Failed to load trust-list contract: {:?}", e);
+                fail_publish("Trust-list contract unavailable");
+                return;
+            }
+        };
+        let mut state_bytes = Vec::new();
+        if let Err(e) = ciborium::into_writer(&list, &mut state_bytes) {
+            fail_publish(&format!("CBOR serialize: {}", e));
+            return;
+        }
+        let contract = WrappedContract::new(code, Parameters::from(list.owner.to_vec()));
+        let request = ClientRequest::ContractOp(ContractRequest::Put {
+            contract: ContractContainer::Wasm(ContractWasmAPIVersion::V1(contract)),
+            state: WrappedState::new(state_bytes),
+            related_contracts: RelatedContracts::new(),
+            subscribe: false,
+            blocking_subscribe: false,
+        });
+        let sent = std::cell::Cell::new(false);
+        with_current_ws(|ws| {
+            send_request(ws, &request);
+            sent.set(true);
+        });
+        if !sent.get() {
+            fail_publish("WebSocket not open");
+            return;
+        }
+        tracing::info!(
+            "Published trust list {} version {}",
+            trust_list_instance_id(&list.owner),
+            list.version
+        );
+        *TRUST_LIST_PUBLISH.write() = Some(PublishStatus::Sent {
+            owner: list.owner,
+            version: list.version,
+        });
+    });
+}
+
+async fn trust_list_code() -> Result<Arc<ContractCode<'static>>, wasm_bindgen::JsValue> {
+    if let Some(code) = TRUST_LIST_CODE.with(|cell| cell.borrow().clone()) {
+        return Ok(code);
+    }
+    let code = Arc::new(ContractCode::from(
+        fetch_bundled(TRUST_LIST_WASM_PATH).await?,
+    ));
+    TRUST_LIST_CODE.with(|cell| *cell.borrow_mut() = Some(code.clone()));
+    Ok(code)
+}
+
+/// Fail a publish waiting on its signature or contract code; other errors
+/// leave its status alone.
+pub fn fail_publish(message: &str) {
+    let mut status = TRUST_LIST_PUBLISH.write();
+    if matches!(*status, Some(PublishStatus::Signing)) {
+        *status = Some(PublishStatus::Failed(message.to_string()));
+    }
+}
+
+/// Mark the list published once the node accepts the put to its contract.
+pub fn handle_put_response(key: &ContractKey) {
+    let mut status = TRUST_LIST_PUBLISH.write();
+    if let Some(PublishStatus::Sent { owner, version }) = *status {
+        if *key.id() == trust_list_instance_id(&owner) {
+            *status = Some(PublishStatus::Published { version });
+        }
+    }
+}
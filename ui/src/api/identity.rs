@@ -0,0 +1,295 @@
+//! Contributor identity via the identity delegate.
+//!
+//! Secret keys stay inside the delegate on the local node; the UI only ever
+//! sees public keys, signatures and passphrase-encrypted backups. The delegate can hold several named
+//! identities; the active one signs contributions.
+
+use std::cell::RefCell;
+
+use dioxus::prelude::*;
+use freenet_stdlib::client_api::{ClientRequest, DelegateRequest};
+use freenet_stdlib::prelude::{
+    ApplicationMessage, Delegate, DelegateCode, DelegateContainer, DelegateWasmAPIVersion,
+    InboundDelegateMsg, OutboundDelegateMsg, Parameters,
+};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Response, WebSocket};
+
+use delegate_identity::{IdentityParameters, IdentityRequest, IdentityResponse};
+
+use crate::state::{
+    ACTIVE_IDENTITY, CONTRIBUTION_ENABLED, CONTRIBUTOR_IDENTITIES, CONTRIBUTOR_PUBKEY,
+    IDENTITY_BACKUP, IDENTITY_ERROR,
+};
+
+use super::contracts::catalog_contract_key;
+use super::node_api::{send_request, with_current_ws};
+
+/// Delegate WASM shipped alongside the UI in the webapp archive.
+const DELEGATE_WASM_PATH: &str = "delegate_identity.wasm";
+
+/// Name of the identity created when the delegate holds none.
+pub const DEFAULT_IDENTITY: &str = "default";
+
+/// localStorage item where the UI kept its contributor key before the
+/// delegate held keys: hex of the 32-byte secret key then the public key.
+const LEGACY_KEYPAIR_ITEM: &str = "contributor_keypair";
+
+/// Name a legacy key is imported under when `DEFAULT_IDENTITY` is taken.
+const LEGACY_IDENTITY: &str = "legacy";
+
+thread_local! {
+    static IDENTITY_DELEGATE: RefCell<Option<Delegate<'static>>> = const { RefCell::new(None) };
+}
+
+/// Register the identity delegate on a fresh connection and look up the
+/// contributor identity, fetching the delegate code on first use.
+pub fn connect_delegate(ws: &WebSocket) {
+    if IDENTITY_DELEGATE.with(|cell| cell.borrow().is_some()) {
+        register_delegate(ws);
+        return;
+    }
+    wasm_bindgen_futures::spawn_local(async {
+        match fetch_bundled(DELEGATE_WASM_PATH).await {
+            Ok(code) => {
+                let delegate = Delegate::from((&DelegateCode::from(code), &delegate_parameters()));
+                IDENTITY_DELEGATE.with(|cell| *cell.borrow_mut() = Some(delegate));
+                with_current_ws(register_delegate);
+            }
+            Err(e) => tracing::error!("Failed to load identity delegate: {:?}", e),
+        }
+    });
+}
+
+/// Parameters the identity delegate is registered with: it answers only the
+/// web app this page was served from, which may also export backups.
+fn delegate_parameters() -> Parameters<'static> {
+    let apps: Vec<[u8; 32]> = webapp_instance_id().into_iter().collect();
+    Parameters::from(
+        IdentityParameters {
+            allowed_apps: apps.clone(),
+            export_apps: apps,
+        }
+        .to_bytes(),
+    )
+}
+
+/// The web container contract serving this page, from its
+/// `/v1/contract/web/<id>/` path.
+fn webapp_instance_id() -> Option<[u8; 32]> {
+    let path = web_sys::window()?.location().pathname().ok()?;
+    let id = path.split('/').skip_while(|s| *s != "web").nth(1)?;
+    bs58::decode(id).into_vec().ok()?.try_into().ok()
+}
+
+/// Fetch a file shipped alongside the UI in the webapp archive.
+pub async fn fetch_bundled(path: &str) -> Result<Vec<u8>, JsValue> {
+    let window = web_sys::window().ok_or(JsValue::from_str("no window"))?;
+    let resp_value = JsFuture::from(window.fetch_with_str(path)).await?;
+    let resp: Response = resp_value.dyn_into()?;
+    if !resp.ok() {
+        return Err(JsValue::from_str(&format!("HTTP {}", resp.status())));
+    }
+    let buf = JsFuture::from(resp.array_buffer()?).await?;
+    Ok(js_sys::Uint8Array::new(&buf).to_vec())
+}
+
+fn register_delegate(ws: &WebSocket) {
+    let Some(delegate) = IDENTITY_DELEGATE.with(|cell| cell.borrow().clone()) else {
+        return;
+    };
+    let request = ClientRequest::DelegateOp(DelegateRequest::RegisterDelegate {
+        delegate: DelegateContainer::Wasm(DelegateWasmAPIVersion::V1(delegate)),
+        cipher: DelegateRequest::DEFAULT_CIPHER,
+        nonce: DelegateRequest::DEFAULT_NONCE,
+    });
+    send_request(ws, &request);
+    tracing::info!("Registered identity delegate");
+
+    send_identity_request(&IdentityRequest::ListIdentities);
+}
+
+/// Send a request to the identity delegate. Returns false if the delegate
+/// isn't loaded yet or the WebSocket is not open.
+pub fn send_identity_request(request: &IdentityRequest) -> bool {
+    let Some(delegate) = IDENTITY_DELEGATE.with(|cell| cell.borrow().clone()) else {
+        tracing::warn!("Identity delegate not loaded yet");
+        return false;
+    };
+    let mut payload = Vec::new();
+    if let Err(e) = ciborium::into_writer(request, &mut payload) {
+        tracing::error!("Failed to serialize identity request: {}", e);
+        return false;
+    }
+
+    let message = ApplicationMessage::new(catalog_contract_key(), payload);
+    let request = ClientRequest::DelegateOp(DelegateRequest::ApplicationMessages {
+        key: delegate.key().clone(),
+        params: delegate_parameters(),
+        inbound: vec![InboundDelegateMsg::ApplicationMessage(message)],
+    });
+    let sent = std::cell::Cell::new(false);
+    with_current_ws(|ws| {
+        send_request(ws, &request);
+        sent.set(true);
+    });
+    sent.get()
+}
+
+/// Ask the delegate to generate a new identity called `name`.
+pub fn create_identity(name: String) {
+    send_identity_request(&IdentityRequest::CreateIdentity { name });
+}
+
+/// Ask the delegate to delete the identity called `name` and its key.
+pub fn delete_identity(name: String) {
+    send_identity_request(&IdentityRequest::DeleteIdentity { name });
+}
+
+/// Ask the delegate for a passphrase-encrypted backup of `name`.
+pub fn export_identity(name: String, passphrase: String) {
+    send_identity_request(&IdentityRequest::ExportIdentity { name, passphrase });
+}
+
+/// Restore a backup, optionally under a different name.
+pub fn import_identity(backup: String, passphrase: String, name: Option<String>) {
+    send_identity_request(&IdentityRequest::ImportIdentity {
+        backup,
+        passphrase,
+        name,
+    });
+}
+
+/// Sign future contributions as `name`.
+pub fn set_active_identity(name: String) {
+    save_active_identity(&name);
+    *ACTIVE_IDENTITY.write() = name;
+    sync_active_pubkey();
+}
+
+/// Load the active identity name from localStorage.
+pub fn load_active_identity() -> String {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item("active_identity").ok().flatten())
+        .unwrap_or_else(|| DEFAULT_IDENTITY.to_string())
+}
+
+fn save_active_identity(name: &str) {
+    if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = storage.set_item("active_identity", name);
+    }
+}
+
+/// Handle the identity delegate's outbound messages.
+pub fn handle_delegate_response(values: Vec<OutboundDelegateMsg>) {
+    for value in values {
+        let OutboundDelegateMsg::ApplicationMessage(message) = value else {
+            continue;
+        };
+        match ciborium::from_reader::<IdentityResponse, _>(message.payload.as_slice()) {
+            Ok(response) => handle_identity_response(response),
+            Err(e) => tracing::warn!("Failed to decode identity response: {}", e),
+        }
+    }
+}
+
+fn handle_identity_response(response: IdentityResponse) {
+    match response {
+        IdentityResponse::Identities(identities) => {
+            if let Some((secret_key, public_key)) = load_legacy_keypair() {
+                if identities.iter().any(|i| i.public_key == public_key) {
+                    remove_legacy_keypair();
+                } else {
+                    let name = if identities.iter().any(|i| i.name == DEFAULT_IDENTITY) {
+                        LEGACY_IDENTITY
+                    } else {
+                        DEFAULT_IDENTITY
+                    };
+                    send_identity_request(&IdentityRequest::ImportKey {
+                        name: name.to_string(),
+                        secret_key,
+                    });
+                }
+            } else if identities.is_empty() {
+                create_identity(DEFAULT_IDENTITY.to_string());
+            }
+            // Fall back to the first identity when the active one is gone
+            let active = ACTIVE_IDENTITY.read().clone();
+            if !identities.iter().any(|i| i.name == active) {
+                if let Some(first) = identities.first() {
+                    save_active_identity(&first.name);
+                    *ACTIVE_IDENTITY.write() = first.name.clone();
+                }
+            }
+            *CONTRIBUTOR_IDENTITIES.write() = identities;
+            *IDENTITY_ERROR.write() = None;
+            sync_active_pubkey();
+        }
+        IdentityResponse::Created { .. }
+        | IdentityResponse::Deleted { .. }
+        | IdentityResponse::Imported { .. } => {
+            send_identity_request(&IdentityRequest::ListIdentities);
+        }
+        IdentityResponse::Exported { name, backup } => {
+            *IDENTITY_ERROR.write() = None;
+            *IDENTITY_BACKUP.write() = Some((name, backup));
+        }
+        IdentityResponse::Signature {
+            contract_key,
+            signature,
+            ..
+        } => super::contribution::complete_contribution(&contract_key, signature),
+        IdentityResponse::TrustList(list) => super::curators::put_trust_list(list),
+        IdentityResponse::Error {
+            message,
+            contract_key,
+        } => {
+            tracing::warn!("Identity delegate error: {}", message);
+            match contract_key {
+                Some(contract_key) => {
+                    super::contribution::fail_contribution(&contract_key, message.clone())
+                }
+                None => super::curators::fail_publish(&message),
+            }
+            *IDENTITY_ERROR.write() = Some(message);
+        }
+        _ => {}
+    }
+}
+
+/// The contributor keypair a pre-delegate UI left in localStorage, if any.
+fn load_legacy_keypair() -> Option<([u8; 32], [u8; 32])> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    let bytes = super::hex_decode(&storage.get_item(LEGACY_KEYPAIR_ITEM).ok()??)?;
+    if bytes.len() != 64 {
+        return None;
+    }
+    Some((bytes[..32].try_into().ok()?, bytes[32..].try_into().ok()?))
+}
+
+/// Forget the legacy keypair once the delegate holds it.
+fn remove_legacy_keypair() {
+    if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = storage.remove_item(LEGACY_KEYPAIR_ITEM);
+        tracing::info!("Moved the contributor key from localStorage into the identity delegate");
+    }
+}
+
+/// Point `CONTRIBUTOR_PUBKEY` at the active identity's key.
+fn sync_active_pubkey() {
+    let active = ACTIVE_IDENTITY.read().clone();
+    let pubkey = CONTRIBUTOR_IDENTITIES
+        .read()
+        .iter()
+        .find(|i| i.name == active)
+        .map(|i| i.public_key);
+    let was_missing = CONTRIBUTOR_PUBKEY.read().is_none();
+    *CONTRIBUTOR_PUBKEY.write() = pubkey;
+    // Apps discovered before the identity was ready were skipped
+    if was_missing && pubkey.is_some() && *CONTRIBUTION_ENABLED.read() {
+        super::contribution::retrigger_contributions();
+    }
+}
@@ -1,5 +1,10 @@
+// Written by deploy-helper; kept as generated
+#[rustfmt::skip]
+mod contract_ids;
 pub mod contracts;
 pub mod contribution;
+pub mod curators;
+pub mod identity;
 pub mod node_api;
 pub mod types;
 
@@ -65,11 +70,10 @@ pub fn init() {
         }
     }
 
-    // 2. Restore contribution settings from localStorage
+    // 2. Restore contribution and ranking settings from localStorage
     *crate::state::CONTRIBUTION_ENABLED.write() = contribution::load_contribution_enabled();
-    if let Some((_, pubkey)) = contribution::load_keypair_from_storage() {
-        *crate::state::CONTRIBUTOR_PUBKEY.write() = Some(pubkey);
-    }
+    *crate::state::ACTIVE_IDENTITY.write() = identity::load_active_identity();
+    crate::search::trust::load_trust_settings();
 
     // 3. Load cached catalog/shard states from localStorage
     contracts::load_cached_states();
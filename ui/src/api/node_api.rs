@@ -84,12 +84,18 @@ pub fn connect_node_api(config: &NodeConfig) {
         // Send diagnostics immediately
         send_diagnostics_query(&ws_for_open.borrow());
 
-        // Subscribe to search index contracts (catalog + 16 shards)
+        // Subscribe to search index contracts (catalog + all shards)
         super::contracts::subscribe_catalog(&ws_for_open.borrow());
-        for shard_id in 0u8..16 {
+        for shard_id in 0..super::contracts::SHARD_COUNT {
             super::contracts::subscribe_shard(&ws_for_open.borrow(), shard_id);
         }
 
+        // Subscribe to the trust lists of followed curators
+        super::curators::subscribe_curators(&ws_for_open.borrow());
+
+        // Register the identity delegate that holds the contributor key
+        super::identity::connect_delegate(&ws_for_open.borrow());
+
         // Only start intervals once (they persist across reconnects)
         if !POLLING_STARTED.swap(true, Ordering::SeqCst) {
             start_polling_intervals();
@@ -207,6 +213,10 @@ fn handle_host_response(bytes: &[u8]) {
                 super::contracts::handle_shard_response(state.as_ref());
                 return;
             }
+            if let Some(owner) = super::curators::matching_curator(&key) {
+                super::curators::handle_curator_response(owner, state.as_ref());
+                return;
+            }
 
             let key_str = format!("{}", key);
             let contract_type =
@@ -298,6 +308,9 @@ fn handle_host_response(bytes: &[u8]) {
                 }
             }
         }
+        HostResponse::ContractResponse(ContractResponse::PutResponse { key, .. }) => {
+            super::curators::handle_put_response(&key);
+        }
         HostResponse::ContractResponse(ContractResponse::UpdateNotification { key, .. }) => {
             // A subscribed contract was updated — re-fetch full state
             if super::contracts::is_catalog_key(&key) {
@@ -306,8 +319,14 @@ fn handle_host_response(bytes: &[u8]) {
             } else if let Some(shard_id) = super::contracts::matching_shard_id(&key) {
                 tracing::debug!("Shard {} updated, re-fetching...", shard_id);
                 with_current_ws(|ws| super::contracts::subscribe_shard(ws, shard_id));
+            } else if let Some(owner) = super::curators::matching_curator(&key) {
+                tracing::debug!("Trust list updated, re-fetching...");
+                with_current_ws(|ws| super::curators::subscribe_curator(ws, owner));
             }
         }
+        HostResponse::DelegateResponse { values, .. } => {
+            super::identity::handle_delegate_response(values);
+        }
         HostResponse::Ok => {}
         _ => {
             tracing::debug!("Received unhandled response type");
@@ -328,13 +347,14 @@ fn start_polling_intervals() {
     );
     diag_callback.forget();
 
-    // Periodic re-fetch of catalog + shard states (compensates for subscription timeouts)
+    // Periodic re-fetch of index and trust-list states (compensates for subscription timeouts)
     let refetch_callback = Closure::<dyn FnMut()>::new(move || {
         with_current_ws(|ws| {
             super::contracts::subscribe_catalog(ws);
-            for shard_id in 0u8..16 {
+            for shard_id in 0..super::contracts::SHARD_COUNT {
                 super::contracts::subscribe_shard(ws, shard_id);
             }
+            super::curators::subscribe_curators(ws);
         });
     });
     let _ = window.set_interval_with_callback_and_timeout_and_arguments_0(
@@ -28,6 +28,7 @@ pub fn extract_metadata(state: &[u8]) -> Option<ExtractedMetadata> {
     let metadata_hash = hashing::metadata_hash(
         title.as_deref().unwrap_or(""),
         description.as_deref().unwrap_or(""),
+        &mini_snippet,
         &snippet,
     );
 
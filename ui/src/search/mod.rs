@@ -1,2 +1,3 @@
 pub mod query;
 pub mod ranking;
+pub mod trust;
@@ -1,113 +1,179 @@
 use std::collections::HashMap;
 
 use dioxus::prelude::*;
+use search_common::query::{evaluate, parse, Document, ParsedQuery, Posting};
 use search_common::types::Status;
-use search_common::{hashing, scoring, tokenization};
+use search_common::vocabulary::Vocabulary;
+use search_common::{hashing, scoring};
 
+use crate::api::contracts::{ANALYZER, SHARD_COUNT};
 use crate::search::ranking;
+use crate::search::trust::AttestationWeights;
 use crate::state::{SearchResult, CATALOG_STATE, SHARD_STATES};
 
-const SHARD_COUNT: u8 = 16;
 const MAX_RESULTS: usize = 50;
 
 pub struct SearchQuery {
-    pub terms: Vec<String>,
+    pub parsed: ParsedQuery,
     pub term_to_shard: HashMap<String, u8>,
 }
 
-/// Parse a raw query string into a SearchQuery with tokenized terms and shard mappings.
+/// Parse a raw query string into a SearchQuery with its AST and shard mappings.
 pub fn parse_query(raw: &str) -> SearchQuery {
-    let terms = tokenization::tokenize(raw);
-    let term_to_shard: HashMap<String, u8> = terms
+    let parsed = parse(raw, &ANALYZER);
+    let term_to_shard: HashMap<String, u8> = parsed
+        .terms
         .iter()
         .map(|t: &String| (t.clone(), hashing::shard_for_word(t, SHARD_COUNT)))
         .collect();
     SearchQuery {
-        terms,
+        parsed,
         term_to_shard,
     }
 }
 
-/// Execute a search query against the local shard and catalog state.
+/// Execute a search query against the local shard and catalog state, weighing
+/// attestations by global or personal trust as the ranking mode selects.
 pub fn execute_search(query: &SearchQuery) -> Vec<SearchResult> {
-    if query.terms.is_empty() {
+    let parsed = &query.parsed;
+    if parsed.highlight_terms.is_empty() {
         return Vec::new();
     }
+    let weights = AttestationWeights::current();
 
     let shard_states = SHARD_STATES.read();
     let catalog_state = CATALOG_STATE.read();
+    let total_docs = catalog_state.as_ref().map_or(0, |c| c.entries.len() as u32);
+
+    // Gather per contract_key: term -> posting
+    let mut candidates: HashMap<String, HashMap<String, Posting>> = HashMap::new();
+
+    let vocabulary = Vocabulary::new(shard_states.values());
+    let mut highlight_terms = parsed.highlight_terms.clone();
+
+    for term in &parsed.terms {
+        // Exact hits at full weight, then prefix/typo expansions of positive terms
+        let mut variants: Vec<(&str, u32)> = vec![(term.as_str(), 10000)];
+        if parsed.highlight_terms.contains(term) {
+            variants.extend(
+                vocabulary
+                    .expand(term)
+                    .into_iter()
+                    .map(|e| (e.term, e.weight)),
+            );
+        }
 
-    // Accumulate per contract_key: (total relevance score, first snippet seen)
-    let mut scores: HashMap<String, (u32, String)> = HashMap::new();
-
-    for term in &query.terms {
-        let shard_id = match query.term_to_shard.get(term) {
-            Some(&id) => id,
-            None => continue,
-        };
+        for (word, weight) in variants {
+            let shard_id = match query.term_to_shard.get(word) {
+                Some(&id) => id,
+                None => hashing::shard_for_word(word, SHARD_COUNT),
+            };
 
-        let shard = match shard_states.get(&shard_id) {
-            Some(s) => s,
-            None => continue,
-        };
+            let entries = match shard_states.get(&shard_id).and_then(|s| s.index.get(word)) {
+                Some(e) => e,
+                None => continue,
+            };
+            if !highlight_terms.iter().any(|t| t == word) {
+                highlight_terms.push(word.to_string());
+            }
 
-        if let Some(entries) = shard.index.get(term) {
+            // Re-weight with current document frequencies so scores submitted
+            // at different corpus sizes stay comparable
+            let idf = scoring::inverse_document_frequency(total_docs, entries.len() as u32);
             for entry in entries {
-                let acc = scores
-                    .entry(entry.contract_key.clone())
-                    .or_insert((0, String::new()));
-                acc.0 = acc.0.saturating_add(entry.tf_idf_score);
-                if acc.1.is_empty() && !entry.snippet.is_empty() {
-                    acc.1 = entry.snippet.clone();
+                let base = if entry.term_frequency > 0 {
+                    scoring::tf_idf(entry.term_frequency, idf)
+                } else {
+                    entry.tf_idf_score
+                };
+                let relevance = (base as u64 * weight as u64 / 10000) as u32;
+                let postings = candidates.entry(entry.contract_key.clone()).or_default();
+                // Keep the best-scoring variant per query term
+                if postings.get(term).is_none_or(|p| relevance > p.relevance) {
+                    postings.insert(
+                        term.clone(),
+                        Posting {
+                            relevance,
+                            positions: entry.positions.clone(),
+                        },
+                    );
                 }
             }
         }
     }
 
+    // Title/description clauses can match catalog entries without postings
+    if parsed.scans_metadata {
+        if let Some(catalog) = catalog_state.as_ref() {
+            for key in catalog.entries.keys() {
+                candidates.entry(key.clone()).or_default();
+            }
+        }
+    }
+
+    // Evaluate the query against each candidate's postings and metadata
+    let scores: HashMap<String, (u32, String)> = candidates
+        .into_iter()
+        .filter_map(|(contract_key, postings)| {
+            let variant = catalog_state
+                .as_ref()
+                .and_then(|c| c.entries.get(&contract_key))
+                .and_then(|e| weights.best_variant(e));
+            let mut doc = match variant {
+                Some(v) => Document::new(&v.title, &v.description, &ANALYZER),
+                None => Document::default(),
+            };
+            doc.postings = postings;
+            let relevance = evaluate(&parsed.root, &doc)?;
+            // Snippets live on catalog variants; the mini snippet stands in
+            // until the full one has synced
+            let snippet = match variant {
+                Some(v) if !v.snippet.is_empty() => v.snippet.clone(),
+                Some(v) => v.mini_snippet.clone(),
+                None => String::new(),
+            };
+            Some((contract_key, (relevance, snippet)))
+        })
+        .collect();
+
     let mut results: Vec<SearchResult> = scores
         .into_iter()
         .map(|(contract_key, (relevance_score, snippet))| {
-            let (title, description, status, attestation_count, rank) = if let Some(catalog) =
-                catalog_state.as_ref()
-            {
-                if let Some(entry) = catalog.entries.get(&contract_key) {
-                    let best_variant = entry.hash_variants.values().max_by_key(|v| v.total_weight);
-
-                    let (title, description) = match best_variant {
-                        Some(v) => (v.title.clone(), v.description.clone()),
-                        None => (contract_key.clone(), String::new()),
-                    };
-
-                    let att_count: u32 = entry
-                        .hash_variants
-                        .values()
-                        .map(|v| v.attestations.len() as u32)
-                        .sum();
-
-                    let weighted_att: u32 = entry
-                        .hash_variants
-                        .values()
-                        .flat_map(|v| &v.attestations)
-                        .map(|a| a.weight)
-                        .sum();
-
-                    let rank = scoring::rank_score(
-                        weighted_att,
-                        entry.version.unwrap_or(0),
-                        0, // subscribers not tracked per-entry
-                        &entry.status,
-                    );
-
-                    (title, description, entry.status.clone(), att_count, rank)
+            let (title, description, status, attestation_count, rank) =
+                if let Some(catalog) = catalog_state.as_ref() {
+                    if let Some(entry) = catalog.entries.get(&contract_key) {
+                        let best_variant = weights.best_variant(entry);
+
+                        let (title, description) = match best_variant {
+                            Some(v) => (v.title.clone(), v.description.clone()),
+                            None => (contract_key.clone(), String::new()),
+                        };
+
+                        let att_count: u32 = entry
+                            .hash_variants
+                            .values()
+                            .map(|v| v.attestations.len() as u32)
+                            .sum();
+
+                        let weighted_att = weights.of_entry(entry);
+
+                        let rank = scoring::rank_score(
+                            weighted_att,
+                            entry.version.unwrap_or(0),
+                            0, // subscribers not tracked per-entry
+                            &entry.status,
+                        );
+
+                        (title, description, entry.status.clone(), att_count, rank)
+                    } else {
+                        no_catalog_metadata(&contract_key)
+                    }
                 } else {
                     no_catalog_metadata(&contract_key)
-                }
-            } else {
-                no_catalog_metadata(&contract_key)
-            };
+                };
 
             let combined = scoring::combined_score(relevance_score, rank);
-            let highlighted = ranking::highlight_snippet(&snippet, &query.terms);
+            let highlighted = ranking::highlight_snippet(&snippet, &highlight_terms);
 
             SearchResult {
                 contract_key,
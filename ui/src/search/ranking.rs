@@ -1,6 +1,7 @@
+use crate::api::contracts::ANALYZER;
 use crate::state::SearchResult;
-use search_common::tokenization;
 use std::collections::HashSet;
+use std::ops::Range;
 
 /// Sort results by combined_score descending.
 pub fn rank_results(results: &mut [SearchResult]) {
@@ -15,52 +16,31 @@ pub fn highlight_snippet(snippet: &str, terms: &[String]) -> String {
 
     let term_set: HashSet<&str> = terms.iter().map(|s| s.as_str()).collect();
 
-    let mut result = String::with_capacity(snippet.len() + terms.len() * 13);
-    let mut first_match_pos: Option<usize> = None;
-    let mut word_start: Option<usize> = None;
-
-    let chars: Vec<char> = snippet.chars().collect();
-
-    for (i, &ch) in chars.iter().enumerate() {
-        if ch.is_alphanumeric() {
-            if word_start.is_none() {
-                word_start = Some(i);
-            }
-        } else if let Some(start) = word_start {
-            let word: String = chars[start..i].iter().collect();
-            let normalized = tokenization::normalize_token(&word);
-            if term_set.contains(normalized.as_str()) {
-                if first_match_pos.is_none() {
-                    first_match_pos = Some(result.len());
-                }
-                result.push_str("<mark>");
-                result.push_str(&word);
-                result.push_str("</mark>");
-            } else {
-                result.push_str(&word);
-            }
-            result.push(ch);
-            word_start = None;
-        } else {
-            result.push(ch);
+    // Terms are index terms, so the snippet goes through the same analyzer;
+    // overlapping matches (CJK bigrams) merge into one mark
+    let mut marks: Vec<Range<usize>> = Vec::new();
+    for token in ANALYZER.tokens(snippet) {
+        if !term_set.contains(token.term.as_str()) {
+            continue;
+        }
+        match marks.last_mut() {
+            Some(last) if token.span.start <= last.end => last.end = last.end.max(token.span.end),
+            _ => marks.push(token.span),
         }
     }
 
-    // Handle trailing word
-    if let Some(start) = word_start {
-        let word: String = chars[start..].iter().collect();
-        let normalized = tokenization::normalize_token(&word);
-        if term_set.contains(normalized.as_str()) {
-            if first_match_pos.is_none() {
-                first_match_pos = Some(result.len());
-            }
-            result.push_str("<mark>");
-            result.push_str(&word);
-            result.push_str("</mark>");
-        } else {
-            result.push_str(&word);
-        }
+    let mut result = String::with_capacity(snippet.len() + marks.len() * 13);
+    let mut first_match_pos: Option<usize> = None;
+    let mut copied = 0;
+    for mark in marks {
+        result.push_str(&snippet[copied..mark.start]);
+        first_match_pos.get_or_insert(result.len());
+        result.push_str("<mark>");
+        result.push_str(&snippet[mark.clone()]);
+        result.push_str("</mark>");
+        copied = mark.end;
     }
+    result.push_str(&snippet[copied..]);
 
     // Truncate to ~300 chars centered around first match
     if result.len() > 300 {
@@ -0,0 +1,174 @@
+//! The local trust list, followed curators, and how attestations are weighed for ranking.
+
+use std::collections::BTreeMap;
+
+use dioxus::prelude::*;
+use search_common::trust::{trust_weights, TrustLink};
+use search_common::types::{Attestation, CatalogEntry, HashVariant};
+
+use crate::state::{
+    CuratorSubscription, RankingMode, CURATORS, CURATOR_LISTS, RANKING_MODE, TRUST_LIST,
+};
+
+const TRUST_LIST_KEY: &str = "trust_list";
+const RANKING_MODE_KEY: &str = "ranking_mode";
+const CURATORS_KEY: &str = "curator_lists";
+
+/// Attestation weights under the selected ranking mode.
+pub enum AttestationWeights {
+    /// The catalog's weights, from each contributor's global trust score.
+    Global,
+    /// Each attestor's weight in the local trust graph; keys outside it weigh nothing.
+    Personal(BTreeMap<[u8; 32], u32>),
+}
+
+impl AttestationWeights {
+    pub fn current() -> Self {
+        match *RANKING_MODE.read() {
+            RankingMode::Global => AttestationWeights::Global,
+            RankingMode::Personal => {
+                let (links, endorsements) = trust_graph();
+                AttestationWeights::Personal(trust_weights(&links, &endorsements))
+            }
+        }
+    }
+
+    pub fn of(&self, att: &Attestation) -> u32 {
+        match self {
+            AttestationWeights::Global => att.weight,
+            AttestationWeights::Personal(weights) => {
+                weights.get(&att.contributor_pubkey).copied().unwrap_or(0)
+            }
+        }
+    }
+
+    pub fn of_variant(&self, variant: &HashVariant) -> u32 {
+        variant.attestations.iter().map(|a| self.of(a)).sum()
+    }
+
+    pub fn of_entry(&self, entry: &CatalogEntry) -> u32 {
+        entry
+            .hash_variants
+            .values()
+            .map(|v| self.of_variant(v))
+            .sum()
+    }
+
+    /// The variant shown for an entry: the one with the most weight behind it.
+    pub fn best_variant<'a>(&self, entry: &'a CatalogEntry) -> Option<&'a HashVariant> {
+        entry
+            .hash_variants
+            .values()
+            .max_by_key(|v| self.of_variant(v))
+    }
+}
+
+/// The local list plus a link to each followed curator whose list has loaded,
+/// with the curators' lists as their endorsements. Curators are linked by the
+/// key they were followed by, which their loaded list was verified against.
+fn trust_graph() -> (Vec<TrustLink>, BTreeMap<[u8; 32], Vec<TrustLink>>) {
+    let mut links = TRUST_LIST.read().clone();
+    let mut endorsements = BTreeMap::new();
+    let lists = CURATOR_LISTS.read();
+    for curator in CURATORS.read().iter() {
+        let Some(list) = lists.get(&curator.owner) else {
+            continue;
+        };
+        links.push(TrustLink {
+            pubkey: curator.owner,
+            weight: curator.weight,
+            depth: curator.depth.max(1),
+        });
+        endorsements.insert(curator.owner, list.endorsements.clone());
+    }
+    (links, endorsements)
+}
+
+/// Trust `link.pubkey`, replacing any earlier link to it.
+pub fn add_trust_link(link: TrustLink) {
+    let mut list = TRUST_LIST.write();
+    list.retain(|l| l.pubkey != link.pubkey);
+    list.push(link);
+    save_trust_list(&list);
+}
+
+pub fn remove_trust_link(pubkey: [u8; 32]) {
+    let mut list = TRUST_LIST.write();
+    list.retain(|l| l.pubkey != pubkey);
+    save_trust_list(&list);
+}
+
+fn save_trust_list(list: &[TrustLink]) {
+    if let Ok(json) = serde_json::to_string(list) {
+        save(TRUST_LIST_KEY, &json);
+    }
+}
+
+/// Follow a curator's trust list, replacing any earlier subscription to it.
+pub fn add_curator(curator: CuratorSubscription) {
+    let owner = curator.owner;
+    let mut curators = CURATORS.write();
+    curators.retain(|c| c.owner != owner);
+    curators.push(curator);
+    save_curators(&curators);
+    drop(curators);
+    crate::api::node_api::with_current_ws(|ws| crate::api::curators::subscribe_curator(ws, owner));
+}
+
+pub fn remove_curator(owner: [u8; 32]) {
+    let mut curators = CURATORS.write();
+    curators.retain(|c| c.owner != owner);
+    save_curators(&curators);
+    CURATOR_LISTS.write().remove(&owner);
+}
+
+fn save_curators(curators: &[CuratorSubscription]) {
+    if let Ok(json) = serde_json::to_string(curators) {
+        save(CURATORS_KEY, &json);
+    }
+}
+
+pub fn set_ranking_mode(mode: RankingMode) {
+    let value = match mode {
+        RankingMode::Global => "global",
+        RankingMode::Personal => "personal",
+    };
+    *RANKING_MODE.write() = mode;
+    save(RANKING_MODE_KEY, value);
+}
+
+/// Restore the trust list, followed curators and ranking mode from localStorage.
+pub fn load_trust_settings() {
+    let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) else {
+        return;
+    };
+    if let Some(list) = storage
+        .get_item(TRUST_LIST_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<Vec<TrustLink>>(&json).ok())
+    {
+        *TRUST_LIST.write() = list;
+    }
+    if let Some(curators) = storage
+        .get_item(CURATORS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<Vec<CuratorSubscription>>(&json).ok())
+    {
+        *CURATORS.write() = curators;
+    }
+    if let Ok(Some(mode)) = storage.get_item(RANKING_MODE_KEY) {
+        *RANKING_MODE.write() = if mode == "personal" {
+            RankingMode::Personal
+        } else {
+            RankingMode::Global
+        };
+    }
+}
+
+fn save(key: &str, value: &str) {
+    if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = storage.set_item(key, value);
+    }
+}
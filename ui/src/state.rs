@@ -2,7 +2,9 @@
 
 use std::collections::{HashMap, VecDeque};
 
+use delegate_identity::IdentityInfo;
 use dioxus::prelude::*;
+use search_common::trust::{TrustLink, TrustListState};
 use search_common::types::{CatalogState, ShardState, Status};
 use serde::{Deserialize, Serialize};
 
@@ -38,6 +40,42 @@ pub enum DiscoveryPhase {
     Complete,
 }
 
+/// Whose trust weighs attestations when ranking.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RankingMode {
+    /// Contributors' catalog trust scores, the same for everyone
+    #[default]
+    Global,
+    /// The local trust list
+    Personal,
+}
+
+/// A followed curator's trust list, trusted like a link to its owner.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CuratorSubscription {
+    /// The curator's public key, which their trust-list contract is parameterized by
+    pub owner: [u8; 32],
+    pub weight: u32,
+    /// Hops to follow from the curator, at least 1 so their list counts
+    pub depth: u8,
+}
+
+/// Where publishing the user's own trust list has got to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PublishStatus {
+    /// Waiting for the identity delegate to sign the list
+    Signing,
+    /// Put to the owner's trust-list contract, not yet acknowledged
+    Sent {
+        owner: [u8; 32],
+        version: u64,
+    },
+    Published {
+        version: u64,
+    },
+    Failed(String),
+}
+
 #[derive(Clone, Debug)]
 pub struct SearchResult {
     pub contract_key: String,
@@ -103,7 +141,7 @@ pub static SHARD_STATES: GlobalSignal<HashMap<u8, ShardState>> = Global::new(Has
 pub static SHARDS_AVAILABLE: GlobalSignal<u8> = Global::new(|| 0);
 
 /// Total number of shards
-pub static SHARDS_TOTAL: GlobalSignal<u8> = Global::new(|| 16);
+pub static SHARDS_TOTAL: GlobalSignal<u8> = Global::new(|| crate::api::contracts::SHARD_COUNT);
 
 /// Full-text search results
 pub static SEARCH_RESULTS: GlobalSignal<Vec<SearchResult>> = Global::new(Vec::new);
@@ -114,5 +152,34 @@ pub static CONTRIBUTION_ENABLED: GlobalSignal<bool> = Global::new(|| false);
 /// History of contribution attempts
 pub static CONTRIBUTION_HISTORY: GlobalSignal<Vec<ContributionRecord>> = Global::new(Vec::new);
 
-/// Contributor's public key (if generated)
+/// Active contributor identity's public key (once the identity delegate reports it)
 pub static CONTRIBUTOR_PUBKEY: GlobalSignal<Option<[u8; 32]>> = Global::new(|| None);
+
+/// Identities held by the identity delegate
+pub static CONTRIBUTOR_IDENTITIES: GlobalSignal<Vec<IdentityInfo>> = Global::new(Vec::new);
+
+/// Name of the identity that signs contributions
+pub static ACTIVE_IDENTITY: GlobalSignal<String> =
+    Global::new(|| crate::api::identity::DEFAULT_IDENTITY.to_string());
+
+/// Last error reported by the identity delegate
+pub static IDENTITY_ERROR: GlobalSignal<Option<String>> = Global::new(|| None);
+
+/// Most recent encrypted backup as (identity name, backup text)
+pub static IDENTITY_BACKUP: GlobalSignal<Option<(String, String)>> = Global::new(|| None);
+
+/// Keys the user trusts, for personal ranking
+pub static TRUST_LIST: GlobalSignal<Vec<TrustLink>> = Global::new(Vec::new);
+
+/// Whether results rank by global or personal trust
+pub static RANKING_MODE: GlobalSignal<RankingMode> = Global::new(RankingMode::default);
+
+/// Curators' trust lists the user follows
+pub static CURATORS: GlobalSignal<Vec<CuratorSubscription>> = Global::new(Vec::new);
+
+/// Latest verified list of each followed curator, by curator key
+pub static CURATOR_LISTS: GlobalSignal<HashMap<[u8; 32], TrustListState>> =
+    Global::new(HashMap::new);
+
+/// Progress of publishing the trust list as the active identity's own
+pub static TRUST_LIST_PUBLISH: GlobalSignal<Option<PublishStatus>> = Global::new(|| None);
@@ -7,6 +7,7 @@ use dioxus::prelude::*;
 use super::app_card::AppCard;
 use search_common::types::CatalogState;
 
+use crate::search::trust::AttestationWeights;
 use crate::state::{
     AppEntry, ContractType, DiscoveryPhase, APP_CATALOG, CATALOG_STATE, CONTRACT_TYPES,
     DISCOVERY_PHASE, NODE_CONNECTED, SEARCH_QUERY,
@@ -38,8 +39,9 @@ pub fn AppDirectory() -> Element {
     }
 
     // Deduplicate: group by title, keep the best entry per title
+    let weights = AttestationWeights::current();
     let catalog_state = CATALOG_STATE.read();
-    let entries = deduplicate_by_title(entries, &catalog_state);
+    let entries = deduplicate_by_title(entries, &catalog_state, &weights);
     drop(catalog_state);
 
     // Apply search
@@ -102,7 +104,7 @@ pub fn AppDirectory() -> Element {
                             let catalog_state = CATALOG_STATE.read();
                             let (cat_desc, status_str, att_count) = if let Some(ref cs) = *catalog_state {
                                 if let Some(cat_entry) = cs.entries.get(key) {
-                                    let best = cat_entry.hash_variants.values().max_by_key(|v| v.total_weight);
+                                    let best = weights.best_variant(cat_entry);
                                     let desc = best.map(|v| v.description.clone());
                                     let status = format!("{:?}", cat_entry.status);
                                     let atts: u32 = cat_entry.hash_variants.values()
@@ -147,12 +149,13 @@ pub fn AppDirectory() -> Element {
 
 /// Group entries by title and keep only the best entry per app.
 ///
-/// Ranking: catalog attestations (network-wide signal) > state size
-/// (larger = more complete) > version (tiebreaker).
+/// Ranking: catalog attestation weight (network-wide signal, weighed by global
+/// or personal trust) > state size (larger = more complete) > version (tiebreaker).
 /// Subscribers are NOT used — they only reflect direct peers, not the network.
 fn deduplicate_by_title(
     entries: Vec<(String, Option<AppEntry>)>,
     catalog_state: &Option<CatalogState>,
+    weights: &AttestationWeights,
 ) -> Vec<(String, Option<AppEntry>)> {
     let mut by_title: HashMap<String, (String, AppEntry)> = HashMap::new();
     let mut no_title: Vec<(String, Option<AppEntry>)> = Vec::new();
@@ -171,13 +174,13 @@ fn deduplicate_by_title(
         if let Some(existing) = by_title.get(&title_lower) {
             let new_content = has_content(&e);
             let old_content = has_content(&existing.1);
-            let new_atts = attestation_count(catalog_state, &key);
-            let old_atts = attestation_count(catalog_state, &existing.0);
+            let new_atts = attestation_weight(catalog_state, &key, weights);
+            let old_atts = attestation_weight(catalog_state, &existing.0, weights);
             let new_size = e.size_bytes.unwrap_or(0);
             let old_size = existing.1.size_bytes.unwrap_or(0);
             let new_ver = e.version.unwrap_or(0);
             let old_ver = existing.1.version.unwrap_or(0);
-            // Working content (non-blank page) wins first, then attestations, size, version
+            // Working content (non-blank page) wins first, then attestation weight, size, version
             let better = (new_content && !old_content)
                 || (new_content == old_content && new_atts > old_atts)
                 || (new_content == old_content && new_atts == old_atts && new_size > old_size)
@@ -209,16 +212,15 @@ fn has_content(entry: &AppEntry) -> bool {
         .unwrap_or(false)
 }
 
-/// Total attestation count for a contract key across all hash variants.
-fn attestation_count(catalog_state: &Option<CatalogState>, key: &str) -> u32 {
+/// Total attestation weight for a contract key across all hash variants.
+fn attestation_weight(
+    catalog_state: &Option<CatalogState>,
+    key: &str,
+    weights: &AttestationWeights,
+) -> u32 {
     catalog_state
         .as_ref()
         .and_then(|cs| cs.entries.get(key))
-        .map(|e| {
-            e.hash_variants
-                .values()
-                .map(|v| v.attestations.len() as u32)
-                .sum()
-        })
+        .map(|e| weights.of_entry(e))
         .unwrap_or(0)
 }
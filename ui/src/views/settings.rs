@@ -1,27 +1,82 @@
 #![allow(non_snake_case)]
 
 use dioxus::prelude::*;
+use search_common::trust::{TrustLink, MAX_TRUST_DEPTH, MAX_TRUST_WEIGHT};
 
+use crate::search::trust;
 use crate::state::{
-    ContributionStatus, CONTRIBUTION_ENABLED, CONTRIBUTION_HISTORY, CONTRIBUTOR_PUBKEY,
+    ContributionStatus, CuratorSubscription, PublishStatus, RankingMode, ACTIVE_IDENTITY,
+    CATALOG_STATE, CONTRIBUTION_ENABLED, CONTRIBUTION_HISTORY, CONTRIBUTOR_IDENTITIES, CURATORS,
+    CURATOR_LISTS, IDENTITY_BACKUP, IDENTITY_ERROR, RANKING_MODE, TRUST_LIST, TRUST_LIST_PUBLISH,
 };
 
 #[component]
 pub fn SettingsPanel() -> Element {
     let enabled = *CONTRIBUTION_ENABLED.read();
-    let pubkey = *CONTRIBUTOR_PUBKEY.read();
     let history = CONTRIBUTION_HISTORY.read();
+    let error = IDENTITY_ERROR.read().clone();
+    let backup = IDENTITY_BACKUP.read().clone();
+    let mut new_identity = use_signal(String::new);
+    let mut passphrase = use_signal(String::new);
+    let mut import_text = use_signal(String::new);
+    let mut import_name = use_signal(String::new);
+    let personal = *RANKING_MODE.read() == RankingMode::Personal;
+    let trust_list = TRUST_LIST.read().clone();
+    let publish_status = TRUST_LIST_PUBLISH
+        .read()
+        .as_ref()
+        .map(|status| match status {
+            PublishStatus::Signing => "Signing...".to_string(),
+            PublishStatus::Sent { version, .. } => format!("Version {} sent", version),
+            PublishStatus::Published { version } => format!("Version {} published", version),
+            PublishStatus::Failed(e) => format!("Failed: {}", e),
+        });
+    let mut trust_pubkey = use_signal(String::new);
+    let mut trust_weight = use_signal(|| "50".to_string());
+    let mut trust_depth = use_signal(|| "0".to_string());
+    let mut trust_error = use_signal(|| None::<String>);
+    let mut curator_key = use_signal(String::new);
+    let mut curator_weight = use_signal(|| "50".to_string());
+    let mut curator_depth = use_signal(|| "1".to_string());
+    let mut curator_error = use_signal(|| None::<String>);
 
-    let pubkey_display = pubkey
-        .map(|k| {
-            let hex = k.iter().map(|b| format!("{:02x}", b)).collect::<String>();
-            if hex.len() > 16 {
-                format!("{}...", &hex[..16])
-            } else {
-                hex
-            }
-        })
-        .unwrap_or_else(|| "Not generated".to_string());
+    // Each followed curator with what their latest list holds, once loaded
+    let curators: Vec<(CuratorSubscription, String, String)> = {
+        let lists = CURATOR_LISTS.read();
+        CURATORS
+            .read()
+            .iter()
+            .map(|curator| {
+                let list = match lists.get(&curator.owner) {
+                    Some(l) => format!("v{}, {} endorsed", l.version, l.endorsements.len()),
+                    None => "not loaded".to_string(),
+                };
+                (curator.clone(), pubkey_display(&curator.owner), list)
+            })
+            .collect()
+    };
+
+    // Each identity's reputation as the catalog currently sees it
+    let active = ACTIVE_IDENTITY.read().clone();
+    let identities: Vec<(String, String, u32, u32, bool)> = {
+        let catalog = CATALOG_STATE.read();
+        CONTRIBUTOR_IDENTITIES
+            .read()
+            .iter()
+            .map(|identity| {
+                let score = catalog
+                    .as_ref()
+                    .and_then(|c| c.contributors.get(&identity.public_key));
+                (
+                    identity.name.clone(),
+                    pubkey_display(&identity.public_key),
+                    score.map_or(0, |s| s.trust_score),
+                    score.map_or(0, |s| s.total_contributions),
+                    identity.name == active,
+                )
+            })
+            .collect()
+    };
 
     rsx! {
         div { class: "settings-panel",
@@ -60,34 +115,308 @@ pub fn SettingsPanel() -> Element {
             }
 
             div { class: "settings-section",
-                h2 { "Identity" }
-
-                div { style: "margin-bottom: 0.5rem;",
-                    span { "Public key: " }
-                    span { class: "settings-pubkey", "{pubkey_display}" }
-                }
-
-                button {
-                    class: "clear-cache-btn",
-                    onclick: move |_| {
-                        // Remove old keypair
-                        if let Some(storage) = web_sys::window()
-                            .and_then(|w| w.local_storage().ok())
-                            .flatten()
-                        {
-                            let _ = storage.remove_item("contributor_keypair");
+                h2 { "Identities" }
+
+                if identities.is_empty() {
+                    p { class: "text-secondary", style: "font-size: 0.8rem;",
+                        "Waiting for the identity delegate..."
+                    }
+                }
+
+                ul { class: "identity-list",
+                    for (name, pubkey, trust_score, total_contributions, active) in identities {
+                        IdentityRow {
+                            key: "{name}",
+                            name,
+                            pubkey,
+                            trust_score,
+                            total_contributions,
+                            active,
+                            passphrase: passphrase.read().clone(),
+                        }
+                    }
+                }
+
+                div { class: "identity-create",
+                    input {
+                        class: "identity-name-input",
+                        r#type: "text",
+                        placeholder: "New identity name",
+                        value: "{new_identity}",
+                        oninput: move |e| new_identity.set(e.value()),
+                    }
+                    button {
+                        class: "clear-cache-btn",
+                        onclick: move |_| {
+                            let name = new_identity.read().trim().to_string();
+                            if !name.is_empty() {
+                                crate::api::identity::create_identity(name);
+                                new_identity.set(String::new());
+                            }
+                        },
+                        "Create identity"
+                    }
+                }
+
+                h3 { "Backup" }
+                p { class: "text-secondary", style: "font-size: 0.8rem;",
+                    "Backups are encrypted with the passphrase below. Keep both to move an identity and its reputation to another browser or node."
+                }
+                input {
+                    class: "identity-name-input",
+                    r#type: "password",
+                    placeholder: "Backup passphrase",
+                    value: "{passphrase}",
+                    oninput: move |e| passphrase.set(e.value()),
+                }
+
+                if let Some((name, text)) = backup {
+                    div { class: "identity-backup",
+                        p { class: "text-secondary", style: "font-size: 0.8rem;",
+                            "Backup of \"{name}\":"
+                        }
+                        textarea {
+                            class: "identity-backup-text",
+                            readonly: true,
+                            rows: 3,
+                            value: "{text}",
+                        }
+                        button {
+                            class: "clear-cache-btn",
+                            onclick: {
+                                let text = text.clone();
+                                move |_| {
+                                    if let Some(window) = web_sys::window() {
+                                        let _ = window.navigator().clipboard().write_text(&text);
+                                    }
+                                }
+                            },
+                            "Copy"
+                        }
+                        button {
+                            class: "clear-cache-btn",
+                            onclick: move |_| *IDENTITY_BACKUP.write() = None,
+                            "Done"
+                        }
+                    }
+                }
+
+                div { class: "identity-backup",
+                    textarea {
+                        class: "identity-backup-text",
+                        rows: 3,
+                        placeholder: "Paste a backup (fse-identity:...) to import",
+                        value: "{import_text}",
+                        oninput: move |e| import_text.set(e.value()),
+                    }
+                    div { class: "identity-create",
+                        input {
+                            class: "identity-name-input",
+                            r#type: "text",
+                            placeholder: "Name (optional)",
+                            value: "{import_name}",
+                            oninput: move |e| import_name.set(e.value()),
                         }
-                        // Generate and display new keypair immediately
-                        let signing_key =
-                            ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
-                        let verifying_key = signing_key.verifying_key();
-                        let secret = signing_key.to_bytes();
-                        let public = verifying_key.to_bytes();
-                        // Save to localStorage
-                        crate::api::contribution::save_keypair(&secret, &public);
-                        *CONTRIBUTOR_PUBKEY.write() = Some(public);
-                    },
-                    "Generate new identity"
+                        button {
+                            class: "clear-cache-btn",
+                            onclick: move |_| {
+                                let text = import_text.read().trim().to_string();
+                                let phrase = passphrase.read().clone();
+                                if text.is_empty() || phrase.is_empty() {
+                                    *IDENTITY_ERROR.write() =
+                                        Some("Paste a backup and enter its passphrase".to_string());
+                                    return;
+                                }
+                                let name = import_name.read().trim().to_string();
+                                crate::api::identity::import_identity(
+                                    text,
+                                    phrase,
+                                    (!name.is_empty()).then_some(name),
+                                );
+                                import_text.set(String::new());
+                                import_name.set(String::new());
+                            },
+                            "Import identity"
+                        }
+                    }
+                }
+
+                if let Some(error) = error {
+                    p { class: "identity-error", "{error}" }
+                }
+            }
+
+            div { class: "settings-section",
+                h2 { "Ranking" }
+
+                label { class: "settings-toggle",
+                    input {
+                        r#type: "checkbox",
+                        checked: personal,
+                        onchange: move |e: Event<FormData>| {
+                            trust::set_ranking_mode(if e.checked() {
+                                RankingMode::Personal
+                            } else {
+                                RankingMode::Global
+                            });
+                        },
+                    }
+                    span { "Personal ranking (web of trust)" }
+                }
+
+                p { class: "text-secondary", style: "font-size: 0.8rem;",
+                    "When enabled, attestations count by how much you trust their contributors instead of by catalog reputation. Contributors you don't trust count for nothing."
+                }
+
+                ul { class: "identity-list",
+                    for link in trust_list {
+                        li { key: "{pubkey_display(&link.pubkey)}", class: "identity-row",
+                            span { class: "settings-pubkey", "{pubkey_display(&link.pubkey)}" }
+                            span { class: "text-secondary",
+                                "weight {link.weight} · depth {link.depth}"
+                            }
+                            button {
+                                class: "clear-cache-btn",
+                                onclick: move |_| trust::remove_trust_link(link.pubkey),
+                                "Remove"
+                            }
+                        }
+                    }
+                }
+
+                div { class: "identity-create",
+                    input {
+                        class: "identity-name-input",
+                        r#type: "text",
+                        placeholder: "Contributor public key (hex)",
+                        value: "{trust_pubkey}",
+                        oninput: move |e| trust_pubkey.set(e.value()),
+                    }
+                    input {
+                        class: "identity-name-input trust-number-input",
+                        r#type: "number",
+                        min: "1",
+                        max: "{MAX_TRUST_WEIGHT}",
+                        title: "Weight",
+                        value: "{trust_weight}",
+                        oninput: move |e| trust_weight.set(e.value()),
+                    }
+                    input {
+                        class: "identity-name-input trust-number-input",
+                        r#type: "number",
+                        min: "0",
+                        max: "{MAX_TRUST_DEPTH}",
+                        title: "Transitive depth",
+                        value: "{trust_depth}",
+                        oninput: move |e| trust_depth.set(e.value()),
+                    }
+                    button {
+                        class: "clear-cache-btn",
+                        onclick: move |_| {
+                            match parse_trust_link(
+                                &trust_pubkey.read(),
+                                &trust_weight.read(),
+                                &trust_depth.read(),
+                            ) {
+                                Ok(link) => {
+                                    trust::add_trust_link(link);
+                                    trust_pubkey.set(String::new());
+                                    trust_error.set(None);
+                                }
+                                Err(msg) => trust_error.set(Some(msg.to_string())),
+                            }
+                        },
+                        "Trust"
+                    }
+                }
+
+                if let Some(error) = trust_error.read().clone() {
+                    p { class: "identity-error", "{error}" }
+                }
+
+                div { class: "identity-create",
+                    button {
+                        class: "clear-cache-btn",
+                        onclick: move |_| crate::api::curators::publish_trust_list(),
+                        "Publish as curator"
+                    }
+                    if let Some(status) = publish_status {
+                        span { class: "text-secondary", "{status}" }
+                    }
+                }
+                p { class: "text-secondary", style: "font-size: 0.8rem;",
+                    "Publishes the keys above as {active}'s trust list, signed in the identity delegate. Others follow it by {active}'s public key."
+                }
+
+                h3 { "Curators" }
+                p { class: "text-secondary", style: "font-size: 0.8rem;",
+                    "Follow a curator's published trust list by their public key. The curator is trusted with the weight below, and the keys they endorse through them."
+                }
+
+                ul { class: "identity-list",
+                    for (curator, id, list) in curators {
+                        li { key: "{id}", class: "identity-row",
+                            span { class: "settings-pubkey", "{id}" }
+                            span { class: "text-secondary",
+                                "weight {curator.weight} · depth {curator.depth} · {list}"
+                            }
+                            button {
+                                class: "clear-cache-btn",
+                                onclick: move |_| trust::remove_curator(curator.owner),
+                                "Unfollow"
+                            }
+                        }
+                    }
+                }
+
+                div { class: "identity-create",
+                    input {
+                        class: "identity-name-input",
+                        r#type: "text",
+                        placeholder: "Curator public key (hex)",
+                        value: "{curator_key}",
+                        oninput: move |e| curator_key.set(e.value()),
+                    }
+                    input {
+                        class: "identity-name-input trust-number-input",
+                        r#type: "number",
+                        min: "1",
+                        max: "{MAX_TRUST_WEIGHT}",
+                        title: "Weight",
+                        value: "{curator_weight}",
+                        oninput: move |e| curator_weight.set(e.value()),
+                    }
+                    input {
+                        class: "identity-name-input trust-number-input",
+                        r#type: "number",
+                        min: "1",
+                        max: "{MAX_TRUST_DEPTH}",
+                        title: "Transitive depth",
+                        value: "{curator_depth}",
+                        oninput: move |e| curator_depth.set(e.value()),
+                    }
+                    button {
+                        class: "clear-cache-btn",
+                        onclick: move |_| {
+                            match parse_curator(
+                                &curator_key.read(),
+                                &curator_weight.read(),
+                                &curator_depth.read(),
+                            ) {
+                                Ok(curator) => {
+                                    trust::add_curator(curator);
+                                    curator_key.set(String::new());
+                                    curator_error.set(None);
+                                }
+                                Err(msg) => curator_error.set(Some(msg.to_string())),
+                            }
+                        },
+                        "Follow"
+                    }
+                }
+
+                if let Some(error) = curator_error.read().clone() {
+                    p { class: "identity-error", "{error}" }
                 }
             }
 
@@ -122,6 +451,138 @@ pub fn SettingsPanel() -> Element {
     }
 }
 
+/// One delegate identity: select it as active, see its catalog reputation,
+/// export it under `passphrase`, or delete it.
+#[component]
+fn IdentityRow(
+    name: String,
+    pubkey: String,
+    trust_score: u32,
+    total_contributions: u32,
+    active: bool,
+    passphrase: String,
+) -> Element {
+    let select_name = name.clone();
+    let export_name = name.clone();
+    let delete_name = name.clone();
+
+    rsx! {
+        li { class: if active { "identity-row active" } else { "identity-row" },
+            label { class: "settings-toggle",
+                input {
+                    r#type: "radio",
+                    name: "active-identity",
+                    checked: active,
+                    onchange: move |_| crate::api::identity::set_active_identity(select_name.clone()),
+                }
+                span { "{name}" }
+            }
+            span { class: "settings-pubkey", "{pubkey}" }
+            span { class: "text-secondary",
+                "trust {trust_score} · {total_contributions} contributions"
+            }
+            button {
+                class: "clear-cache-btn",
+                onclick: move |_| {
+                    if passphrase.is_empty() {
+                        *IDENTITY_ERROR.write() =
+                            Some("Enter a backup passphrase first".to_string());
+                    } else {
+                        crate::api::identity::export_identity(
+                            export_name.clone(),
+                            passphrase.clone(),
+                        );
+                    }
+                },
+                "Export"
+            }
+            button {
+                class: "clear-cache-btn",
+                onclick: move |_| {
+                    // Deleting the key gives up its reputation for good
+                    let confirmed = web_sys::window()
+                        .and_then(|w| {
+                            w.confirm_with_message(&format!(
+                                "Delete identity \"{}\"? Its key and reputation cannot be recovered.",
+                                delete_name
+                            ))
+                            .ok()
+                        })
+                        .unwrap_or(false);
+                    if confirmed {
+                        crate::api::identity::delete_identity(delete_name.clone());
+                    }
+                },
+                "Delete"
+            }
+        }
+    }
+}
+
+/// A trust link from the form's pubkey hex, weight, and depth fields.
+fn parse_trust_link(pubkey: &str, weight: &str, depth: &str) -> Result<TrustLink, &'static str> {
+    let pubkey: [u8; 32] = Some(pubkey.trim())
+        .filter(|hex| hex.is_ascii())
+        .and_then(crate::api::hex_decode)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Public key must be 64 hex characters")?;
+    let weight: u32 = weight
+        .trim()
+        .parse()
+        .ok()
+        .filter(|w| (1..=MAX_TRUST_WEIGHT).contains(w))
+        .ok_or("Weight must be between 1 and 100")?;
+    let depth: u8 = depth
+        .trim()
+        .parse()
+        .ok()
+        .filter(|d| *d <= MAX_TRUST_DEPTH)
+        .ok_or("Depth must be between 0 and 3")?;
+    Ok(TrustLink {
+        pubkey,
+        weight,
+        depth,
+    })
+}
+
+/// A curator subscription from the form's pubkey hex, weight, and depth fields.
+fn parse_curator(
+    owner: &str,
+    weight: &str,
+    depth: &str,
+) -> Result<CuratorSubscription, &'static str> {
+    let owner: [u8; 32] = Some(owner.trim())
+        .filter(|hex| hex.is_ascii())
+        .and_then(crate::api::hex_decode)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Public key must be 64 hex characters")?;
+    let weight: u32 = weight
+        .trim()
+        .parse()
+        .ok()
+        .filter(|w| (1..=MAX_TRUST_WEIGHT).contains(w))
+        .ok_or("Weight must be between 1 and 100")?;
+    let depth: u8 = depth
+        .trim()
+        .parse()
+        .ok()
+        .filter(|d| (1..=MAX_TRUST_DEPTH).contains(d))
+        .ok_or("Depth must be between 1 and 3")?;
+    Ok(CuratorSubscription {
+        owner,
+        weight,
+        depth,
+    })
+}
+
+fn pubkey_display(pubkey: &[u8; 32]) -> String {
+    let hex = pubkey
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("{}...", &hex[..16])
+}
+
 use super::truncate_key;
 
 fn relative_time(timestamp_ms: u64) -> String {